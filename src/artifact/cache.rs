@@ -0,0 +1,133 @@
+//! On-disk cache of downloaded artifacts, so that tests don't download the same wasm files on
+//! every run.
+//!
+//! Wasm files are stored by their sha256 hash, and shared between URL and chain downloads.
+//! Chain downloads are also indexed by RPC endpoint and code ID, and by RPC endpoint and contract
+//! address. Set [`OFFLINE_ENV`] to only serve artifacts from the cache, e.g. in CI when the
+//! public RPC endpoints are down.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use sha2::{Digest, Sha256};
+
+use super::ArtifactError;
+
+/// Environment variable to set the cache directory.
+pub const CACHE_DIR_ENV: &str = "CW_IT_CACHE_DIR";
+
+/// Cache directory used if [`CACHE_DIR_ENV`] is not set, relative to the working directory.
+pub const DEFAULT_CACHE_DIR: &str = "target/cw-it-cache";
+
+/// Environment variable that disables downloads if set to `1` or `true`.
+pub const OFFLINE_ENV: &str = "CW_IT_OFFLINE";
+
+/// Returns the cache directory, from [`CACHE_DIR_ENV`] or [`DEFAULT_CACHE_DIR`].
+pub fn cache_dir() -> PathBuf {
+    env::var_os(CACHE_DIR_ENV)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_CACHE_DIR))
+}
+
+/// Returns true if [`OFFLINE_ENV`] is set.
+pub fn is_offline() -> bool {
+    matches!(env::var(OFFLINE_ENV).as_deref(), Ok("1") | Ok("true"))
+}
+
+/// Returns the lowercase hex encoded sha256 hash of `bytes`.
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+/// A cache directory, and whether artifacts that are not cached may be downloaded.
+#[derive(Clone, Debug)]
+pub(crate) struct Cache {
+    pub dir: PathBuf,
+    pub offline: bool,
+}
+
+impl Cache {
+    /// Returns the cache configured with [`CACHE_DIR_ENV`] and [`OFFLINE_ENV`].
+    pub fn from_env() -> Self {
+        Self {
+            dir: cache_dir(),
+            offline: is_offline(),
+        }
+    }
+
+    /// Returns [`ArtifactError::Offline`] if `artifact` may not be downloaded.
+    pub fn check_online(&self, artifact: &str) -> Result<(), ArtifactError> {
+        if self.offline {
+            return Err(ArtifactError::Offline(artifact.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Returns the cached file `key`, or `None` if it isn't cached.
+    pub fn read(&self, key: &str) -> Option<Vec<u8>> {
+        fs::read(self.dir.join(key)).ok()
+    }
+
+    /// Returns the cached file `key` as a string.
+    #[cfg(feature = "chain-download")]
+    pub fn read_string(&self, key: &str) -> Option<String> {
+        self.read(key)
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+    }
+
+    /// Stores `bytes` as the file `key`. The file is written to a temporary file first, so that
+    /// concurrent tests never read a partially written file.
+    pub fn write(&self, key: &str, bytes: &[u8]) -> io::Result<()> {
+        write(&self.dir, key, bytes)
+    }
+
+    /// Returns the cached file with the hex encoded sha256 hash `sha256`, if its content still
+    /// matches the hash.
+    pub fn read_blob(&self, sha256: &str) -> Option<Vec<u8>> {
+        let sha256 = sha256.to_lowercase();
+        self.read(&blob_key(&sha256))
+            .filter(|bytes| sha256_hex(bytes) == sha256)
+    }
+
+    /// Stores `bytes` by their hash and returns the hex encoded hash.
+    pub fn write_blob(&self, bytes: &[u8]) -> io::Result<String> {
+        let sha256 = sha256_hex(bytes);
+        self.write(&blob_key(&sha256), bytes)?;
+        Ok(sha256)
+    }
+}
+
+#[cfg(test)]
+impl Cache {
+    /// Returns an empty cache in a new temporary directory, which is removed when the returned
+    /// `TempDir` is dropped. The cache directory itself is only created when something is written.
+    pub fn temp() -> (tempfile::TempDir, Self) {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cache = Self {
+            dir: temp_dir.path().join("cache"),
+            offline: false,
+        };
+        (temp_dir, cache)
+    }
+}
+
+fn blob_key(sha256: &str) -> String {
+    format!("sha256-{}", sha256)
+}
+
+fn write(dir: &Path, key: &str, bytes: &[u8]) -> io::Result<()> {
+    static WRITES: AtomicU64 = AtomicU64::new(0);
+
+    fs::create_dir_all(dir)?;
+    let tmp = dir.join(format!(
+        "{}.{}.{}.tmp",
+        key,
+        std::process::id(),
+        WRITES.fetch_add(1, Ordering::Relaxed)
+    ));
+    fs::write(&tmp, bytes)?;
+    fs::rename(tmp, dir.join(key))
+}
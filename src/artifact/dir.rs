@@ -0,0 +1,284 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use super::{gzip, Artifact, ArtifactError, ContractMap, ContractType};
+
+/// Name of the checksums file written by rust-optimizer and workspace-optimizer.
+pub const CHECKSUMS_FILE: &str = "checksums.txt";
+
+/// CPU architectures that cw-optimizoor and the arm64 optimizer images append to wasm file names.
+const ARCH_SUFFIXES: [&str; 3] = ["x86_64", "aarch64", "arm64"];
+
+/// A directory of optimized wasm files, e.g. the `artifacts` directory of a workspace.
+///
+/// Contract names are the file names without the `.wasm` or `.wasm.gz` extension and the optional
+/// `-<arch>` suffix, so `astroport_pair-aarch64.wasm` is loaded as `astroport_pair`. If a
+/// `checksums.txt` file is next to the wasm files, every file listed in it is checked against its
+/// hash. Gzipped files can be listed by their own name, or by the name of the uncompressed file
+/// with the hash of the uncompressed file.
+#[derive(Clone, Debug)]
+pub struct ArtifactDir {
+    /// The directory the artifacts were loaded from.
+    pub path: PathBuf,
+    /// Paths of the wasm files by contract name.
+    pub artifacts: HashMap<String, PathBuf>,
+    /// Names of the contracts whose wasm files are not listed in `checksums.txt`.
+    pub unverified: Vec<String>,
+}
+
+impl ArtifactDir {
+    /// Scans `path` for wasm files and verifies them against `checksums.txt`.
+    ///
+    /// Returns [`ArtifactError::ChecksumMismatch`] if a file doesn't match its checksum. Files
+    /// that are not listed in `checksums.txt` are loaded anyway and added to `unverified`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ArtifactError> {
+        let path = path.as_ref().to_path_buf();
+        let checksums = read_checksums(&path.join(CHECKSUMS_FILE))?;
+
+        // Files without an arch suffix take precedence, then files built for this machine, and
+        // uncompressed files take precedence over gzipped ones
+        let mut found: HashMap<String, (u8, PathBuf)> = HashMap::new();
+        for entry in fs::read_dir(&path)? {
+            let file = entry?.path();
+            let Some((stem, gzipped)) = wasm_stem(&file) else {
+                continue;
+            };
+            let (name, priority) = match strip_arch(stem) {
+                Some((name, arch)) if arch == std::env::consts::ARCH => (name, 2),
+                Some((name, _)) => (name, 4),
+                None => (stem, 0),
+            };
+            let priority = priority + gzipped as u8;
+            match found.get(name) {
+                Some((existing, _)) if *existing <= priority => {}
+                _ => {
+                    found.insert(name.to_string(), (priority, file));
+                }
+            }
+        }
+
+        let mut artifacts = HashMap::new();
+        let mut unverified = vec![];
+        for (name, (_, file)) in found {
+            let file_name = file.file_name().unwrap().to_string_lossy().to_string();
+            let checksum = match checksums.get(&file_name) {
+                Some(expected) => Some((expected, fs::read(&file)?)),
+                None => match file_name.strip_suffix(".gz").and_then(|f| checksums.get(f)) {
+                    Some(expected) => Some((expected, gzip::decompress(fs::read(&file)?)?)),
+                    None => None,
+                },
+            };
+            match checksum {
+                Some((expected, bytes)) => {
+                    let actual = hex::encode(Sha256::digest(bytes));
+                    if &actual != expected {
+                        return Err(ArtifactError::ChecksumMismatch {
+                            artifact: file.display().to_string(),
+                            expected: expected.clone(),
+                            actual,
+                        });
+                    }
+                }
+                None => unverified.push(name.clone()),
+            }
+            artifacts.insert(name, file);
+        }
+        unverified.sort();
+
+        Ok(Self {
+            path,
+            artifacts,
+            unverified,
+        })
+    }
+
+    /// Returns the artifact of the contract `name`, if it is in the directory.
+    pub fn artifact(&self, name: &str) -> Option<Artifact> {
+        self.artifacts
+            .get(name)
+            .map(|file| Artifact::Local(file.to_string_lossy().to_string()))
+    }
+
+    /// Returns a [`ContractMap`] with the contracts in `names`.
+    ///
+    /// Returns [`ArtifactError::NotFound`] if one of the contracts is not in the directory.
+    pub fn contract_map<S: AsRef<str>>(
+        &self,
+        names: impl IntoIterator<Item = S>,
+    ) -> Result<ContractMap, ArtifactError> {
+        names
+            .into_iter()
+            .map(|name| {
+                let name = name.as_ref();
+                let artifact = self.artifact(name).ok_or_else(|| ArtifactError::NotFound {
+                    name: name.to_string(),
+                    dir: self.path.display().to_string(),
+                })?;
+                Ok((name.to_string(), ContractType::Artifact(artifact)))
+            })
+            .collect()
+    }
+}
+
+/// Reads the lowercase hex encoded hashes by file name from the checksums file at `path`.
+/// Returns an empty map if there is no checksums file.
+fn read_checksums(path: &Path) -> Result<HashMap<String, String>, ArtifactError> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            // `sha256sum` format, with a `*` before the file name in binary mode
+            let mut parts = line.split_whitespace();
+            match (parts.next(), parts.next(), parts.next()) {
+                (Some(hash), Some(file), None) => Ok((
+                    file.trim_start_matches('*').to_string(),
+                    hash.to_lowercase(),
+                )),
+                _ => Err(ArtifactError::Generic(format!(
+                    "invalid line in {}: {}",
+                    path.display(),
+                    line
+                ))),
+            }
+        })
+        .collect()
+}
+
+/// Returns the file name of `file` without the `.wasm` or `.wasm.gz` extension and whether it is
+/// gzipped, or `None` if it isn't a wasm file.
+fn wasm_stem(file: &Path) -> Option<(&str, bool)> {
+    if !file.is_file() {
+        return None;
+    }
+    let file_name = file.file_name()?.to_str()?;
+    match file_name.strip_suffix(".wasm.gz") {
+        Some(stem) => Some((stem, true)),
+        None => file_name.strip_suffix(".wasm").map(|stem| (stem, false)),
+    }
+}
+
+/// Splits `stem` into the contract name and the arch suffix, if it has one.
+fn strip_arch(stem: &str) -> Option<(&str, &str)> {
+    let (name, arch) = stem.rsplit_once('-')?;
+    ARCH_SUFFIXES.contains(&arch).then_some((name, arch))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_astroport_artifacts() {
+        let dir = ArtifactDir::load("artifacts/4d3be0e").unwrap();
+
+        assert_eq!(dir.artifacts.len(), 22);
+        assert_eq!(
+            dir.unverified,
+            vec!["astroport_liquidity_manager", "astroport_token"]
+        );
+
+        let contracts = dir
+            .contract_map(["astroport_factory", "astroport_pair"])
+            .unwrap();
+        assert_eq!(contracts.len(), 2);
+        match &contracts["astroport_pair"] {
+            ContractType::Artifact(Artifact::Local(path)) => {
+                assert!(path.ends_with("astroport_pair.wasm"))
+            }
+            contract => panic!("unexpected contract: {:?}", contract),
+        }
+    }
+
+    #[test]
+    fn missing_contract() {
+        let dir = ArtifactDir::load("artifacts/4d3be0e").unwrap();
+
+        let err = dir.contract_map(["astroport_pair", "foo"]).unwrap_err();
+        assert!(matches!(err, ArtifactError::NotFound { name, .. } if name == "foo"));
+    }
+
+    #[test]
+    fn strips_arch_suffix() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path();
+        fs::write(path.join("counter-aarch64.wasm"), b"\0asm").unwrap();
+        fs::write(path.join("cw20_base-x86_64.wasm"), b"\0asm").unwrap();
+        fs::write(path.join("cw20_base.wasm"), b"\0asm\x01").unwrap();
+        fs::write(path.join("my-contract.wasm"), b"\0asm").unwrap();
+        fs::write(path.join("notes.txt"), b"").unwrap();
+
+        let dir = ArtifactDir::load(path).unwrap();
+
+        let mut names = dir.artifacts.keys().cloned().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, vec!["counter", "cw20_base", "my-contract"]);
+        assert_eq!(dir.artifacts["cw20_base"], path.join("cw20_base.wasm"));
+        assert_eq!(dir.unverified, names);
+    }
+
+    #[test]
+    fn checksum_mismatch() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path();
+        fs::write(path.join("counter.wasm"), b"\0asm").unwrap();
+        let expected = hex::encode(Sha256::digest(b"other"));
+        fs::write(
+            path.join(CHECKSUMS_FILE),
+            format!("{}  counter.wasm\n", expected.to_uppercase()),
+        )
+        .unwrap();
+
+        let err = ArtifactDir::load(path).unwrap_err();
+        match err {
+            ArtifactError::ChecksumMismatch {
+                expected: e,
+                actual,
+                ..
+            } => {
+                assert_eq!(e, expected);
+                assert_eq!(actual, hex::encode(Sha256::digest(b"\0asm")));
+            }
+            err => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[test]
+    fn gzipped_artifacts() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path();
+        let wasm = b"\0asm".to_vec();
+        let gzipped = gzip::compress(&wasm).unwrap();
+        fs::write(path.join("counter.wasm.gz"), &gzipped).unwrap();
+        fs::write(path.join("cw20_base.wasm.gz"), &gzipped).unwrap();
+        fs::write(
+            path.join(CHECKSUMS_FILE),
+            format!(
+                "{}  counter.wasm.gz\n{}  cw20_base.wasm\n",
+                hex::encode(Sha256::digest(&gzipped)),
+                hex::encode(Sha256::digest(&wasm))
+            ),
+        )
+        .unwrap();
+
+        let dir = ArtifactDir::load(path).unwrap();
+
+        assert!(dir.unverified.is_empty());
+        assert_eq!(dir.artifacts["counter"], path.join("counter.wasm.gz"));
+        assert_eq!(
+            dir.artifact("cw20_base")
+                .unwrap()
+                .get_wasm_byte_code()
+                .unwrap(),
+            wasm
+        );
+    }
+}
@@ -0,0 +1,48 @@
+use std::io::{self, Read, Write};
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+/// The first bytes of a gzip file.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Returns true if `bytes` are gzipped.
+pub fn is_gzipped(bytes: &[u8]) -> bool {
+    bytes.starts_with(&GZIP_MAGIC)
+}
+
+/// Decompresses `bytes` if they are gzipped, otherwise returns them unchanged.
+pub fn decompress(bytes: Vec<u8>) -> io::Result<Vec<u8>> {
+    if !is_gzipped(&bytes) {
+        return Ok(bytes);
+    }
+
+    let mut wasm = vec![];
+    GzDecoder::new(bytes.as_slice()).read_to_end(&mut wasm)?;
+    Ok(wasm)
+}
+
+/// Gzips `bytes`. Chains accept gzipped wasm in `MsgStoreCode`, which makes uploads cheaper.
+pub fn compress(bytes: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(vec![], Compression::best());
+    encoder.write_all(bytes)?;
+    encoder.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WASM: &[u8] = b"\0asm\x01\0\0\0";
+
+    #[test]
+    fn roundtrip() {
+        let gzipped = compress(WASM).unwrap();
+        assert!(is_gzipped(&gzipped));
+        assert_eq!(decompress(gzipped).unwrap(), WASM);
+
+        // Uncompressed bytes are returned unchanged
+        assert_eq!(decompress(WASM.to_vec()).unwrap(), WASM);
+    }
+}
@@ -0,0 +1,431 @@
+//! Inspection of wasm files before they are uploaded.
+//!
+//! [`WasmInfo`] lists what a contract exports and uses, and
+//! [`WasmInfo::check_compatible`] rejects code that chains would reject, with an error that says
+//! why. Runners check artifacts in `store_code`, and remember the entry points of stored codes by
+//! checksum to warn when a code is instantiated or migrated without the matching entry point.
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use wasmparser::{ExternalKind, Parser, Payload};
+
+use super::{Artifact, ArtifactError};
+
+/// Maximum size of a wasm file with the default wasmd config.
+pub const DEFAULT_MAX_WASM_SIZE: usize = 800 * 1024;
+
+/// Environment variable to set the maximum wasm size for chains with a different limit.
+pub const MAX_WASM_SIZE_ENV: &str = "CW_IT_MAX_WASM_SIZE";
+
+/// The contract interface version supported by CosmWasm 1.x chains.
+pub const SUPPORTED_INTERFACE_VERSION: u32 = 8;
+
+/// Exports that are called by the chain.
+pub const ENTRY_POINTS: &[&str] = &[
+    "instantiate",
+    "execute",
+    "query",
+    "migrate",
+    "sudo",
+    "reply",
+    "ibc_channel_open",
+    "ibc_channel_connect",
+    "ibc_channel_close",
+    "ibc_packet_receive",
+    "ibc_packet_ack",
+    "ibc_packet_timeout",
+];
+
+/// Exports every contract must have.
+const REQUIRED_EXPORTS: &[&str] = &["allocate", "deallocate"];
+
+const INTERFACE_VERSION_PREFIX: &str = "interface_version_";
+const CAPABILITY_PREFIX: &str = "requires_";
+
+/// What a wasm file exports and uses.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WasmInfo {
+    /// Size of the wasm file in bytes.
+    pub size: usize,
+    /// All exported functions.
+    pub exports: BTreeSet<String>,
+    /// The exported [`ENTRY_POINTS`].
+    pub entry_points: BTreeSet<String>,
+    /// Capabilities the chain must have, e.g. `stargate` or `cosmwasm_1_2`, from the
+    /// `requires_*` exports.
+    pub capabilities: BTreeSet<String>,
+    /// The version of the `interface_version_*` export.
+    pub interface_version: Option<u32>,
+    /// Names of the floating point operators in the code, e.g. `F64Add`.
+    pub float_ops: BTreeSet<String>,
+}
+
+impl WasmInfo {
+    /// Parses the wasm file `wasm`.
+    pub fn parse(wasm: &[u8]) -> Result<Self, ArtifactError> {
+        let mut info = WasmInfo {
+            size: wasm.len(),
+            ..Default::default()
+        };
+
+        let mut op_name = String::new();
+        for payload in Parser::new(0).parse_all(wasm) {
+            match payload? {
+                Payload::ExportSection(reader) => {
+                    for export in reader {
+                        let export = export?;
+                        if export.kind == ExternalKind::Func {
+                            info.add_export(export.name);
+                        }
+                    }
+                }
+                Payload::CodeSectionEntry(body) => {
+                    let mut reader = body.get_operators_reader()?;
+                    while !reader.eof() {
+                        let op = reader.read()?;
+                        // Operators are named after their types, e.g. `F32Add` or `I64TruncF64S`,
+                        // which is simpler than listing all float operators of every proposal
+                        op_name.clear();
+                        write!(op_name, "{:?}", op).unwrap();
+                        let name = op_name
+                            .split(|c: char| !c.is_ascii_alphanumeric())
+                            .next()
+                            .unwrap_or_default();
+                        if name.contains("F32") || name.contains("F64") {
+                            info.float_ops.insert(name.to_string());
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(info)
+    }
+
+    fn add_export(&mut self, name: &str) {
+        if ENTRY_POINTS.contains(&name) {
+            self.entry_points.insert(name.to_string());
+        } else if let Some(capability) = name.strip_prefix(CAPABILITY_PREFIX) {
+            self.capabilities.insert(capability.to_string());
+        } else if let Some(version) = name.strip_prefix(INTERFACE_VERSION_PREFIX) {
+            self.interface_version = version.parse().ok();
+        }
+        self.exports.insert(name.to_string());
+    }
+
+    /// Returns true if the code exports the entry point `name`.
+    pub fn has_entry_point(&self, name: &str) -> bool {
+        self.entry_points.contains(name)
+    }
+
+    /// Returns an error if chains would reject the code, i.e. if it is larger than `max_size`,
+    /// doesn't have the supported interface version or the required exports, or uses floating
+    /// point operators.
+    pub fn check_compatible(&self, max_size: usize) -> Result<(), ArtifactError> {
+        if self.size > max_size {
+            return Err(ArtifactError::CodeTooLarge {
+                size: self.size,
+                max: max_size,
+            });
+        }
+        match self.interface_version {
+            Some(SUPPORTED_INTERFACE_VERSION) => {}
+            Some(version) => {
+                return Err(ArtifactError::IncompatibleWasm(format!(
+                    "interface version {} is not supported, expected {}",
+                    version, SUPPORTED_INTERFACE_VERSION
+                )))
+            }
+            None => {
+                return Err(ArtifactError::IncompatibleWasm(
+                    "missing interface_version_* export, this is not a CosmWasm contract"
+                        .to_string(),
+                ))
+            }
+        }
+        if let Some(missing) = REQUIRED_EXPORTS
+            .iter()
+            .find(|export| !self.exports.contains(**export))
+        {
+            return Err(ArtifactError::IncompatibleWasm(format!(
+                "missing required export {}",
+                missing
+            )));
+        }
+        if !self.float_ops.is_empty() {
+            return Err(ArtifactError::FloatingPoint(
+                self.float_ops.iter().cloned().collect(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Artifact {
+    /// Reads the wasm file and returns what it exports and uses.
+    pub fn inspect(&self) -> Result<WasmInfo, ArtifactError> {
+        WasmInfo::parse(&self.get_wasm_byte_code()?)
+    }
+}
+
+/// Returns the maximum wasm size, from [`MAX_WASM_SIZE_ENV`] or [`DEFAULT_MAX_WASM_SIZE`].
+pub fn max_wasm_size() -> usize {
+    std::env::var(MAX_WASM_SIZE_ENV)
+        .ok()
+        .and_then(|size| size.parse().ok())
+        .unwrap_or(DEFAULT_MAX_WASM_SIZE)
+}
+
+/// Parses `wasm` and checks that it can be stored on a chain.
+#[cfg(any(
+    feature = "rpc-runner",
+    feature = "osmosis-test-tube",
+    feature = "multi-test"
+))]
+pub(crate) fn check_wasm(wasm: &[u8]) -> Result<WasmInfo, ArtifactError> {
+    let info = WasmInfo::parse(wasm)?;
+    info.check_compatible(max_wasm_size())?;
+    Ok(info)
+}
+
+/// The entry points of the codes stored by the runners, to warn when a code is instantiated or
+/// migrated without the matching entry point.
+#[cfg(any(feature = "rpc-runner", feature = "osmosis-test-tube"))]
+mod stored_codes {
+    use std::collections::{BTreeSet, HashMap};
+    use std::sync::{Mutex, OnceLock};
+
+    use osmosis_std::types::cosmwasm::wasm::v1::{
+        MsgInstantiateContract, MsgInstantiateContract2, MsgMigrateContract,
+    };
+    use prost::Message;
+    use sha2::{Digest, Sha256};
+
+    use super::WasmInfo;
+    #[cfg(feature = "osmosis-test-tube")]
+    use crate::traits::CwItRunner;
+
+    /// Entry points of stored codes, by the sha256 checksum of the wasm file. The entry points only
+    /// depend on the wasm file, so they are the same for every runner the code is stored on.
+    fn stored_codes() -> &'static Mutex<HashMap<Vec<u8>, BTreeSet<String>>> {
+        static STORED_CODES: OnceLock<Mutex<HashMap<Vec<u8>, BTreeSet<String>>>> = OnceLock::new();
+        STORED_CODES.get_or_init(Default::default)
+    }
+
+    /// Remembers the entry points of the stored wasm file `wasm`.
+    pub(crate) fn record_stored_code(wasm: &[u8], info: WasmInfo) {
+        stored_codes()
+            .lock()
+            .unwrap()
+            .insert(Sha256::digest(wasm).to_vec(), info.entry_points);
+    }
+
+    /// Returns the code id and the entry point needed by the message with `type_url` and `value`, if
+    /// it instantiates or migrates to a code. Returns `None` if no code was recorded, since the
+    /// checksum of the code doesn't need to be queried then.
+    pub(crate) fn required_entry_point(
+        type_url: &str,
+        value: &[u8],
+    ) -> Option<(u64, &'static str)> {
+        if stored_codes().lock().unwrap().is_empty() {
+            return None;
+        }
+        match type_url {
+            MsgInstantiateContract::TYPE_URL => MsgInstantiateContract::decode(value)
+                .ok()
+                .map(|msg| (msg.code_id, "instantiate")),
+            MsgInstantiateContract2::TYPE_URL => MsgInstantiateContract2::decode(value)
+                .ok()
+                .map(|msg| (msg.code_id, "instantiate")),
+            MsgMigrateContract::TYPE_URL => MsgMigrateContract::decode(value)
+                .ok()
+                .map(|msg| (msg.code_id, "migrate")),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the code with `checksum` was stored by a runner and doesn't have the entry
+    /// point `entry_point`.
+    pub(super) fn lacks_entry_point(checksum: &[u8], entry_point: &str) -> bool {
+        stored_codes()
+            .lock()
+            .unwrap()
+            .get(checksum)
+            .is_some_and(|entry_points| !entry_points.contains(entry_point))
+    }
+
+    /// Prints a warning if the code `code_id` with `checksum` was stored by a runner and doesn't have
+    /// the entry point `entry_point`.
+    pub(crate) fn warn_missing_entry_point(checksum: &[u8], code_id: u64, entry_point: &str) {
+        if lacks_entry_point(checksum, entry_point) {
+            eprintln!(
+                "Warning: code id {} has no {} entry point, calling it will fail",
+                code_id, entry_point
+            );
+        }
+    }
+
+    /// Prints a warning if the message with `type_url` and `value` instantiates or migrates to a code
+    /// without the `instantiate` or `migrate` entry point. The checksum of the code is queried from
+    /// `runner`.
+    #[cfg(feature = "osmosis-test-tube")]
+    pub(crate) fn check_entry_point<'a, R: CwItRunner<'a>>(
+        runner: &'a R,
+        type_url: &str,
+        value: &[u8],
+    ) {
+        if let Some((code_id, entry_point)) = required_entry_point(type_url, value) {
+            if let Ok(checksum) = runner.query_code_checksum(code_id) {
+                warn_missing_entry_point(&checksum, code_id, entry_point);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "osmosis-test-tube")]
+pub(crate) use stored_codes::check_entry_point;
+#[cfg(any(feature = "rpc-runner", feature = "osmosis-test-tube"))]
+pub(crate) use stored_codes::{record_stored_code, required_entry_point, warn_missing_entry_point};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a minimal module with one function that runs `body`, exported as `exports`.
+    fn module(exports: &[&str], body: &[u8]) -> Vec<u8> {
+        let mut wasm = b"\0asm\x01\0\0\0".to_vec();
+        // Type section: one function type without params and results
+        wasm.extend([0x01, 0x04, 0x01, 0x60, 0x00, 0x00]);
+        // Function section: one function of type 0
+        wasm.extend([0x03, 0x02, 0x01, 0x00]);
+
+        let mut section = vec![exports.len() as u8];
+        for name in exports {
+            section.push(name.len() as u8);
+            section.extend(name.as_bytes());
+            section.extend([0x00, 0x00]);
+        }
+        wasm.extend([0x07, section.len() as u8]);
+        wasm.extend(section);
+
+        let mut code = vec![0x00];
+        code.extend(body);
+        code.push(0x0b);
+        wasm.extend([0x0a, code.len() as u8 + 2, 0x01, code.len() as u8]);
+        wasm.extend(code);
+        wasm
+    }
+
+    const CONTRACT_EXPORTS: &[&str] = &[
+        "allocate",
+        "deallocate",
+        "interface_version_8",
+        "requires_stargate",
+        "instantiate",
+        "execute",
+        "query",
+    ];
+
+    #[test]
+    fn parse_exports() {
+        let info = WasmInfo::parse(&module(CONTRACT_EXPORTS, &[])).unwrap();
+
+        assert_eq!(
+            info.entry_points,
+            ["execute", "instantiate", "query"]
+                .into_iter()
+                .map(String::from)
+                .collect()
+        );
+        assert_eq!(info.capabilities, BTreeSet::from(["stargate".to_string()]));
+        assert_eq!(info.interface_version, Some(8));
+        assert!(info.float_ops.is_empty());
+        assert!(!info.has_entry_point("migrate"));
+        info.check_compatible(DEFAULT_MAX_WASM_SIZE).unwrap();
+    }
+
+    #[test]
+    fn float_ops() {
+        // f32.const 1.0, f32.const 1.0, f32.add, drop
+        let body = [
+            0x43, 0x00, 0x00, 0x80, 0x3f, 0x43, 0x00, 0x00, 0x80, 0x3f, 0x92, 0x1a,
+        ];
+        let info = WasmInfo::parse(&module(CONTRACT_EXPORTS, &body)).unwrap();
+
+        assert_eq!(
+            info.float_ops,
+            BTreeSet::from(["F32Add".to_string(), "F32Const".to_string()])
+        );
+        let err = info.check_compatible(DEFAULT_MAX_WASM_SIZE).unwrap_err();
+        assert!(matches!(err, ArtifactError::FloatingPoint(ops) if ops.len() == 2));
+    }
+
+    #[test]
+    fn incompatible() {
+        let wasm = module(CONTRACT_EXPORTS, &[]);
+        let err = WasmInfo::parse(&wasm)
+            .unwrap()
+            .check_compatible(wasm.len() - 1)
+            .unwrap_err();
+        assert!(matches!(err, ArtifactError::CodeTooLarge { .. }));
+
+        let err = WasmInfo::parse(&module(&["allocate", "deallocate"], &[]))
+            .unwrap()
+            .check_compatible(DEFAULT_MAX_WASM_SIZE)
+            .unwrap_err();
+        assert!(matches!(err, ArtifactError::IncompatibleWasm(_)));
+
+        let err = WasmInfo::parse(&module(&["interface_version_8", "allocate"], &[]))
+            .unwrap()
+            .check_compatible(DEFAULT_MAX_WASM_SIZE)
+            .unwrap_err();
+        assert!(err.to_string().contains("deallocate"));
+
+        assert!(WasmInfo::parse(b"\0asm").is_err());
+    }
+
+    #[cfg(any(feature = "rpc-runner", feature = "osmosis-test-tube"))]
+    #[test]
+    fn stored_code_entry_points() {
+        use osmosis_std::types::cosmwasm::wasm::v1::MsgMigrateContract;
+        use prost::Message;
+        use sha2::{Digest, Sha256};
+
+        use super::stored_codes::lacks_entry_point;
+
+        let wasm = module(CONTRACT_EXPORTS, &[1]);
+        let checksum = Sha256::digest(&wasm).to_vec();
+        assert!(!lacks_entry_point(&checksum, "migrate"));
+
+        record_stored_code(&wasm, WasmInfo::parse(&wasm).unwrap());
+        assert!(!lacks_entry_point(&checksum, "instantiate"));
+        assert!(lacks_entry_point(&checksum, "migrate"));
+
+        let migrate = MsgMigrateContract {
+            code_id: 7,
+            ..Default::default()
+        };
+        assert_eq!(
+            required_entry_point(MsgMigrateContract::TYPE_URL, &migrate.encode_to_vec()),
+            Some((7, "migrate"))
+        );
+        assert_eq!(
+            required_entry_point("/cosmos.bank.v1beta1.MsgSend", &[]),
+            None
+        );
+    }
+
+    #[test]
+    fn inspect_counter_artifact() {
+        let info = Artifact::Local("artifacts/counter.wasm".to_string())
+            .inspect()
+            .unwrap();
+
+        assert!(info.has_entry_point("instantiate"));
+        assert!(info.has_entry_point("execute"));
+        assert!(info.has_entry_point("query"));
+        info.check_compatible(DEFAULT_MAX_WASM_SIZE).unwrap();
+    }
+}
@@ -0,0 +1,389 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::{Artifact, ArtifactError, ContractMap, ContractType};
+use crate::TestRunner;
+
+/// A manifest file mapping contract names to the sources of their wasm files. The format is
+/// detected from the file extension and can be YAML, TOML or JSON, e.g:
+/// ```yaml
+/// rpc_endpoint: https://rpc.baryon.ntrn.info/
+/// contracts:
+///   counter:
+///     path: artifacts/counter.wasm
+///   cw20_base:
+///     url: https://github.com/CosmWasm/cw-plus/releases/download/v1.1.0/cw20_base.wasm
+///     sha256: <hex encoded sha256 hash>
+///   astroport_token:
+///     code_id: 62
+///   astroport_factory:
+///     contract_address: neutron1fuaym3wkqvts8r9vafd77q00jxuplacchde552amyk05gjqtmy2s84lnvr
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// RPC endpoint used by chain sources that don't set their own.
+    #[serde(default)]
+    pub rpc_endpoint: Option<String>,
+    pub contracts: BTreeMap<String, ContractSource>,
+}
+
+/// Where to get the wasm file of a contract in a [`Manifest`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ContractSource {
+    /// A local wasm file. Relative paths are relative to the directory of the manifest.
+    Local { path: String },
+    /// A wasm file to download, see [`Artifact::Url`]. Needs the `url-download` feature.
+    Url {
+        url: String,
+        #[serde(default)]
+        sha256: Option<String>,
+    },
+    /// The code of a code id on a chain. Needs the `chain-download` feature.
+    CodeId {
+        code_id: u64,
+        #[serde(default)]
+        rpc_endpoint: Option<String>,
+    },
+    /// The code of a contract on a chain. Needs the `chain-download` feature.
+    ContractAddress {
+        contract_address: String,
+        #[serde(default)]
+        rpc_endpoint: Option<String>,
+    },
+}
+
+impl Manifest {
+    /// Reads the manifest at `path`.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ArtifactError> {
+        Ok(config::Config::builder()
+            .add_source(config::File::from(path.as_ref()))
+            .build()?
+            .try_deserialize()?)
+    }
+
+    /// Returns the artifacts of all contracts. Relative local paths are resolved against
+    /// `base_dir`.
+    pub fn artifacts(
+        &self,
+        base_dir: impl AsRef<Path>,
+    ) -> Result<BTreeMap<String, Artifact>, ArtifactError> {
+        self.contracts
+            .iter()
+            .map(|(name, source)| {
+                let artifact = source
+                    .to_artifact(base_dir.as_ref(), self.rpc_endpoint.as_deref())
+                    .map_err(|e| ArtifactError::Generic(format!("contract {}: {}", name, e)))?;
+                Ok((name.clone(), artifact))
+            })
+            .collect()
+    }
+}
+
+impl ContractSource {
+    /// Returns the artifact for this source. `rpc_endpoint` is used by chain sources that don't
+    /// set their own.
+    #[cfg_attr(not(feature = "chain-download"), allow(unused_variables))]
+    pub fn to_artifact(
+        &self,
+        base_dir: &Path,
+        rpc_endpoint: Option<&str>,
+    ) -> Result<Artifact, ArtifactError> {
+        #[cfg(feature = "chain-download")]
+        let rpc_endpoint = |own: &Option<String>| {
+            own.as_deref()
+                .or(rpc_endpoint)
+                .map(str::to_string)
+                .ok_or_else(|| ArtifactError::Generic("no rpc_endpoint set".to_string()))
+        };
+
+        match self {
+            ContractSource::Local { path } => Ok(Artifact::Local(
+                base_dir.join(path).to_string_lossy().to_string(),
+            )),
+            #[cfg(feature = "url-download")]
+            ContractSource::Url { url, sha256 } => Ok(Artifact::Url {
+                url: url.clone(),
+                sha256: sha256.clone(),
+            }),
+            #[cfg(feature = "chain-download")]
+            ContractSource::CodeId {
+                code_id,
+                rpc_endpoint: own,
+            } => Ok(Artifact::ChainCodeId {
+                rpc_endpoint: rpc_endpoint(own)?,
+                code_id: *code_id,
+            }),
+            #[cfg(feature = "chain-download")]
+            ContractSource::ContractAddress {
+                contract_address,
+                rpc_endpoint: own,
+            } => Ok(Artifact::ChainContractAddress {
+                rpc_endpoint: rpc_endpoint(own)?,
+                contract_address: contract_address.clone(),
+            }),
+            #[allow(unreachable_patterns)]
+            ContractSource::Url { .. } => Err(ArtifactError::Generic(
+                "url sources need the url-download feature".to_string(),
+            )),
+            #[allow(unreachable_patterns)]
+            _ => Err(ArtifactError::Generic(
+                "chain sources need the chain-download feature".to_string(),
+            )),
+        }
+    }
+}
+
+/// Builds a [`ContractMap`] from a [`Manifest`] file.
+pub trait FromManifest: Sized {
+    /// Reads the manifest at `path` and resolves the sources of all contracts in it.
+    fn from_manifest(path: impl AsRef<Path>, runner: &TestRunner) -> Result<Self, ArtifactError> {
+        Self::from_manifest_with_overrides(path, runner, ContractMap::new())
+    }
+
+    /// Like [`from_manifest`](FromManifest::from_manifest), but if `runner` is a
+    /// `MultiTestRunner`, contracts in `multi_test_contracts` replace the contracts with the same
+    /// name in the manifest. This lets the same manifest be used with runners that need wasm files
+    /// and with multi-test, which needs `MultiTestContract` wrappers.
+    fn from_manifest_with_overrides(
+        path: impl AsRef<Path>,
+        runner: &TestRunner,
+        multi_test_contracts: ContractMap,
+    ) -> Result<Self, ArtifactError>;
+}
+
+impl FromManifest for ContractMap {
+    #[cfg_attr(not(feature = "multi-test"), allow(unused_variables))]
+    fn from_manifest_with_overrides(
+        path: impl AsRef<Path>,
+        runner: &TestRunner,
+        multi_test_contracts: ContractMap,
+    ) -> Result<Self, ArtifactError> {
+        let path = path.as_ref();
+        let manifest = Manifest::from_file(path)?;
+        let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+        #[allow(unused_mut)]
+        let mut contracts: ContractMap = manifest
+            .artifacts(base_dir)?
+            .into_iter()
+            .map(|(name, artifact)| (name, ContractType::Artifact(artifact)))
+            .collect();
+
+        #[cfg(feature = "multi-test")]
+        if let TestRunner::MultiTest(_) = runner {
+            contracts.extend(multi_test_contracts);
+        }
+
+        Ok(contracts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use tempfile::TempDir;
+
+    use super::*;
+
+    const YAML: &str = r#"
+rpc_endpoint: https://rpc.example.com/
+contracts:
+  counter:
+    path: artifacts/counter.wasm
+  cw20_base:
+    url: https://example.com/cw20_base.wasm
+    sha256: abcd
+  astroport_token:
+    code_id: 62
+  astroport_factory:
+    contract_address: neutron1factory
+    rpc_endpoint: https://other.example.com/
+"#;
+
+    const TOML: &str = r#"
+rpc_endpoint = "https://rpc.example.com/"
+
+[contracts.counter]
+path = "artifacts/counter.wasm"
+
+[contracts.cw20_base]
+url = "https://example.com/cw20_base.wasm"
+sha256 = "abcd"
+
+[contracts.astroport_token]
+code_id = 62
+
+[contracts.astroport_factory]
+contract_address = "neutron1factory"
+rpc_endpoint = "https://other.example.com/"
+"#;
+
+    const JSON: &str = r#"{
+  "rpc_endpoint": "https://rpc.example.com/",
+  "contracts": {
+    "counter": { "path": "artifacts/counter.wasm" },
+    "cw20_base": { "url": "https://example.com/cw20_base.wasm", "sha256": "abcd" },
+    "astroport_token": { "code_id": 62 },
+    "astroport_factory": {
+      "contract_address": "neutron1factory",
+      "rpc_endpoint": "https://other.example.com/"
+    }
+  }
+}"#;
+
+    /// Writes `content` to `file` in `dir`.
+    fn write_manifest(dir: &TempDir, file: &str, content: &str) -> PathBuf {
+        let path = dir.path().join(file);
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn manifest_formats() {
+        let dir = tempfile::tempdir().unwrap();
+        for (file, content) in [
+            ("contracts.yaml", YAML),
+            ("contracts.toml", TOML),
+            ("contracts.json", JSON),
+        ] {
+            let path = write_manifest(&dir, file, content);
+            let manifest = Manifest::from_file(&path).unwrap();
+
+            assert_eq!(
+                manifest.rpc_endpoint.as_deref(),
+                Some("https://rpc.example.com/")
+            );
+            assert_eq!(
+                manifest.contracts["counter"],
+                ContractSource::Local {
+                    path: "artifacts/counter.wasm".to_string()
+                }
+            );
+            assert_eq!(
+                manifest.contracts["cw20_base"],
+                ContractSource::Url {
+                    url: "https://example.com/cw20_base.wasm".to_string(),
+                    sha256: Some("abcd".to_string())
+                }
+            );
+            assert_eq!(
+                manifest.contracts["astroport_token"],
+                ContractSource::CodeId {
+                    code_id: 62,
+                    rpc_endpoint: None
+                }
+            );
+            assert_eq!(
+                manifest.contracts["astroport_factory"],
+                ContractSource::ContractAddress {
+                    contract_address: "neutron1factory".to_string(),
+                    rpc_endpoint: Some("https://other.example.com/".to_string())
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn local_paths_are_relative_to_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_manifest(
+            &dir,
+            "contracts.yaml",
+            "contracts:\n  counter:\n    path: artifacts/counter.wasm\n",
+        );
+
+        let contracts = ContractMap::from_manifest(&path, &TestRunner::PhantomData(&())).unwrap();
+
+        match &contracts["counter"] {
+            ContractType::Artifact(Artifact::Local(file)) => assert_eq!(
+                PathBuf::from(file),
+                path.parent().unwrap().join("artifacts/counter.wasm")
+            ),
+            contract => panic!("unexpected contract: {:?}", contract),
+        }
+    }
+
+    #[cfg(all(feature = "chain-download", feature = "url-download"))]
+    #[test]
+    fn chain_sources() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_manifest(&dir, "contracts.yaml", YAML);
+        let artifacts = Manifest::from_file(&path)
+            .unwrap()
+            .artifacts(path.parent().unwrap())
+            .unwrap();
+
+        assert_eq!(
+            artifacts["astroport_token"],
+            Artifact::ChainCodeId {
+                rpc_endpoint: "https://rpc.example.com/".to_string(),
+                code_id: 62
+            }
+        );
+        assert_eq!(
+            artifacts["astroport_factory"],
+            Artifact::ChainContractAddress {
+                rpc_endpoint: "https://other.example.com/".to_string(),
+                contract_address: "neutron1factory".to_string()
+            }
+        );
+
+        // Without a default endpoint, chain sources must set their own
+        let manifest = Manifest {
+            rpc_endpoint: None,
+            ..Manifest::from_file(&path).unwrap()
+        };
+        let err = manifest.artifacts("").unwrap_err();
+        assert!(err.to_string().contains("astroport_token"));
+    }
+
+    #[cfg(feature = "multi-test")]
+    #[test]
+    fn multi_test_overrides() {
+        use crate::multi_test::MultiTestRunner;
+        use crate::test_helpers::test_contract;
+
+        let dir = tempfile::tempdir().unwrap();
+
+        let path = write_manifest(
+            &dir,
+            "contracts.yaml",
+            "contracts:\n  counter:\n    path: counter.wasm\n  other:\n    path: other.wasm\n",
+        );
+        let overrides = || {
+            let mut contracts = ContractMap::new();
+            contracts.insert(
+                "counter".to_string(),
+                ContractType::MultiTestContract(test_contract::contract()),
+            );
+            contracts
+        };
+
+        let runner = MultiTestRunner::new("osmo");
+        let contracts = ContractMap::from_manifest_with_overrides(
+            &path,
+            &TestRunner::MultiTest(&runner),
+            overrides(),
+        )
+        .unwrap();
+        assert!(matches!(
+            contracts["counter"],
+            ContractType::MultiTestContract(_)
+        ));
+        assert!(matches!(contracts["other"], ContractType::Artifact(_)));
+
+        // Overrides are only used with multi-test
+        let contracts = ContractMap::from_manifest_with_overrides(
+            &path,
+            &TestRunner::PhantomData(&()),
+            overrides(),
+        )
+        .unwrap();
+        assert!(matches!(contracts["counter"], ContractType::Artifact(_)));
+    }
+}
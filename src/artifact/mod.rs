@@ -11,23 +11,51 @@ use {apollo_cw_multi_test::Contract, cosmwasm_std::Empty};
 #[cfg(feature = "chain-download")]
 use self::on_chain::{download_wasm_from_code_id, download_wasm_from_contract_address};
 
+#[cfg(feature = "url-download")]
+use self::url::download_wasm_from_url;
+
+#[cfg(feature = "artifact-dir")]
+pub use self::dir::ArtifactDir;
+#[cfg(feature = "wasm-inspect")]
+pub use self::inspect::WasmInfo;
+pub use self::manifest::{ContractSource, FromManifest, Manifest};
+pub use self::set::ArtifactSet;
+
+#[cfg(any(feature = "url-download", feature = "chain-download"))]
+pub mod cache;
+#[cfg(feature = "artifact-dir")]
+pub mod dir;
+pub mod gzip;
+#[cfg(feature = "wasm-inspect")]
+pub mod inspect;
+pub mod manifest;
 #[cfg(feature = "chain-download")]
 mod on_chain;
+pub mod set;
+#[cfg(feature = "url-download")]
+mod url;
 
 /// Enum to represent the different ways to get a contract artifact, i.e a contract wasm file.
 #[cw_serde]
 pub enum Artifact {
-    /// A path to a local wasm file.
+    /// A path to a local wasm file, which may be gzipped.
     Local(String),
-    /// A url to download the wasm file from.
+    /// The wasm byte code, e.g. built by the test or embedded with `include_bytes!`. May be
+    /// gzipped.
+    Bytes(Vec<u8>),
+    /// A url to download the wasm file from, optionally with the hex encoded sha256 hash the
+    /// downloaded file must have, i.e. the hash of the gzipped file for gzipped files. Downloads
+    /// are cached, see [`cache`].
     #[cfg(feature = "url-download")]
-    Url(String),
+    Url { url: String, sha256: Option<String> },
     /// An RPC endpoint to download the artifact from, together with a code id.
-    /// Downloads the wasm mapping to the code id from the chain.
+    /// Downloads the wasm mapping to the code id from the chain. Downloads are cached, see
+    /// [`cache`].
     #[cfg(feature = "chain-download")]
     ChainCodeId { rpc_endpoint: String, code_id: u64 },
     /// An RPC endpoint to download the artifact from, together with a contract address.
-    /// Downloads the wasm mapping to the contract address from the chain.
+    /// Downloads the wasm mapping to the contract address from the chain. Downloads are cached,
+    /// see [`cache`].
     #[cfg(feature = "chain-download")]
     ChainContractAddress {
         rpc_endpoint: String,
@@ -49,6 +77,10 @@ pub enum ContractType {
 impl Debug for ContractType {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
+            // Wasm files are too large to print
+            ContractType::Artifact(Artifact::Bytes(bytes)) => {
+                write!(f, "Artifact(Bytes({} bytes))", bytes.len())
+            }
             ContractType::Artifact(artifact) => write!(f, "Artifact({:?})", artifact),
             #[cfg(feature = "multi-test")]
             ContractType::MultiTestContract(_) => write!(f, "MultiTestContract"),
@@ -92,6 +124,43 @@ pub enum ArtifactError {
     #[error("{0}")]
     Generic(String),
 
+    #[error("Checksum mismatch for {artifact}: expected {expected}, got {actual}")]
+    ChecksumMismatch {
+        artifact: String,
+        expected: String,
+        actual: String,
+    },
+
+    #[error("{0}")]
+    ConfigError(#[from] config::ConfigError),
+
+    #[error("Wasm code is {size} bytes, larger than the maximum of {max} bytes")]
+    CodeTooLarge { size: usize, max: usize },
+
+    #[error("Incompatible wasm code: {0}")]
+    IncompatibleWasm(String),
+
+    #[error("Wasm code uses floating point operations: {}", .0.join(", "))]
+    FloatingPoint(Vec<String>),
+
+    #[cfg(feature = "wasm-inspect")]
+    #[error("Invalid wasm code: {0}")]
+    InvalidWasm(#[from] wasmparser::BinaryReaderError),
+
+    #[error("Unknown artifact set version {0}")]
+    UnknownVersion(String),
+
+    #[error("Artifact {name} not found in {dir}")]
+    NotFound { name: String, dir: String },
+
+    #[cfg(any(feature = "url-download", feature = "chain-download"))]
+    #[error("{0} is not cached and downloads are disabled with CW_IT_OFFLINE")]
+    Offline(String),
+
+    #[cfg(feature = "url-download")]
+    #[error("{0}")]
+    HttpError(#[from] Box<ureq::Error>),
+
     #[cfg(feature = "chain-download")]
     #[error("{0}")]
     DecodeError(#[from] prost::DecodeError),
@@ -103,22 +172,26 @@ pub enum ArtifactError {
 
 impl Artifact {
     /// Return the wasm byte code for the artifact.
+    ///
+    /// Gzipped wasm files, e.g. `.wasm.gz` files, are decompressed.
     pub fn get_wasm_byte_code(&self) -> Result<Vec<u8>, ArtifactError> {
-        match self {
-            Artifact::Local(path) => Ok(fs::read(path)?),
+        let bytes = match self {
+            Artifact::Local(path) => fs::read(path)?,
+            Artifact::Bytes(bytes) => bytes.clone(),
             #[cfg(feature = "url-download")]
-            Artifact::Url(_url) => todo!(),
+            Artifact::Url { url, sha256 } => download_wasm_from_url(url, sha256.as_deref())?,
             #[cfg(feature = "chain-download")]
             Artifact::ChainCodeId {
                 rpc_endpoint,
                 code_id,
-            } => download_wasm_from_code_id(rpc_endpoint, *code_id),
+            } => download_wasm_from_code_id(rpc_endpoint, *code_id)?,
             #[cfg(feature = "chain-download")]
             Artifact::ChainContractAddress {
                 rpc_endpoint,
                 contract_address,
-            } => download_wasm_from_contract_address(rpc_endpoint, contract_address),
-        }
+            } => download_wasm_from_contract_address(rpc_endpoint, contract_address)?,
+        };
+        Ok(gzip::decompress(bytes)?)
     }
 }
 
@@ -133,6 +206,15 @@ mod tests {
         assert_eq!(format!("{:?}", contract_type), "Artifact(Local(\"foo\"))");
     }
 
+    #[test]
+    fn contract_type_debug_bytes() {
+        let contract_type = ContractType::Artifact(Artifact::Bytes(vec![0; 1024]));
+        assert_eq!(
+            format!("{:?}", contract_type),
+            "Artifact(Bytes(1024 bytes))"
+        );
+    }
+
     #[cfg(feature = "multi-test")]
     mod multi_test {
         use apollo_cw_multi_test::ContractWrapper;
@@ -5,16 +5,48 @@ use cosmrs::{
     rpc::{endpoint::abci_query::AbciQuery, Client, HttpClient},
 };
 use prost::Message;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
 
 use crate::helpers::block_on;
 
+use super::cache::{sha256_hex, Cache};
 use super::ArtifactError;
 
 pub fn download_wasm_from_code_id(
     rpc_endpoint: &str,
     code_id: u64,
 ) -> Result<Vec<u8>, ArtifactError> {
-    let http_client = HttpClient::new(rpc_endpoint)?;
+    download_wasm_from_code_id_cached(rpc_endpoint, code_id, &Cache::from_env())
+}
+
+pub fn download_wasm_from_contract_address(
+    rpc_endpoint: &str,
+    contract_address: impl Into<String>,
+) -> Result<Vec<u8>, ArtifactError> {
+    download_wasm_from_contract_address_cached(
+        rpc_endpoint,
+        &contract_address.into(),
+        &Cache::from_env(),
+    )
+}
+
+fn download_wasm_from_code_id_cached(
+    rpc_endpoint: &str,
+    code_id: u64,
+    cache: &Cache,
+) -> Result<Vec<u8>, ArtifactError> {
+    // The code of a code id never changes, so a cached file is always up to date
+    let key = format!("code-{}-{}", sha256_hex(rpc_endpoint.as_bytes()), code_id);
+    if let Some(wasm) = cache
+        .read_string(&key)
+        .and_then(|sha256| cache.read_blob(&sha256))
+    {
+        return Ok(wasm);
+    }
+
+    cache.check_online(&format!("code id {} on {}", code_id, rpc_endpoint))?;
+    let http_client = http_client(rpc_endpoint)?;
     // Query wasm file
     let code_res = QueryCodeResponse::decode(
         rpc_query(
@@ -25,21 +57,57 @@ pub fn download_wasm_from_code_id(
         .value
         .as_slice(),
     )?;
+
+    let sha256 = cache.write_blob(&code_res.data)?;
+    cache.write(&key, sha256.as_bytes())?;
     Ok(code_res.data)
 }
 
-pub fn download_wasm_from_contract_address(
+fn download_wasm_from_contract_address_cached(
     rpc_endpoint: &str,
-    contract_address: impl Into<String>,
+    contract_address: &str,
+    cache: &Cache,
 ) -> Result<Vec<u8>, ArtifactError> {
-    let http_client = HttpClient::new(rpc_endpoint)?;
+    // Contracts can be migrated, so the code id is only read from the cache if the chain can't
+    // be queried
+    let key = format!(
+        "contract-{}-{}",
+        sha256_hex(rpc_endpoint.as_bytes()),
+        contract_address
+    );
+    let cached_code_id = || {
+        cache
+            .read_string(&key)
+            .and_then(|code_id| code_id.parse::<u64>().ok())
+    };
+
+    let code_id = if cache.offline {
+        cached_code_id().ok_or_else(|| {
+            ArtifactError::Offline(format!("contract {} on {}", contract_address, rpc_endpoint))
+        })?
+    } else {
+        match query_code_id(rpc_endpoint, contract_address) {
+            Ok(code_id) => {
+                cache.write(&key, code_id.to_string().as_bytes())?;
+                code_id
+            }
+            Err(e @ ArtifactError::RpcError(_)) => cached_code_id().ok_or(e)?,
+            Err(e) => return Err(e),
+        }
+    };
+
+    download_wasm_from_code_id_cached(rpc_endpoint, code_id, cache)
+}
+
+fn query_code_id(rpc_endpoint: &str, contract_address: &str) -> Result<u64, ArtifactError> {
+    let http_client = http_client(rpc_endpoint)?;
 
     // Query contract info
-    let code_id = QueryContractInfoResponse::decode(
+    Ok(QueryContractInfoResponse::decode(
         rpc_query(
             &http_client,
             QueryContractInfoRequest {
-                address: contract_address.into(),
+                address: contract_address.to_string(),
             },
             "/cosmwasm.wasm.v1.Query/ContractInfo",
         )?
@@ -50,9 +118,20 @@ pub fn download_wasm_from_contract_address(
     .ok_or(ArtifactError::Generic(
         "failed to query contract info".to_string(),
     ))?
-    .code_id;
+    .code_id)
+}
 
-    download_wasm_from_code_id(rpc_endpoint, code_id)
+/// Returns a client for `rpc_endpoint`. Clients are cached per endpoint so that connections are
+/// reused between downloads.
+fn http_client(rpc_endpoint: &str) -> Result<HttpClient, ArtifactError> {
+    static CLIENTS: OnceLock<Mutex<HashMap<String, HttpClient>>> = OnceLock::new();
+    let mut clients = CLIENTS.get_or_init(Default::default).lock().unwrap();
+    if let Some(client) = clients.get(rpc_endpoint) {
+        return Ok(client.clone());
+    }
+    let client = HttpClient::new(rpc_endpoint)?;
+    clients.insert(rpc_endpoint.to_string(), client.clone());
+    Ok(client)
 }
 
 fn rpc_query<T: Message>(
@@ -62,20 +141,120 @@ fn rpc_query<T: Message>(
 ) -> Result<AbciQuery, ArtifactError> {
     let mut buf = Vec::with_capacity(req.encoded_len());
     req.encode(&mut buf).unwrap();
-    Ok(block_on(client.abci_query(
-        Some(path.parse().unwrap()),
-        buf,
-        None,
-        false,
-    ))?)
+    let res = block_on(client.abci_query(Some(path.parse().unwrap()), buf, None, false))?;
+    // Failed queries must not be decoded, or an empty response would be cached as the result
+    if res.code.is_err() {
+        return Err(ArtifactError::Generic(format!(
+            "query {} failed: {}",
+            path, res.log
+        )));
+    }
+    Ok(res)
 }
 
-// Commented out because the RPC node goes down sometimes which breaks CI
-// #[test]
-// fn test_rpc_query() {
-//     let rpc_endpoint = "https://rpc.osmosis.zone/".to_string();
-//     let http_client = HttpClient::new(rpc_endpoint.as_str()).unwrap();
-//     let req = QueryCodeRequest { code_id: 1 };
-//     let res = rpc_query(&http_client, req, "/cosmwasm.wasm.v1.Query/ContractInfo").unwrap();
-//     println!("{:?}", res);
-// }
+#[cfg(all(test, feature = "rpc-facade"))]
+mod tests {
+    use cosmrs::proto::cosmwasm::wasm::v1::ContractInfo;
+    use cosmrs::tendermint::abci::types::ExecTxResult;
+
+    use super::*;
+    use crate::rpc_facade::{AbciError, FacadeBackend, RpcFacade};
+
+    const CODE_ID: u64 = 7;
+    const CONTRACT: &str = "osmo14hj2tavq8fpesdwxxcu44rty3hh90vhujrvcmstl4zr3txmfvw9sq2r9g9";
+    const WASM: &[u8] = b"\0asm";
+
+    /// Serves the code and contract info of a single contract.
+    struct ContractBackend;
+
+    impl FacadeBackend for ContractBackend {
+        fn abci_query(&mut self, path: &str, data: &[u8]) -> Result<Vec<u8>, AbciError> {
+            match path {
+                "/cosmwasm.wasm.v1.Query/Code" => {
+                    let req = QueryCodeRequest::decode(data).unwrap();
+                    if req.code_id != CODE_ID {
+                        return Err(AbciError::invalid_request("not found"));
+                    }
+                    Ok(QueryCodeResponse {
+                        code_info: None,
+                        data: WASM.to_vec(),
+                    }
+                    .encode_to_vec())
+                }
+                "/cosmwasm.wasm.v1.Query/ContractInfo" => Ok(QueryContractInfoResponse {
+                    address: CONTRACT.to_string(),
+                    contract_info: Some(ContractInfo {
+                        code_id: CODE_ID,
+                        ..Default::default()
+                    }),
+                }
+                .encode_to_vec()),
+                _ => Err(AbciError::unknown_request(path)),
+            }
+        }
+
+        fn deliver_tx(&mut self, _tx: &[u8]) -> ExecTxResult {
+            ExecTxResult::default()
+        }
+
+        fn block_time_nanos(&self) -> u64 {
+            0
+        }
+    }
+
+    #[test]
+    fn download_from_code_id() {
+        let facade = RpcFacade::start("cw-it-1", || ContractBackend).unwrap();
+        let endpoint = facade.endpoint();
+        let (_dir, mut cache) = Cache::temp();
+
+        let wasm = download_wasm_from_code_id_cached(&endpoint, CODE_ID, &cache).unwrap();
+        assert_eq!(wasm, WASM);
+
+        // Served from the cache once the chain is gone
+        drop(facade);
+        cache.offline = true;
+        let wasm = download_wasm_from_code_id_cached(&endpoint, CODE_ID, &cache).unwrap();
+        assert_eq!(wasm, WASM);
+    }
+
+    #[test]
+    fn failed_query_is_not_cached() {
+        let facade = RpcFacade::start("cw-it-1", || ContractBackend).unwrap();
+        let (_dir, cache) = Cache::temp();
+
+        let err =
+            download_wasm_from_code_id_cached(&facade.endpoint(), CODE_ID + 1, &cache).unwrap_err();
+        assert!(matches!(err, ArtifactError::Generic(_)));
+        assert!(!cache.dir.exists());
+    }
+
+    #[test]
+    fn offline_without_cache() {
+        let (_dir, mut cache) = Cache::temp();
+        cache.offline = true;
+
+        let err =
+            download_wasm_from_code_id_cached("http://127.0.0.1:1", CODE_ID, &cache).unwrap_err();
+        assert!(matches!(err, ArtifactError::Offline(_)));
+        let err =
+            download_wasm_from_contract_address_cached("http://127.0.0.1:1", CONTRACT, &cache)
+                .unwrap_err();
+        assert!(matches!(err, ArtifactError::Offline(_)));
+    }
+
+    #[test]
+    fn download_from_contract_address() {
+        let facade = RpcFacade::start("cw-it-1", || ContractBackend).unwrap();
+        let endpoint = facade.endpoint();
+        let (_dir, cache) = Cache::temp();
+
+        let wasm = download_wasm_from_contract_address_cached(&endpoint, CONTRACT, &cache).unwrap();
+        assert_eq!(wasm, WASM);
+
+        // Falls back to the cached code id if the chain can't be queried
+        drop(facade);
+        let wasm = download_wasm_from_contract_address_cached(&endpoint, CONTRACT, &cache).unwrap();
+        assert_eq!(wasm, WASM);
+    }
+}
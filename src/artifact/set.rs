@@ -0,0 +1,333 @@
+use std::collections::{BTreeMap, HashMap};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::Path;
+
+use serde::Serialize;
+use test_tube::SigningAccount;
+
+use super::{Artifact, ArtifactError, ContractMap, ContractType};
+use crate::error::CwItError;
+use crate::helpers::{migrate_contract, upload_wasm_files};
+use crate::traits::CwItRunner;
+
+/// Code ids by contract name, as returned by [`ArtifactSet::upload`] and [`ArtifactSet::migrate`].
+pub type CodeIds = HashMap<String, u64>;
+
+/// Named versions of a set of contracts, e.g. two releases of a protocol, to test migrations
+/// from one version to the next and to run the same test against every version.
+///
+/// Versions are ordered as they are added, so they should be added oldest first.
+#[derive(Clone, Debug, Default)]
+pub struct ArtifactSet {
+    versions: Vec<(String, BTreeMap<String, Artifact>)>,
+}
+
+impl ArtifactSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the version `version` with the given artifacts by contract name. Replaces the version
+    /// if it was already added.
+    pub fn with_version<N: Into<String>>(
+        mut self,
+        version: impl Into<String>,
+        artifacts: impl IntoIterator<Item = (N, Artifact)>,
+    ) -> Self {
+        let version = version.into();
+        let artifacts = artifacts
+            .into_iter()
+            .map(|(name, artifact)| (name.into(), artifact))
+            .collect();
+        match self.versions.iter_mut().find(|(v, _)| *v == version) {
+            Some((_, existing)) => *existing = artifacts,
+            None => self.versions.push((version, artifacts)),
+        }
+        self
+    }
+
+    /// Adds the version `version` with the wasm files `<dir>/<name>.wasm` of the contracts in
+    /// `names`.
+    pub fn with_local_version<S: AsRef<str>>(
+        self,
+        version: impl Into<String>,
+        dir: impl AsRef<Path>,
+        names: impl IntoIterator<Item = S>,
+    ) -> Self {
+        let dir = dir.as_ref();
+        let artifacts = names.into_iter().map(|name| {
+            let path = dir.join(format!("{}.wasm", name.as_ref()));
+            (
+                name.as_ref().to_string(),
+                Artifact::Local(path.to_string_lossy().to_string()),
+            )
+        });
+        self.with_version(version, artifacts)
+    }
+
+    /// Returns the names of the versions, oldest first.
+    pub fn versions(&self) -> impl Iterator<Item = &str> {
+        self.versions.iter().map(|(version, _)| version.as_str())
+    }
+
+    /// Returns the pairs of consecutive versions, i.e. the upgrades to test.
+    pub fn upgrades(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.versions
+            .windows(2)
+            .map(|pair| (pair[0].0.as_str(), pair[1].0.as_str()))
+    }
+
+    /// Returns the artifacts of `version` by contract name.
+    pub fn artifacts(&self, version: &str) -> Result<&BTreeMap<String, Artifact>, ArtifactError> {
+        self.versions
+            .iter()
+            .find(|(v, _)| v == version)
+            .map(|(_, artifacts)| artifacts)
+            .ok_or_else(|| ArtifactError::UnknownVersion(version.to_string()))
+    }
+
+    /// Returns the contracts of `version` as a [`ContractMap`].
+    pub fn contract_map(&self, version: &str) -> Result<ContractMap, ArtifactError> {
+        Ok(self
+            .artifacts(version)?
+            .iter()
+            .map(|(name, artifact)| (name.clone(), ContractType::Artifact(artifact.clone())))
+            .collect())
+    }
+
+    /// Uploads all contracts of `version` and returns their code ids.
+    pub fn upload<'a, R: CwItRunner<'a>>(
+        &self,
+        runner: &'a R,
+        version: &str,
+        signer: &SigningAccount,
+    ) -> Result<CodeIds, CwItError> {
+        upload_wasm_files(runner, signer, self.contract_map(version)?)
+    }
+
+    /// Uploads the code of `version` for every contract in `contracts`, which maps contract names
+    /// to addresses, and migrates the contracts to it with the message returned by `migrate_msg`
+    /// for the contract name. `admin` must be the admin of the contracts. Returns the new code
+    /// ids.
+    pub fn migrate<'a, R, M, F>(
+        &self,
+        runner: &'a R,
+        version: &str,
+        contracts: &HashMap<String, String>,
+        migrate_msg: F,
+        admin: &SigningAccount,
+    ) -> Result<CodeIds, CwItError>
+    where
+        R: CwItRunner<'a>,
+        M: Serialize,
+        F: Fn(&str) -> M,
+    {
+        let artifacts = self.artifacts(version)?;
+        let mut code_ids = CodeIds::new();
+        for (name, address) in contracts {
+            let artifact = artifacts.get(name).ok_or_else(|| ArtifactError::NotFound {
+                name: name.clone(),
+                dir: format!("version {}", version),
+            })?;
+            let code_id = runner.store_code(ContractType::Artifact(artifact.clone()), admin)?;
+            migrate_contract(runner, admin, address, code_id, &migrate_msg(name))?;
+            code_ids.insert(name.clone(), code_id);
+        }
+        Ok(code_ids)
+    }
+
+    /// Runs `test` with the contracts of every version. All versions are run even if the test
+    /// fails for one of them, and the test panics afterwards with the versions it failed for.
+    pub fn matrix<F>(&self, mut test: F)
+    where
+        F: FnMut(&str, ContractMap),
+    {
+        let mut failed = vec![];
+        for (version, _) in &self.versions {
+            let contracts = self.contract_map(version).unwrap();
+            if catch_unwind(AssertUnwindSafe(|| test(version, contracts))).is_err() {
+                failed.push(version.as_str());
+            }
+        }
+        if !failed.is_empty() {
+            panic!("test failed for versions: {}", failed.join(", "));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTRACTS: [&str; 2] = ["astroport_factory", "astroport_pair"];
+
+    fn astroport_versions() -> ArtifactSet {
+        ArtifactSet::new()
+            .with_local_version("c73a2db", "artifacts/c73a2db", CONTRACTS)
+            .with_local_version("4d3be0e", "artifacts/4d3be0e", CONTRACTS)
+    }
+
+    #[test]
+    fn versions() {
+        let set = astroport_versions().with_version(
+            "local",
+            [("counter", Artifact::Local("artifacts/counter.wasm".into()))],
+        );
+
+        assert_eq!(
+            set.versions().collect::<Vec<_>>(),
+            vec!["c73a2db", "4d3be0e", "local"]
+        );
+        assert_eq!(
+            set.upgrades().collect::<Vec<_>>(),
+            vec![("c73a2db", "4d3be0e"), ("4d3be0e", "local")]
+        );
+
+        let contracts = set.contract_map("4d3be0e").unwrap();
+        assert_eq!(contracts.len(), 2);
+        for contract in contracts.values() {
+            match contract {
+                ContractType::Artifact(artifact) => {
+                    assert!(!artifact.get_wasm_byte_code().unwrap().is_empty())
+                }
+                #[allow(unreachable_patterns)]
+                _ => panic!("unexpected contract: {:?}", contract),
+            }
+        }
+
+        assert!(matches!(
+            set.contract_map("v3"),
+            Err(ArtifactError::UnknownVersion(_))
+        ));
+    }
+
+    #[test]
+    fn replace_version() {
+        let set = astroport_versions().with_local_version(
+            "c73a2db",
+            "artifacts/c73a2db",
+            ["astroport_factory"],
+        );
+
+        assert_eq!(set.versions().count(), 2);
+        assert_eq!(set.artifacts("c73a2db").unwrap().len(), 1);
+    }
+
+    #[cfg(feature = "multi-test")]
+    #[test]
+    fn upload_instantiate_and_migrate() {
+        use cosmwasm_std::{Addr, Empty};
+        use test_tube::{Account, Module, Wasm};
+
+        use crate::multi_test::MultiTestRunner;
+
+        let v1 = Artifact::Bytes(b"counter v1".to_vec());
+        let v2 = Artifact::Bytes(b"counter v2".to_vec());
+        let set = ArtifactSet::new()
+            .with_version("v1", [("counter", v1.clone())])
+            .with_version("v2", [("counter", v2.clone())]);
+        let runner = MultiTestRunner::new("osmo")
+            .with_artifact_contract(v1, versioned_counter::v1)
+            .with_artifact_contract(v2, versioned_counter::v2);
+        let admin = runner.init_default_account().unwrap();
+        let wasm = Wasm::new(&runner);
+
+        let code_ids = set.upload(&runner, "v1", &admin).unwrap();
+        let address = wasm
+            .instantiate(
+                code_ids["counter"],
+                &5u64,
+                Some(&admin.address()),
+                Some("counter"),
+                &[],
+                &admin,
+            )
+            .unwrap()
+            .data
+            .address;
+
+        let contracts = HashMap::from([("counter".to_string(), address.clone())]);
+        let new_code_ids = set
+            .migrate(&runner, "v2", &contracts, |_| 10u64, &admin)
+            .unwrap();
+        assert_ne!(new_code_ids["counter"], code_ids["counter"]);
+
+        let contract = runner
+            .app
+            .contract_data(&Addr::unchecked(&address))
+            .unwrap();
+        assert_eq!(contract.code_id, new_code_ids["counter"] as usize);
+        let count: u64 = wasm.query(&address, &Empty {}).unwrap();
+        assert_eq!(count, 50);
+    }
+
+    /// A counter whose second version multiplies the count by the migrate message.
+    #[cfg(feature = "multi-test")]
+    mod versioned_counter {
+        use apollo_cw_multi_test::{Contract, ContractWrapper};
+        use cosmwasm_std::{
+            from_json, to_json_vec, Binary, Deps, DepsMut, Empty, Env, MessageInfo, Response,
+            StdResult,
+        };
+
+        const COUNT_KEY: &[u8] = b"count";
+
+        fn instantiate(deps: DepsMut, _: Env, _: MessageInfo, count: u64) -> StdResult<Response> {
+            deps.storage.set(COUNT_KEY, &to_json_vec(&count)?);
+            Ok(Response::new())
+        }
+
+        fn execute(_: DepsMut, _: Env, _: MessageInfo, _: Empty) -> StdResult<Response> {
+            Ok(Response::new())
+        }
+
+        fn query(deps: Deps, _: Env, _: Empty) -> StdResult<Binary> {
+            Ok(deps.storage.get(COUNT_KEY).unwrap_or_default().into())
+        }
+
+        fn migrate(deps: DepsMut, _: Env, factor: u64) -> StdResult<Response> {
+            let count: u64 = from_json(deps.storage.get(COUNT_KEY).unwrap_or_default())?;
+            deps.storage
+                .set(COUNT_KEY, &to_json_vec(&(count * factor))?);
+            Ok(Response::new())
+        }
+
+        pub fn v1() -> Box<dyn Contract<Empty>> {
+            Box::new(ContractWrapper::new_with_empty(execute, instantiate, query))
+        }
+
+        pub fn v2() -> Box<dyn Contract<Empty>> {
+            Box::new(
+                ContractWrapper::new_with_empty(execute, instantiate, query).with_migrate(migrate),
+            )
+        }
+    }
+
+    #[test]
+    fn matrix_runs_every_version() {
+        let mut runs = vec![];
+        astroport_versions().matrix(|version, contracts| {
+            assert_eq!(contracts.len(), 2);
+            runs.push(version.to_string());
+        });
+        assert_eq!(runs, vec!["c73a2db", "4d3be0e"]);
+    }
+
+    #[test]
+    fn matrix_reports_failed_versions() {
+        let mut runs = 0;
+        let res = catch_unwind(AssertUnwindSafe(|| {
+            astroport_versions().matrix(|version, _| {
+                runs += 1;
+                assert_ne!(version, "c73a2db");
+            })
+        }));
+
+        assert_eq!(runs, 2);
+        let err = res.unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<String>().unwrap(),
+            "test failed for versions: c73a2db"
+        );
+    }
+}
@@ -0,0 +1,185 @@
+use std::io::Read;
+use std::time::Duration;
+
+use super::cache::{sha256_hex, Cache};
+use super::ArtifactError;
+
+/// Timeout of a whole download.
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Downloads the wasm file at `url`, or reads it from the [`cache`](super::cache).
+///
+/// If `sha256` is set, the hex encoded sha256 hash of the downloaded file must match it. Files are
+/// returned as they are served, so gzipped files are not decompressed.
+pub fn download_wasm_from_url(url: &str, sha256: Option<&str>) -> Result<Vec<u8>, ArtifactError> {
+    download_wasm_from_url_cached(url, sha256, &Cache::from_env())
+}
+
+fn download_wasm_from_url_cached(
+    url: &str,
+    sha256: Option<&str>,
+    cache: &Cache,
+) -> Result<Vec<u8>, ArtifactError> {
+    let cached = match sha256 {
+        Some(sha256) => cache.read_blob(sha256),
+        None => cache.read(&url_key(url)),
+    };
+    if let Some(bytes) = cached {
+        return Ok(bytes);
+    }
+
+    cache.check_online(url)?;
+    let bytes = download(url)?;
+    match sha256 {
+        Some(expected) => {
+            let actual = sha256_hex(&bytes);
+            if actual != expected.to_lowercase() {
+                return Err(ArtifactError::ChecksumMismatch {
+                    artifact: url.to_string(),
+                    expected: expected.to_lowercase(),
+                    actual,
+                });
+            }
+            cache.write_blob(&bytes)?;
+        }
+        None => cache.write(&url_key(url), &bytes)?,
+    }
+
+    Ok(bytes)
+}
+
+fn url_key(url: &str) -> String {
+    format!("url-{}", sha256_hex(url.as_bytes()))
+}
+
+fn download(url: &str) -> Result<Vec<u8>, ArtifactError> {
+    let response = ureq::AgentBuilder::new()
+        .timeout(DOWNLOAD_TIMEOUT)
+        .build()
+        .get(url)
+        .call()
+        .map_err(Box::new)?;
+
+    let mut bytes = vec![];
+    response.into_reader().read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    use super::*;
+
+    const WASM: &[u8] = b"\0asm\x01\0\0\0";
+
+    /// Serves `body` for every request and returns the URL and the number of requests served.
+    /// Connections that fail are dropped, so the client sees an error instead of the server
+    /// thread panicking.
+    fn serve(body: Vec<u8>) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/contract.wasm", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+
+        thread::spawn({
+            let requests = requests.clone();
+            move || {
+                for stream in listener.incoming() {
+                    let Ok(mut stream) = stream else { continue };
+                    if let Err(e) = respond(&mut stream, &body) {
+                        eprintln!("test server failed to respond: {}", e);
+                    }
+                    requests.fetch_add(1, Ordering::SeqCst);
+                }
+            }
+        });
+
+        (url, requests)
+    }
+
+    /// Reads the request headers from `stream` and responds with `body`.
+    fn respond(stream: &mut TcpStream, body: &[u8]) -> std::io::Result<()> {
+        let mut reader = BufReader::new(&mut *stream);
+        let mut line = String::new();
+        while reader.read_line(&mut line)? > 2 {
+            line.clear();
+        }
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            body.len()
+        )?;
+        stream.write_all(body)
+    }
+
+    #[test]
+    fn download_and_cache() {
+        let (url, requests) = serve(WASM.to_vec());
+        let (_dir, mut cache) = Cache::temp();
+        let sha256 = sha256_hex(WASM).to_uppercase();
+
+        for _ in 0..2 {
+            let wasm = download_wasm_from_url_cached(&url, Some(&sha256), &cache).unwrap();
+            assert_eq!(wasm, WASM);
+        }
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        // Without a hash the file is cached by URL
+        download_wasm_from_url_cached(&url, None, &cache).unwrap();
+        download_wasm_from_url_cached(&url, None, &cache).unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+        // Offline, cached files are still served
+        cache.offline = true;
+        download_wasm_from_url_cached(&url, Some(&sha256), &cache).unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn offline() {
+        let (url, requests) = serve(WASM.to_vec());
+        let (_dir, mut cache) = Cache::temp();
+        cache.offline = true;
+
+        let err = download_wasm_from_url_cached(&url, None, &cache).unwrap_err();
+        assert!(matches!(err, ArtifactError::Offline(_)));
+        assert_eq!(requests.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn checksum_mismatch() {
+        let (url, _) = serve(WASM.to_vec());
+        let (_dir, cache) = Cache::temp();
+        let expected = sha256_hex(b"other");
+
+        let err = download_wasm_from_url_cached(&url, Some(&expected), &cache).unwrap_err();
+        match err {
+            ArtifactError::ChecksumMismatch {
+                expected: e,
+                actual,
+                ..
+            } => {
+                assert_eq!(e, expected);
+                assert_eq!(actual, sha256_hex(WASM));
+            }
+            err => panic!("unexpected error: {}", err),
+        }
+        assert!(cache.read_blob(&expected).is_none());
+    }
+
+    #[test]
+    fn gzipped() {
+        let gzipped = crate::artifact::gzip::compress(WASM).unwrap();
+        let (url, _) = serve(gzipped.clone());
+        let (_dir, cache) = Cache::temp();
+
+        // The hash is of the file as it is served, and it is returned as is
+        let bytes =
+            download_wasm_from_url_cached(&url, Some(&sha256_hex(&gzipped)), &cache).unwrap();
+        assert_eq!(bytes, gzipped);
+    }
+}
@@ -859,7 +859,7 @@ mod tests {
     /// against the configured docker container.
     #[cfg(feature = "rpc-runner")]
     fn get_rpc_runner<'a>() -> OwnedTestRunner<'a> {
-        let rpc_runner_config = RpcRunnerConfig::from_yaml(TEST_CONFIG_PATH);
+        let rpc_runner_config = RpcRunnerConfig::from_yaml(TEST_CONFIG_PATH).unwrap();
 
         let runner = RpcRunner::new(rpc_runner_config).unwrap();
         OwnedTestRunner::RpcRunner(runner)
@@ -0,0 +1,363 @@
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+
+use cosmwasm_std::{from_json, Coin, Empty};
+use osmosis_std::types::cosmwasm::wasm::v1::{
+    MsgExecuteContractResponse, MsgMigrateContractResponse, MsgUpdateAdmin, MsgUpdateAdminResponse,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use test_tube::{Account, Module, RunnerExecuteResult, RunnerResult, SigningAccount, Wasm};
+
+use crate::artifact::ContractType;
+use crate::helpers::migrate_contract;
+use crate::traits::CwItRunner;
+
+/// A typed client for an instantiated contract, with the message types of the contract as type
+/// parameters. Works with any [`CwItRunner`].
+///
+/// ```ignore
+/// type Counter<'a, R> = ContractClient<'a, R, InstantiateMsg, ExecuteMsg, QueryMsg, MigrateMsg>;
+///
+/// let code_id = Counter::store_code(&runner, contract, &admin)?;
+/// let counter = Counter::instantiate(&runner, code_id, &InstantiateMsg { count: 0 }, Some(&admin.address()), None, &[], &admin)?;
+/// counter.execute(&ExecuteMsg::Increment {}, &[], &admin)?;
+/// let res: GetCountResponse = counter.query(&QueryMsg::GetCount {})?;
+/// ```
+pub struct ContractClient<'a, R, I = Empty, E = Empty, Q = Empty, M = Empty> {
+    runner: &'a R,
+    /// The code id the contract is running.
+    pub code_id: u64,
+    /// The address of the contract.
+    pub address: String,
+    msgs: PhantomData<(I, E, Q, M)>,
+}
+
+impl<'a, R, I, E, Q, M> ContractClient<'a, R, I, E, Q, M>
+where
+    R: CwItRunner<'a>,
+    I: Serialize,
+    E: Serialize,
+    Q: Serialize,
+    M: Serialize,
+{
+    /// Creates a client for the contract at `address` running the code `code_id`.
+    pub fn new(runner: &'a R, code_id: u64, address: impl Into<String>) -> Self {
+        Self {
+            runner,
+            code_id,
+            address: address.into(),
+            msgs: PhantomData,
+        }
+    }
+
+    /// Stores the code of the contract and returns the code id.
+    pub fn store_code(
+        runner: &'a R,
+        contract: ContractType,
+        signer: &SigningAccount,
+    ) -> Result<u64, anyhow::Error> {
+        runner.store_code(contract, signer)
+    }
+
+    /// Instantiates the code `code_id` with `msg` and returns a client for the new contract.
+    pub fn instantiate(
+        runner: &'a R,
+        code_id: u64,
+        msg: &I,
+        admin: Option<&str>,
+        label: Option<&str>,
+        funds: &[Coin],
+        signer: &SigningAccount,
+    ) -> RunnerResult<Self> {
+        let res = Wasm::new(runner).instantiate(code_id, msg, admin, label, funds, signer)?;
+        Ok(Self::new(runner, code_id, res.data.address))
+    }
+
+    /// Returns the runner the client uses.
+    pub fn runner(&self) -> &'a R {
+        self.runner
+    }
+
+    /// Executes `msg` on the contract, sending `funds` with it.
+    pub fn execute(
+        &self,
+        msg: &E,
+        funds: &[Coin],
+        signer: &SigningAccount,
+    ) -> RunnerExecuteResult<MsgExecuteContractResponse> {
+        Wasm::new(self.runner).execute(&self.address, msg, funds, signer)
+    }
+
+    /// Queries the contract with `msg`.
+    pub fn query<T: DeserializeOwned>(&self, msg: &Q) -> RunnerResult<T> {
+        Wasm::new(self.runner).query(&self.address, msg)
+    }
+
+    /// Queries the contract with a [`TypedQuery`], returning its response type.
+    pub fn query_typed<T: TypedQuery<Q>>(&self, query: T) -> RunnerResult<T::Response> {
+        self.query(&query.into_query())
+    }
+
+    /// Migrates the contract to the code `new_code_id` with `msg`. `admin` must be the admin of
+    /// the contract.
+    pub fn migrate(
+        &mut self,
+        msg: &M,
+        new_code_id: u64,
+        admin: &SigningAccount,
+    ) -> RunnerExecuteResult<MsgMigrateContractResponse> {
+        let res = migrate_contract(self.runner, admin, &self.address, new_code_id, msg)?;
+        self.code_id = new_code_id;
+        Ok(res)
+    }
+
+    /// Sets the admin of the contract to `new_admin`. `admin` must be the current admin.
+    pub fn update_admin(
+        &self,
+        new_admin: &str,
+        admin: &SigningAccount,
+    ) -> RunnerExecuteResult<MsgUpdateAdminResponse> {
+        self.runner.execute(
+            MsgUpdateAdmin {
+                sender: admin.address(),
+                new_admin: new_admin.to_string(),
+                contract: self.address.clone(),
+            },
+            MsgUpdateAdmin::TYPE_URL,
+            admin,
+        )
+    }
+
+    /// Returns the value stored under `key` in the raw storage of the contract.
+    pub fn query_raw(&self, key: &[u8]) -> Result<Option<Vec<u8>>, anyhow::Error> {
+        self.runner.query_wasm_raw(&self.address, key)
+    }
+
+    /// Returns the JSON value stored under `key` in the raw storage of the contract, e.g. of a
+    /// `cw_storage_plus::Item`.
+    pub fn query_raw_json<T: DeserializeOwned>(
+        &self,
+        key: &[u8],
+    ) -> Result<Option<T>, anyhow::Error> {
+        Ok(self.query_raw(key)?.map(from_json).transpose()?)
+    }
+
+    /// Returns all key-value pairs in the raw storage of the contract.
+    pub fn query_raw_all(&self) -> Result<BTreeMap<Vec<u8>, Vec<u8>>, anyhow::Error> {
+        self.runner.query_wasm_raw_all(&self.address)
+    }
+}
+
+/// A query of the contract with the query message type `Q` whose response type is known, so the
+/// response type of [`ContractClient::query_typed`] is inferred. Implemented by the types that
+/// [`typed_queries!`](crate::typed_queries) generates for the variants of `Q`.
+pub trait TypedQuery<Q> {
+    /// The type the contract responds to the query with.
+    type Response: DeserializeOwned;
+
+    /// Returns the query message.
+    fn into_query(self) -> Q;
+}
+
+/// Generates a struct implementing [`TypedQuery`] for each listed variant of a query message
+/// enum, with the fields of the variant, so that [`ContractClient::query_typed`] infers the
+/// response type. The response types should be the ones declared with `#[returns(...)]`.
+///
+/// ```ignore
+/// mod queries {
+///     use counter::msg::{ConfigResponse, GetCountResponse, QueryMsg};
+///
+///     cw_it::typed_queries! {
+///         QueryMsg {
+///             GetCount {} => GetCountResponse,
+///             Config { at_height: Option<u64> } => ConfigResponse,
+///         }
+///     }
+/// }
+///
+/// let res = counter.query_typed(queries::GetCount {})?;
+/// assert_eq!(res.count, 1);
+/// ```
+#[macro_export]
+macro_rules! typed_queries {
+    (
+        $query_msg:path {
+            $(
+                $variant:ident { $($field:ident : $field_ty:ty),* $(,)? } => $response:ty
+            ),* $(,)?
+        }
+    ) => {
+        $(
+            #[derive(Clone, Debug, PartialEq)]
+            pub struct $variant {
+                $(pub $field: $field_ty),*
+            }
+
+            impl $crate::client::TypedQuery<$query_msg> for $variant {
+                type Response = $response;
+
+                fn into_query(self) -> $query_msg {
+                    type Msg = $query_msg;
+                    Msg::$variant {
+                        $($field: self.$field),*
+                    }
+                }
+            }
+        )*
+    };
+}
+
+#[cfg(feature = "multi-test")]
+#[cfg(test)]
+mod tests {
+    use apollo_cw_multi_test::ContractWrapper;
+    use cosmwasm_std::{
+        to_json_binary, to_json_vec, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult,
+    };
+
+    use super::*;
+    use crate::multi_test::MultiTestRunner;
+    use crate::test_helpers::counter::{ExecuteMsg, GetCountResponse, InstantiateMsg, QueryMsg};
+
+    const COUNT_KEY: &[u8] = b"count";
+
+    mod queries {
+        use cosmwasm_schema::cw_serde;
+        use cosmwasm_std::Uint128;
+
+        use crate::test_helpers::counter::{GetCountResponse, QueryMsg};
+
+        typed_queries! {
+            QueryMsg {
+                GetCount {} => GetCountResponse,
+            }
+        }
+
+        #[cw_serde]
+        pub enum TokenQueryMsg {
+            Balance { address: String, denom: String },
+        }
+
+        typed_queries! {
+            self::TokenQueryMsg {
+                Balance { address: String, denom: String } => Uint128,
+            }
+        }
+    }
+
+    type Counter<'a, 'r> =
+        ContractClient<'a, MultiTestRunner<'r>, InstantiateMsg, ExecuteMsg, QueryMsg, Empty>;
+
+    fn instantiate(
+        deps: DepsMut,
+        _: Env,
+        _: MessageInfo,
+        msg: InstantiateMsg,
+    ) -> StdResult<Response> {
+        deps.storage.set(COUNT_KEY, &to_json_vec(&msg.count)?);
+        Ok(Response::new())
+    }
+
+    fn execute(deps: DepsMut, _: Env, _: MessageInfo, msg: ExecuteMsg) -> StdResult<Response> {
+        let count: i32 = from_json(deps.storage.get(COUNT_KEY).unwrap())?;
+        let count = match msg {
+            ExecuteMsg::Increment {} => count + 1,
+            ExecuteMsg::Reset { count } => count,
+        };
+        deps.storage.set(COUNT_KEY, &to_json_vec(&count)?);
+        Ok(Response::new())
+    }
+
+    fn query(deps: Deps, _: Env, msg: QueryMsg) -> StdResult<Binary> {
+        match msg {
+            QueryMsg::GetCount {} => to_json_binary(&GetCountResponse {
+                count: from_json(deps.storage.get(COUNT_KEY).unwrap())?,
+            }),
+        }
+    }
+
+    fn migrate(_: DepsMut, _: Env, _: Empty) -> StdResult<Response> {
+        Ok(Response::new())
+    }
+
+    fn counter() -> ContractType {
+        ContractType::MultiTestContract(Box::new(
+            ContractWrapper::new_with_empty(execute, instantiate, query).with_migrate(migrate),
+        ))
+    }
+
+    #[test]
+    fn instantiate_execute_query() {
+        let runner = MultiTestRunner::new("osmo");
+        let admin = runner.init_default_account().unwrap();
+
+        let code_id = Counter::store_code(&runner, counter(), &admin).unwrap();
+        let client = Counter::instantiate(
+            &runner,
+            code_id,
+            &InstantiateMsg { count: 1 },
+            Some(&admin.address()),
+            Some("counter"),
+            &[],
+            &admin,
+        )
+        .unwrap();
+        client
+            .execute(&ExecuteMsg::Increment {}, &[], &admin)
+            .unwrap();
+
+        let res: GetCountResponse = client.query(&QueryMsg::GetCount {}).unwrap();
+        assert_eq!(res.count, 2);
+        assert_eq!(client.query_typed(queries::GetCount {}).unwrap().count, 2);
+
+        assert_eq!(client.query_raw(COUNT_KEY).unwrap(), Some(b"2".to_vec()));
+        assert_eq!(client.query_raw_json::<i32>(COUNT_KEY).unwrap(), Some(2));
+        assert_eq!(client.query_raw_json::<i32>(b"missing").unwrap(), None);
+        assert_eq!(client.query_raw_all().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn typed_query_with_fields() {
+        let query = queries::Balance {
+            address: "osmo1alice".to_string(),
+            denom: "uosmo".to_string(),
+        };
+        assert_eq!(
+            query.into_query(),
+            queries::TokenQueryMsg::Balance {
+                address: "osmo1alice".to_string(),
+                denom: "uosmo".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn migrate_and_update_admin() {
+        let runner = MultiTestRunner::new("osmo");
+        let accounts = runner.init_default_accounts().unwrap();
+        let (admin, new_admin) = (&accounts[0], &accounts[1]);
+
+        let code_id = Counter::store_code(&runner, counter(), admin).unwrap();
+        let new_code_id = Counter::store_code(&runner, counter(), admin).unwrap();
+        let mut client = Counter::instantiate(
+            &runner,
+            code_id,
+            &InstantiateMsg { count: 1 },
+            Some(&admin.address()),
+            None,
+            &[],
+            admin,
+        )
+        .unwrap();
+
+        client.migrate(&Empty {}, new_code_id, admin).unwrap();
+        assert_eq!(client.code_id, new_code_id);
+
+        client.update_admin(&new_admin.address(), admin).unwrap();
+        client.migrate(&Empty {}, code_id, admin).unwrap_err();
+        assert_eq!(client.code_id, new_code_id);
+        client.migrate(&Empty {}, code_id, new_admin).unwrap();
+        assert_eq!(client.code_id, code_id);
+    }
+}
@@ -0,0 +1,468 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use cosmwasm_std::{to_json_vec, Coin, StdResult};
+use osmosis_std::types::cosmos::base::v1beta1::Coin as ProtoCoin;
+use osmosis_std::types::cosmwasm::wasm::v1::{
+    MsgInstantiateContract, MsgInstantiateContractResponse,
+};
+use serde::{Deserialize, Serialize};
+use test_tube::{Account, SigningAccount};
+
+use crate::artifact::ContractMap;
+use crate::error::CwItError;
+use crate::traits::CwItRunner;
+
+type MsgFn<'a> = Box<dyn Fn(&Deployment) -> StdResult<Vec<u8>> + 'a>;
+
+/// A contract to instantiate as part of a [`DeploymentPlan`].
+pub struct DeploymentStep<'a> {
+    /// The name of the deployed contract in the [`Deployment`].
+    pub name: String,
+    /// The name of the contract in the [`ContractMap`] of the plan.
+    pub contract: String,
+    /// The names of the steps that must be deployed before this one.
+    pub depends_on: Vec<String>,
+    /// The funds sent with the instantiate message.
+    pub funds: Vec<Coin>,
+    /// The admin of the contract. Defaults to the account deploying the plan.
+    pub admin: Option<String>,
+    /// The label of the contract. Defaults to the name of the step.
+    pub label: Option<String>,
+    msg: MsgFn<'a>,
+}
+
+impl<'a> DeploymentStep<'a> {
+    /// Creates a step that instantiates the contract `contract` as `name`, with the instantiate
+    /// message returned by `msg`. `msg` gets the contracts deployed so far, which include all
+    /// steps in `depends_on`.
+    pub fn new<M, F>(name: impl Into<String>, contract: impl Into<String>, msg: F) -> Self
+    where
+        M: Serialize,
+        F: Fn(&Deployment) -> M + 'a,
+    {
+        Self {
+            name: name.into(),
+            contract: contract.into(),
+            depends_on: vec![],
+            funds: vec![],
+            admin: None,
+            label: None,
+            msg: Box::new(move |deployment| to_json_vec(&msg(deployment))),
+        }
+    }
+
+    pub fn depends_on<S: Into<String>>(mut self, names: impl IntoIterator<Item = S>) -> Self {
+        self.depends_on.extend(names.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn funds(mut self, funds: &[Coin]) -> Self {
+        self.funds = funds.to_vec();
+        self
+    }
+
+    pub fn admin(mut self, admin: impl Into<String>) -> Self {
+        self.admin = Some(admin.into());
+        self
+    }
+
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+}
+
+/// A plan to deploy a set of contracts that depend on each other.
+///
+/// Deploying the plan uploads all contracts in the [`ContractMap`] and instantiates the steps so
+/// that every step comes after the steps it depends on. Otherwise the steps are instantiated in
+/// the order they were added.
+///
+/// ```ignore
+/// let deployment = DeploymentPlan::new(contracts)
+///     .step(DeploymentStep::new("factory", "astroport_factory", |d| FactoryInstantiateMsg {
+///         token_code_id: d.code_id("astroport_token"),
+///         coin_registry_address: d.address("coin_registry").to_string(),
+///         ..
+///     }).depends_on(["coin_registry"]))
+///     .step(DeploymentStep::new("coin_registry", "astroport_native_coin_registry", |_| {
+///         CoinRegistryInstantiateMsg { owner: admin.address() }
+///     }))
+///     .deploy(&runner, &admin)?;
+/// ```
+pub struct DeploymentPlan<'a> {
+    contracts: ContractMap,
+    steps: Vec<DeploymentStep<'a>>,
+}
+
+impl<'a> DeploymentPlan<'a> {
+    pub fn new(contracts: ContractMap) -> Self {
+        Self {
+            contracts,
+            steps: vec![],
+        }
+    }
+
+    /// Adds `step` to the plan.
+    pub fn step(mut self, step: DeploymentStep<'a>) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    /// Returns the names of the steps in the order they are deployed.
+    ///
+    /// Returns [`CwItError::InvalidDeploymentPlan`] if a name is used twice, a step depends on a
+    /// step that is not in the plan, or the dependencies form a cycle.
+    pub fn order(&self) -> Result<Vec<&str>, CwItError> {
+        let invalid = |msg: String| Err(CwItError::InvalidDeploymentPlan(msg));
+
+        let mut index = BTreeMap::new();
+        for (i, step) in self.steps.iter().enumerate() {
+            if index.insert(step.name.as_str(), i).is_some() {
+                return invalid(format!("step {} is added twice", step.name));
+            }
+        }
+        for step in &self.steps {
+            if let Some(dep) = step
+                .depends_on
+                .iter()
+                .find(|dep| !index.contains_key(dep.as_str()))
+            {
+                return invalid(format!(
+                    "step {} depends on unknown step {}",
+                    step.name, dep
+                ));
+            }
+        }
+
+        // Repeatedly deploy the first step whose dependencies are all deployed
+        let mut deployed = vec![false; self.steps.len()];
+        let mut order = Vec::with_capacity(self.steps.len());
+        while order.len() < self.steps.len() {
+            let next = (0..self.steps.len()).find(|&i| {
+                !deployed[i]
+                    && self.steps[i]
+                        .depends_on
+                        .iter()
+                        .all(|dep| deployed[index[dep.as_str()]])
+            });
+            match next {
+                Some(i) => {
+                    deployed[i] = true;
+                    order.push(self.steps[i].name.as_str());
+                }
+                None => {
+                    let cycle = self
+                        .steps
+                        .iter()
+                        .enumerate()
+                        .filter(|(i, _)| !deployed[*i])
+                        .map(|(_, step)| step.name.as_str())
+                        .collect::<Vec<_>>();
+                    return invalid(format!("dependency cycle between {}", cycle.join(", ")));
+                }
+            }
+        }
+        Ok(order)
+    }
+
+    /// Uploads all contracts and instantiates all steps with `signer`, and returns the deployed
+    /// contracts.
+    pub fn deploy<'r, R: CwItRunner<'r>>(
+        self,
+        runner: &'r R,
+        signer: &SigningAccount,
+    ) -> Result<Deployment, CwItError> {
+        let order = self
+            .order()?
+            .into_iter()
+            .map(String::from)
+            .collect::<Vec<_>>();
+        if let Some(step) = self
+            .steps
+            .iter()
+            .find(|step| !self.contracts.contains_key(&step.contract))
+        {
+            return Err(CwItError::InvalidDeploymentPlan(format!(
+                "contract {} of step {} is not in the contract map",
+                step.contract, step.name
+            )));
+        }
+
+        let mut deployment = Deployment::default();
+        let mut contracts = self.contracts.into_iter().collect::<Vec<_>>();
+        contracts.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (name, contract) in contracts {
+            println!("Uploading {} ...", name);
+            let code_id = runner.store_code(contract, signer)?;
+            deployment.code_ids.insert(name, code_id);
+        }
+
+        let mut steps = self
+            .steps
+            .into_iter()
+            .map(|step| (step.name.clone(), step))
+            .collect::<BTreeMap<_, _>>();
+        for name in order {
+            let step = steps.remove(&name).unwrap();
+            let code_id = deployment.code_ids[&step.contract];
+            let label = step.label.unwrap_or_else(|| step.name.clone());
+            let msg = (step.msg)(&deployment).map_err(|e| {
+                CwItError::InvalidDeploymentPlan(format!(
+                    "failed to serialize instantiate message of {}: {}",
+                    step.name, e
+                ))
+            })?;
+
+            println!("Instantiating {} ...", step.name);
+            let res = runner.execute::<_, MsgInstantiateContractResponse>(
+                MsgInstantiateContract {
+                    sender: signer.address(),
+                    admin: step.admin.unwrap_or_else(|| signer.address()),
+                    code_id,
+                    label: label.clone(),
+                    msg,
+                    funds: step
+                        .funds
+                        .iter()
+                        .map(|c| ProtoCoin {
+                            denom: c.denom.clone(),
+                            amount: c.amount.to_string(),
+                        })
+                        .collect(),
+                },
+                MsgInstantiateContract::TYPE_URL,
+                signer,
+            )?;
+
+            deployment.contracts.insert(
+                step.name,
+                DeployedContract {
+                    contract: step.contract,
+                    code_id,
+                    address: res.data.address,
+                    label,
+                },
+            );
+        }
+
+        Ok(deployment)
+    }
+}
+
+/// A contract instantiated by a [`DeploymentPlan`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeployedContract {
+    /// The name of the contract in the [`ContractMap`] of the plan.
+    pub contract: String,
+    pub code_id: u64,
+    pub address: String,
+    pub label: String,
+}
+
+/// The code ids and contracts deployed by a [`DeploymentPlan`]. Can be saved as JSON, e.g. to
+/// reuse a deployment on a live chain with `RpcRunner`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Deployment {
+    /// The code ids by contract name.
+    pub code_ids: BTreeMap<String, u64>,
+    /// The deployed contracts by step name.
+    pub contracts: BTreeMap<String, DeployedContract>,
+}
+
+impl Deployment {
+    /// Returns the address of the contract deployed by the step `name`. Panics if it is not
+    /// deployed, e.g. because it is missing from the `depends_on` of the step calling this.
+    pub fn address(&self, name: &str) -> &str {
+        match self.contracts.get(name) {
+            Some(contract) => &contract.address,
+            None => panic!(
+                "{} is not deployed yet, is it missing from depends_on?",
+                name
+            ),
+        }
+    }
+
+    /// Returns the code id of the contract `contract`. Panics if it was not uploaded.
+    pub fn code_id(&self, contract: &str) -> u64 {
+        match self.code_ids.get(contract) {
+            Some(code_id) => *code_id,
+            None => panic!("{} is not in the contract map", contract),
+        }
+    }
+
+    /// Returns the deployment as JSON.
+    pub fn to_json(&self) -> StdResult<String> {
+        cosmwasm_std::to_json_string(self)
+    }
+
+    /// Parses a deployment from JSON.
+    pub fn from_json(json: &str) -> StdResult<Self> {
+        cosmwasm_std::from_json(json)
+    }
+
+    /// Writes the deployment as JSON to `path`.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), CwItError> {
+        let json = self.to_json().map_err(anyhow::Error::from)?;
+        fs::write(path, json).map_err(anyhow::Error::from)?;
+        Ok(())
+    }
+
+    /// Reads a deployment saved with [`Deployment::save`] from `path`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, CwItError> {
+        let json = fs::read_to_string(path).map_err(anyhow::Error::from)?;
+        Ok(Self::from_json(&json).map_err(anyhow::Error::from)?)
+    }
+}
+
+#[cfg(feature = "multi-test")]
+#[cfg(test)]
+mod tests {
+    use apollo_cw_multi_test::ContractWrapper;
+    use cosmwasm_schema::cw_serde;
+    use cosmwasm_std::{
+        coin, from_json, to_json_binary, Binary, Deps, DepsMut, Empty, Env, MessageInfo, Response,
+    };
+    use test_tube::{Module, Wasm};
+
+    use super::*;
+    use crate::artifact::ContractType;
+    use crate::helpers::bank_balance_query;
+    use crate::multi_test::MultiTestRunner;
+
+    /// Instantiate message of a contract that stores the addresses of other contracts.
+    #[cw_serde]
+    struct InstantiateMsg {
+        deps: Vec<String>,
+    }
+
+    fn instantiate(
+        deps: DepsMut,
+        _: Env,
+        _: MessageInfo,
+        msg: InstantiateMsg,
+    ) -> StdResult<Response> {
+        deps.storage.set(b"deps", &to_json_vec(&msg.deps)?);
+        Ok(Response::new())
+    }
+
+    fn execute(_: DepsMut, _: Env, _: MessageInfo, _: Empty) -> StdResult<Response> {
+        Ok(Response::new())
+    }
+
+    fn query(deps: Deps, _: Env, _: Empty) -> StdResult<Binary> {
+        let deps: Vec<String> = from_json(deps.storage.get(b"deps").unwrap())?;
+        to_json_binary(&deps)
+    }
+
+    fn contracts() -> ContractMap {
+        ["hub", "spoke"]
+            .into_iter()
+            .map(|name| {
+                let contract = ContractWrapper::new_with_empty(execute, instantiate, query);
+                (
+                    name.to_string(),
+                    ContractType::MultiTestContract(Box::new(contract)),
+                )
+            })
+            .collect()
+    }
+
+    fn spoke(name: &str, deps: &'static [&'static str]) -> DeploymentStep<'static> {
+        DeploymentStep::new(name, "spoke", move |d: &Deployment| InstantiateMsg {
+            deps: deps.iter().map(|dep| d.address(dep).to_string()).collect(),
+        })
+        .depends_on(deps.iter().copied())
+    }
+
+    #[test]
+    fn deploys_in_dependency_order() {
+        let runner = MultiTestRunner::new("osmo");
+        let admin = runner.init_default_account().unwrap();
+
+        let plan = DeploymentPlan::new(contracts())
+            .step(spoke("b", &["a", "hub"]))
+            .step(spoke("a", &["hub"]))
+            .step(
+                DeploymentStep::new("hub", "hub", |_: &Deployment| InstantiateMsg {
+                    deps: vec![],
+                })
+                .funds(&[coin(100, "uosmo")])
+                .label("Hub"),
+            );
+        assert_eq!(plan.order().unwrap(), vec!["hub", "a", "b"]);
+
+        let deployment = plan.deploy(&runner, &admin).unwrap();
+
+        assert_eq!(deployment.code_ids.len(), 2);
+        assert_eq!(deployment.contracts["hub"].label, "Hub");
+        assert_eq!(deployment.contracts["a"].label, "a");
+        assert_eq!(
+            deployment.contracts["b"].code_id,
+            deployment.code_id("spoke")
+        );
+
+        let deps: Vec<String> = Wasm::new(&runner)
+            .query(deployment.address("b"), &Empty {})
+            .unwrap();
+        assert_eq!(
+            deps,
+            vec![deployment.address("a"), deployment.address("hub")]
+        );
+
+        let balance = bank_balance_query(
+            &runner,
+            deployment.address("hub").to_string(),
+            "uosmo".to_string(),
+        )
+        .unwrap();
+        assert_eq!(balance.u128(), 100);
+
+        let json = deployment.to_json().unwrap();
+        assert_eq!(Deployment::from_json(&json).unwrap(), deployment);
+    }
+
+    #[test]
+    fn invalid_plans() {
+        let plan = DeploymentPlan::new(contracts())
+            .step(spoke("a", &["b"]))
+            .step(spoke("b", &["a"]))
+            .step(spoke("c", &[]));
+        let err = plan.order().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid deployment plan: dependency cycle between a, b"
+        );
+
+        let plan = DeploymentPlan::new(contracts()).step(spoke("a", &["hub"]));
+        let err = plan.order().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid deployment plan: step a depends on unknown step hub"
+        );
+
+        let plan = DeploymentPlan::new(contracts())
+            .step(spoke("a", &[]))
+            .step(spoke("a", &[]));
+        assert!(plan.order().is_err());
+    }
+
+    #[test]
+    fn unknown_contract() {
+        let runner = MultiTestRunner::new("osmo");
+        let admin = runner.init_default_account().unwrap();
+
+        let err = DeploymentPlan::new(contracts())
+            .step(DeploymentStep::new("vault", "vault", |_: &Deployment| {
+                Empty {}
+            }))
+            .deploy(&runner, &admin)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid deployment plan: contract vault of step vault is not in the contract map"
+        );
+    }
+}
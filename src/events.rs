@@ -0,0 +1,250 @@
+use std::collections::BTreeMap;
+
+use cosmwasm_std::{Event, StdError, StdResult};
+use serde::Serialize;
+
+use crate::client::ContractClient;
+use crate::traits::CwItRunner;
+
+/// The type of the event emitted for every instantiated contract.
+pub const INSTANTIATE_EVENT: &str = "instantiate";
+
+/// The attributes of the instantiate event with the contract address. wasmd uses the first one
+/// and cw-multi-test the second one.
+const CONTRACT_ADDRESS_ATTRIBUTES: [&str; 2] = ["_contract_address", "_contract_addr"];
+
+/// A contract found in the events of a transaction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InstantiatedContract {
+    pub address: String,
+    pub code_id: u64,
+    /// The label of the contract. Not part of the events, so only set after
+    /// [`InstantiatedContracts::with_labels`].
+    pub label: Option<String>,
+}
+
+impl InstantiatedContract {
+    /// Returns a [`ContractClient`] for the contract with the given message types.
+    pub fn client<'a, R, I, E, Q, M>(&self, runner: &'a R) -> ContractClient<'a, R, I, E, Q, M>
+    where
+        R: CwItRunner<'a>,
+        I: Serialize,
+        E: Serialize,
+        Q: Serialize,
+        M: Serialize,
+    {
+        ContractClient::new(runner, self.code_id, &self.address)
+    }
+}
+
+/// All contracts instantiated in a transaction, e.g. the contracts created by a factory contract,
+/// in the order they were instantiated.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct InstantiatedContracts {
+    pub contracts: Vec<InstantiatedContract>,
+}
+
+impl InstantiatedContracts {
+    /// Collects the contracts from the `instantiate` events in `events`. Returns an error if an
+    /// `instantiate` event has no contract address or code id.
+    pub fn from_events(events: &[Event]) -> StdResult<Self> {
+        let contracts = events
+            .iter()
+            .filter(|event| event.ty == INSTANTIATE_EVENT)
+            .map(|event| {
+                let attribute = |keys: &[&str]| {
+                    event
+                        .attributes
+                        .iter()
+                        .find(|attr| keys.contains(&attr.key.as_str()))
+                        .map(|attr| attr.value.clone())
+                };
+                let address = attribute(&CONTRACT_ADDRESS_ATTRIBUTES).ok_or_else(|| {
+                    StdError::generic_err("instantiate event without contract address")
+                })?;
+                let code_id = attribute(&["code_id"])
+                    .ok_or_else(|| {
+                        StdError::generic_err(format!(
+                            "instantiate event of {} without code id",
+                            address
+                        ))
+                    })?
+                    .parse()
+                    .map_err(|_| {
+                        StdError::generic_err(format!("invalid code id of {}", address))
+                    })?;
+                Ok(InstantiatedContract {
+                    address,
+                    code_id,
+                    label: None,
+                })
+            })
+            .collect::<StdResult<_>>()?;
+        Ok(Self { contracts })
+    }
+
+    /// Queries the labels of the contracts.
+    pub fn with_labels<'a, R: CwItRunner<'a>>(
+        mut self,
+        runner: &'a R,
+    ) -> Result<Self, anyhow::Error> {
+        for contract in &mut self.contracts {
+            contract.label = Some(runner.query_contract_label(&contract.address)?);
+        }
+        Ok(self)
+    }
+
+    pub fn len(&self) -> usize {
+        self.contracts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.contracts.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &InstantiatedContract> {
+        self.contracts.iter()
+    }
+
+    /// Returns the addresses of all contracts, in the order they were instantiated.
+    pub fn addresses(&self) -> Vec<&str> {
+        self.iter()
+            .map(|contract| contract.address.as_str())
+            .collect()
+    }
+
+    /// Returns the contracts of the code `code_id`, in the order they were instantiated.
+    pub fn by_code_id(&self, code_id: u64) -> Vec<&InstantiatedContract> {
+        self.iter()
+            .filter(|contract| contract.code_id == code_id)
+            .collect()
+    }
+
+    /// Returns the first contract of the code `code_id`.
+    pub fn first_by_code_id(&self, code_id: u64) -> Option<&InstantiatedContract> {
+        self.iter().find(|contract| contract.code_id == code_id)
+    }
+
+    /// Returns the contracts grouped by code id.
+    pub fn grouped_by_code_id(&self) -> BTreeMap<u64, Vec<&InstantiatedContract>> {
+        let mut groups: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for contract in self.iter() {
+            groups.entry(contract.code_id).or_default().push(contract);
+        }
+        groups
+    }
+
+    /// Returns the first contract with the label `label`. Requires the labels to be queried with
+    /// [`InstantiatedContracts::with_labels`].
+    pub fn by_label(&self, label: &str) -> Option<&InstantiatedContract> {
+        self.iter()
+            .find(|contract| contract.label.as_deref() == Some(label))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instantiate_event(address_key: &str, address: &str, code_id: &str) -> Event {
+        Event::new(INSTANTIATE_EVENT)
+            .add_attribute(address_key, address)
+            .add_attribute("code_id", code_id)
+    }
+
+    #[test]
+    fn from_events() {
+        let events = vec![
+            Event::new("message").add_attribute("action", "execute"),
+            instantiate_event("_contract_address", "pair", "2"),
+            Event::new("wasm").add_attribute("_contract_address", "factory"),
+            instantiate_event("_contract_addr", "lp_token", "3"),
+            instantiate_event("_contract_address", "other_pair", "2"),
+        ];
+
+        let contracts = InstantiatedContracts::from_events(&events).unwrap();
+
+        assert_eq!(
+            contracts.addresses(),
+            vec!["pair", "lp_token", "other_pair"]
+        );
+        let pairs = contracts.by_code_id(2);
+        assert_eq!(pairs.len(), 2);
+        assert_eq!(pairs[1].address, "other_pair");
+        assert_eq!(contracts.first_by_code_id(3).unwrap().address, "lp_token");
+        assert!(contracts.first_by_code_id(4).is_none());
+
+        let groups = contracts.grouped_by_code_id();
+        assert_eq!(groups.keys().collect::<Vec<_>>(), vec![&2, &3]);
+        assert!(contracts.by_label("pair").is_none());
+    }
+
+    #[test]
+    fn invalid_events() {
+        let events = vec![Event::new(INSTANTIATE_EVENT).add_attribute("code_id", "1")];
+        assert!(InstantiatedContracts::from_events(&events).is_err());
+
+        let events = vec![instantiate_event("_contract_address", "pair", "two")];
+        assert!(InstantiatedContracts::from_events(&events).is_err());
+
+        assert!(InstantiatedContracts::from_events(&[]).unwrap().is_empty());
+    }
+
+    #[cfg(feature = "multi-test")]
+    #[test]
+    fn factory_created_contracts() {
+        use cosmwasm_std::{to_json_binary, WasmMsg};
+        use test_tube::{Module, Wasm};
+
+        use crate::multi_test::MultiTestRunner;
+        use crate::test_helpers::test_contract;
+        use crate::ContractType;
+
+        let runner = MultiTestRunner::new("osmo");
+        let admin = runner.init_default_account().unwrap();
+        let code_id = runner
+            .store_code(
+                ContractType::MultiTestContract(test_contract::contract()),
+                &admin,
+            )
+            .unwrap();
+        let wasm = Wasm::new(&runner);
+        let factory = wasm
+            .instantiate(
+                code_id,
+                &test_contract::EmptyMsg {},
+                None,
+                Some("factory"),
+                &[],
+                &admin,
+            )
+            .unwrap()
+            .data
+            .address;
+
+        // The test contract sends the message it is executed with
+        let res = wasm
+            .execute(
+                &factory,
+                &WasmMsg::Instantiate {
+                    admin: None,
+                    code_id,
+                    msg: to_json_binary(&test_contract::EmptyMsg {}).unwrap(),
+                    funds: vec![],
+                    label: "vault".to_string(),
+                },
+                &[],
+                &admin,
+            )
+            .unwrap();
+
+        let contracts = InstantiatedContracts::from_events(&res.events)
+            .unwrap()
+            .with_labels(&runner)
+            .unwrap();
+        assert_eq!(contracts.len(), 1);
+        let vault = contracts.by_label("vault").unwrap();
+        assert_eq!(vault.code_id, code_id);
+        assert_ne!(vault.address, factory);
+    }
+}
@@ -2,15 +2,23 @@ use std::env;
 use std::fmt::Debug;
 use std::{collections::HashMap, str::FromStr};
 
-use cosmwasm_std::{Coin, StdError, StdResult, Uint128};
+use cosmwasm_std::{
+    instantiate2_address, to_json_vec, CanonicalAddr, Coin, StdError, StdResult, Uint128,
+};
 use osmosis_std::types::cosmos::bank::v1beta1::{
     MsgSend, MsgSendResponse, QueryAllBalancesRequest, QueryAllBalancesResponse,
     QueryBalanceRequest,
 };
 use osmosis_std::types::cosmos::base::query::v1beta1::PageRequest;
 use osmosis_std::types::cosmos::base::v1beta1::Coin as ProtoCoin;
+use osmosis_std::types::cosmwasm::wasm::v1::{
+    MsgInstantiateContract2, MsgInstantiateContract2Response, MsgMigrateContract,
+    MsgMigrateContractResponse,
+};
 use serde::Serialize;
-use test_tube::{Account, Module, Runner, RunnerExecuteResult, RunnerResult, SigningAccount};
+use test_tube::{
+    Account, Module, Runner, RunnerError, RunnerExecuteResult, RunnerResult, SigningAccount,
+};
 use test_tube::{Bank, Wasm};
 
 use crate::error::CwItError;
@@ -20,12 +28,49 @@ use crate::{ArtifactError, ContractType};
 #[cfg(feature = "tokio")]
 use std::future::Future;
 #[cfg(feature = "tokio")]
-pub fn block_on<F: Future>(f: F) -> F::Output {
-    tokio::runtime::Builder::new_current_thread()
-        .enable_all()
-        .build()
-        .unwrap()
-        .block_on(f)
+use std::sync::OnceLock;
+
+/// Returns the tokio runtime shared by all blocking calls in this crate. It is created on first
+/// use and lives until the end of the process, so connections of clients can be reused between
+/// calls.
+#[cfg(feature = "tokio")]
+pub fn runtime() -> &'static tokio::runtime::Runtime {
+    static RUNTIME: OnceLock<tokio::runtime::Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| {
+        tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .thread_name("cw-it-runtime")
+            .enable_all()
+            .build()
+            .expect("failed to create tokio runtime")
+    })
+}
+
+/// Runs the future to completion on the shared [`runtime`] and returns its output.
+///
+/// Can also be called from inside another tokio runtime, e.g. from a `#[tokio::test]`. On a
+/// multi-threaded runtime the current worker thread is handed off with
+/// [`tokio::task::block_in_place`], on a current-thread runtime the future is driven from a
+/// separate thread.
+#[cfg(feature = "tokio")]
+pub fn block_on<F>(f: F) -> F::Output
+where
+    F: Future + Send,
+    F::Output: Send,
+{
+    use tokio::runtime::{Handle, RuntimeFlavor};
+
+    match Handle::try_current() {
+        Err(_) => runtime().block_on(f),
+        Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+            tokio::task::block_in_place(|| runtime().block_on(f))
+        }
+        Ok(_) => std::thread::scope(|s| {
+            s.spawn(|| runtime().block_on(f))
+                .join()
+                .unwrap_or_else(|e| std::panic::resume_unwind(e))
+        }),
+    }
 }
 
 pub fn upload_wasm_files<'a, R: CwItRunner<'a>>(
@@ -84,6 +129,93 @@ where
     instantiate_contract_with_funds(app, admin, code_id, instantite_msg, &[])
 }
 
+/// Instantiates the code `code_id` with `MsgInstantiateContract2`, so that the address of the
+/// contract is derived from the code checksum, the address of `admin` and `salt`. `admin` is the
+/// admin of the contract. The address can be computed beforehand with [`predict_address`].
+pub fn instantiate2_contract<'a, R, M, S>(
+    app: &'a R,
+    admin: &SigningAccount,
+    code_id: u64,
+    instantiate_msg: &M,
+    salt: &[u8],
+    funds: &[Coin],
+) -> RunnerResult<S>
+where
+    R: Runner<'a>,
+    M: Serialize,
+    S: From<String>,
+{
+    let msg = MsgInstantiateContract2 {
+        sender: admin.address(),
+        admin: admin.address(),
+        code_id,
+        label: " ".to_string(),
+        msg: to_json_vec(instantiate_msg).map_err(|e| RunnerError::GenericError(e.to_string()))?,
+        funds: funds
+            .iter()
+            .map(|c| ProtoCoin {
+                denom: c.denom.clone(),
+                amount: c.amount.to_string(),
+            })
+            .collect(),
+        salt: salt.to_vec(),
+        fix_msg: false,
+    };
+
+    println!(
+        "Instantiating contract with code id {} and salt {:?}",
+        code_id, salt
+    );
+    app.execute::<_, MsgInstantiateContract2Response>(msg, MsgInstantiateContract2::TYPE_URL, admin)
+        .map(|r| r.data.address.into())
+}
+
+/// Returns the address of the contract that `creator` instantiates from the code `code_id` with
+/// `MsgInstantiateContract2` and `salt`, as computed by [`cosmwasm_std::instantiate2_address`].
+/// The address has the same bech32 prefix as `creator`.
+pub fn predict_address<'a, R: CwItRunner<'a>>(
+    runner: &'a R,
+    code_id: u64,
+    creator: &str,
+    salt: &[u8],
+) -> Result<String, CwItError> {
+    let checksum = runner.query_code_checksum(code_id)?;
+    let creator = cosmrs::AccountId::from_str(creator)
+        .map_err(|e| anyhow::anyhow!("invalid creator address {}: {}", creator, e))?;
+    let address = instantiate2_address(&checksum, &CanonicalAddr::from(creator.to_bytes()), salt)
+        .map_err(anyhow::Error::from)?;
+    let address = cosmrs::AccountId::new(creator.prefix(), address.as_slice())
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    Ok(address.to_string())
+}
+
+/// Migrates the contract at `contract_addr` to the code `code_id`. `admin` must be the admin of
+/// the contract.
+pub fn migrate_contract<'a, R, M>(
+    app: &'a R,
+    admin: &SigningAccount,
+    contract_addr: &str,
+    code_id: u64,
+    migrate_msg: &M,
+) -> RunnerExecuteResult<MsgMigrateContractResponse>
+where
+    R: Runner<'a>,
+    M: Serialize,
+{
+    let msg = MsgMigrateContract {
+        sender: admin.address(),
+        contract: contract_addr.to_string(),
+        code_id,
+        msg: to_json_vec(migrate_msg).map_err(|e| RunnerError::GenericError(e.to_string()))?,
+    };
+
+    println!(
+        "Migrating contract {} to code id {}",
+        contract_addr, code_id
+    );
+    app.execute(msg, MsgMigrateContract::TYPE_URL, admin)
+}
+
 /// Uploads a wasm file to the chain and returns the code_id
 pub fn upload_wasm_file<'a, R: CwItRunner<'a>>(
     runner: &'a R,
@@ -204,3 +336,39 @@ fn test_unwrap_panic() {
     let res: Result<u32, &str> = Err("random");
     Unwrap::Err("test").unwrap(res);
 }
+
+#[cfg(all(test, feature = "tokio"))]
+mod tests {
+    use super::*;
+
+    async fn sleep_and_add(a: u64, b: u64) -> u64 {
+        tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+        a + b
+    }
+
+    #[test]
+    fn block_on_outside_runtime() {
+        assert_eq!(block_on(sleep_and_add(1, 2)), 3);
+        assert_eq!(block_on(sleep_and_add(3, 4)), 7);
+    }
+
+    #[test]
+    fn block_on_inside_current_thread_runtime() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        assert_eq!(rt.block_on(async { block_on(sleep_and_add(1, 2)) }), 3);
+    }
+
+    #[test]
+    fn block_on_inside_multi_thread_runtime() {
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let res =
+            rt.block_on(async { tokio::spawn(async { block_on(sleep_and_add(1, 2)) }).await });
+        assert_eq!(res.unwrap(), 3);
+    }
+}
@@ -1,7 +1,11 @@
 pub mod artifact;
+pub mod client;
 pub mod const_coin;
+pub mod deployment;
 pub mod error;
+pub mod events;
 pub mod helpers;
+pub mod migration;
 pub mod robot;
 pub mod traits;
 
@@ -22,6 +26,10 @@ pub mod multi_test;
 #[cfg_attr(docsrs, doc(cfg(feature = "rpc-runner")))]
 pub mod rpc_runner;
 
+#[cfg(feature = "rpc-facade")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rpc-facade")))]
+pub mod rpc_facade;
+
 #[cfg(feature = "osmosis")]
 #[cfg_attr(docsrs, doc(cfg(feature = "osmosis")))]
 pub mod osmosis;
@@ -0,0 +1,403 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{from_json, Coin};
+use osmosis_std::types::cosmwasm::wasm::v1::{QueryContractInfoRequest, QueryContractInfoResponse};
+use serde::Serialize;
+use test_tube::SigningAccount;
+
+use crate::artifact::ContractType;
+use crate::helpers::{instantiate_contract_with_funds, migrate_contract};
+use crate::robot::TestRobot;
+use crate::traits::CwItRunner;
+
+/// The storage key under which `cw2` stores the [`ContractVersion`].
+pub const CONTRACT_VERSION_KEY: &[u8] = b"contract_info";
+
+/// The contract name and version stored by `cw2::set_contract_version`.
+#[cw_serde]
+pub struct ContractVersion {
+    pub contract: String,
+    pub version: String,
+}
+
+/// The difference between two snapshots of the raw storage of a contract.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StateDiff {
+    /// Keys that were added, with their new values.
+    pub added: BTreeMap<Vec<u8>, Vec<u8>>,
+    /// Keys that were removed, with their old values.
+    pub removed: BTreeMap<Vec<u8>, Vec<u8>>,
+    /// Keys whose value changed, with the old and the new value.
+    pub changed: BTreeMap<Vec<u8>, (Vec<u8>, Vec<u8>)>,
+}
+
+impl StateDiff {
+    /// Compares the storage `before` with the storage `after`.
+    pub fn new(before: &BTreeMap<Vec<u8>, Vec<u8>>, after: &BTreeMap<Vec<u8>, Vec<u8>>) -> Self {
+        let mut diff = Self::default();
+        for (key, old) in before {
+            match after.get(key) {
+                None => {
+                    diff.removed.insert(key.clone(), old.clone());
+                }
+                Some(new) if new != old => {
+                    diff.changed.insert(key.clone(), (old.clone(), new.clone()));
+                }
+                Some(_) => {}
+            }
+        }
+        for (key, new) in after {
+            if !before.contains_key(key) {
+                diff.added.insert(key.clone(), new.clone());
+            }
+        }
+        diff
+    }
+
+    /// Returns true if the storage didn't change.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// Returns the diff without the keys in `keys`.
+    pub fn without_keys(mut self, keys: &[&[u8]]) -> Self {
+        for key in keys {
+            self.added.remove(*key);
+            self.removed.remove(*key);
+            self.changed.remove(*key);
+        }
+        self
+    }
+}
+
+impl fmt::Display for StateDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lossy = |bytes: &[u8]| String::from_utf8_lossy(bytes).to_string();
+        for (key, value) in &self.added {
+            writeln!(f, "+ {}: {}", lossy(key), lossy(value))?;
+        }
+        for (key, value) in &self.removed {
+            writeln!(f, "- {}: {}", lossy(key), lossy(value))?;
+        }
+        for (key, (old, new)) in &self.changed {
+            writeln!(f, "~ {}: {} -> {}", lossy(key), lossy(old), lossy(new))?;
+        }
+        Ok(())
+    }
+}
+
+/// A robot to test the migration of a contract from one code to another.
+///
+/// The robot stores the old and the new code, instantiates the contract on the old code and
+/// migrates it to the new code, with assertions on the `cw2` contract version and the raw storage
+/// of the contract along the way. All methods panic on error.
+///
+/// ```ignore
+/// MigrationRobot::new(&runner, old_code, new_code, &admin)
+///     .instantiate(&InstantiateMsg { count: 1 }, &[])
+///     .run(|robot| {
+///         robot.wasm().execute(robot.contract_addr(), &ExecuteMsg::Increment {}, &[], &admin).unwrap();
+///     })
+///     .assert_contract_version("crates.io:counter", "1.0.0")
+///     .snapshot_state()
+///     .migrate(&MigrateMsg {})
+///     .assert_contract_version("crates.io:counter", "2.0.0")
+///     .assert_state_unchanged(&[CONTRACT_VERSION_KEY]);
+/// ```
+pub struct MigrationRobot<'a, R: CwItRunner<'a>> {
+    runner: &'a R,
+    admin: &'a SigningAccount,
+    /// The code id of the code the contract is instantiated with.
+    pub old_code_id: u64,
+    /// The code id of the code the contract is migrated to.
+    pub new_code_id: u64,
+    contract_addr: Option<String>,
+    snapshot: Option<BTreeMap<Vec<u8>, Vec<u8>>>,
+}
+
+impl<'a, R: CwItRunner<'a>> MigrationRobot<'a, R> {
+    /// Stores the `old` and the `new` code. `admin` stores the code, instantiates the contract and
+    /// is its admin.
+    pub fn new(
+        runner: &'a R,
+        old: ContractType,
+        new: ContractType,
+        admin: &'a SigningAccount,
+    ) -> Self {
+        let old_code_id = runner.store_code(old, admin).unwrap();
+        let new_code_id = runner.store_code(new, admin).unwrap();
+        Self::with_code_ids(runner, old_code_id, new_code_id, admin)
+    }
+
+    /// Creates a robot for code that is already stored.
+    pub fn with_code_ids(
+        runner: &'a R,
+        old_code_id: u64,
+        new_code_id: u64,
+        admin: &'a SigningAccount,
+    ) -> Self {
+        Self {
+            runner,
+            admin,
+            old_code_id,
+            new_code_id,
+            contract_addr: None,
+            snapshot: None,
+        }
+    }
+
+    /// Returns the admin of the contract.
+    pub fn admin(&self) -> &'a SigningAccount {
+        self.admin
+    }
+
+    /// Returns the address of the contract. Panics if it was not instantiated yet.
+    pub fn contract_addr(&self) -> &str {
+        self.contract_addr
+            .as_deref()
+            .expect("contract not instantiated, call `instantiate` first")
+    }
+
+    /// Instantiates the contract on the old code with `msg` and `funds`.
+    pub fn instantiate<M: Serialize>(mut self, msg: &M, funds: &[Coin]) -> Self {
+        let addr: String =
+            instantiate_contract_with_funds(self.runner, self.admin, self.old_code_id, msg, funds)
+                .unwrap();
+        self.contract_addr = Some(addr);
+        self
+    }
+
+    /// Runs `scenario`, e.g. executing messages on the contract to build up state before the
+    /// migration.
+    pub fn run(self, scenario: impl FnOnce(&Self)) -> Self {
+        scenario(&self);
+        self
+    }
+
+    /// Migrates the contract to the new code with `msg` and asserts that it runs the new code.
+    pub fn migrate<M: Serialize>(self, msg: &M) -> Self {
+        migrate_contract(
+            self.runner,
+            self.admin,
+            self.contract_addr(),
+            self.new_code_id,
+            msg,
+        )
+        .unwrap();
+        assert_eq!(self.query_code_id(), self.new_code_id);
+        self
+    }
+
+    /// Returns the code id the contract is running.
+    pub fn query_code_id(&self) -> u64 {
+        let res: QueryContractInfoResponse = self
+            .runner
+            .query(
+                "/cosmwasm.wasm.v1.Query/ContractInfo",
+                &QueryContractInfoRequest {
+                    address: self.contract_addr().to_string(),
+                },
+            )
+            .unwrap();
+        res.contract_info.unwrap().code_id
+    }
+
+    /// Returns the `cw2` contract version of the contract, or `None` if it is not set.
+    pub fn query_contract_version(&self) -> Option<ContractVersion> {
+        self.runner
+            .query_wasm_raw(self.contract_addr(), CONTRACT_VERSION_KEY)
+            .unwrap()
+            .map(|value| from_json(value).unwrap())
+    }
+
+    /// Asserts that the `cw2` contract version of the contract is `contract` at `version`.
+    pub fn assert_contract_version(self, contract: &str, version: &str) -> Self {
+        assert_eq!(
+            self.query_contract_version(),
+            Some(ContractVersion {
+                contract: contract.to_string(),
+                version: version.to_string(),
+            })
+        );
+        self
+    }
+
+    /// Returns the raw storage of the contract.
+    pub fn query_state(&self) -> BTreeMap<Vec<u8>, Vec<u8>> {
+        self.runner
+            .query_wasm_raw_all(self.contract_addr())
+            .unwrap()
+    }
+
+    /// Takes a snapshot of the raw storage of the contract to compare with later.
+    pub fn snapshot_state(mut self) -> Self {
+        self.snapshot = Some(self.query_state());
+        self
+    }
+
+    /// Returns the difference between the raw storage at the last snapshot and now. Panics if no
+    /// snapshot was taken.
+    pub fn state_diff(&self) -> StateDiff {
+        let snapshot = self
+            .snapshot
+            .as_ref()
+            .expect("no snapshot taken, call `snapshot_state` first");
+        StateDiff::new(snapshot, &self.query_state())
+    }
+
+    /// Asserts that the raw storage didn't change since the last snapshot, except for the keys in
+    /// `ignored_keys`.
+    pub fn assert_state_unchanged(self, ignored_keys: &[&[u8]]) -> Self {
+        let diff = self.state_diff().without_keys(ignored_keys);
+        assert!(diff.is_empty(), "contract state changed:\n{}", diff);
+        self
+    }
+}
+
+impl<'a, R: CwItRunner<'a>> TestRobot<'a, R> for MigrationRobot<'a, R> {
+    fn runner(&self) -> &'a R {
+        self.runner
+    }
+}
+
+#[cfg(feature = "multi-test")]
+#[cfg(test)]
+mod tests {
+    use apollo_cw_multi_test::ContractWrapper;
+    use cosmwasm_std::{
+        to_json_binary, to_json_vec, Binary, Deps, DepsMut, Empty, Env, MessageInfo, Response,
+        StdError, StdResult,
+    };
+
+    use super::*;
+    use crate::multi_test::MultiTestRunner;
+
+    const CONTRACT_NAME: &str = "crates.io:counter";
+    const COUNT_KEY: &[u8] = b"count";
+
+    fn set_version(deps: DepsMut, version: &str) -> StdResult<()> {
+        let value = to_json_vec(&ContractVersion {
+            contract: CONTRACT_NAME.to_string(),
+            version: version.to_string(),
+        })?;
+        deps.storage.set(CONTRACT_VERSION_KEY, &value);
+        Ok(())
+    }
+
+    fn instantiate(deps: DepsMut, _: Env, _: MessageInfo, count: u32) -> StdResult<Response> {
+        deps.storage.set(COUNT_KEY, &to_json_vec(&count)?);
+        set_version(deps, "1.0.0")?;
+        Ok(Response::new())
+    }
+
+    fn execute(deps: DepsMut, _: Env, _: MessageInfo, _: Empty) -> StdResult<Response> {
+        let count: u32 = from_json(deps.storage.get(COUNT_KEY).unwrap())?;
+        deps.storage.set(COUNT_KEY, &to_json_vec(&(count + 1))?);
+        Ok(Response::new())
+    }
+
+    fn query(deps: Deps, _: Env, _: Empty) -> StdResult<Binary> {
+        to_json_binary(&deps.storage.get(COUNT_KEY))
+    }
+
+    /// Migrates to version 2.0.0 and resets the count if `reset` is true.
+    fn migrate(deps: DepsMut, _: Env, reset: bool) -> StdResult<Response> {
+        if reset {
+            deps.storage.set(COUNT_KEY, &to_json_vec(&0u32)?);
+        }
+        set_version(deps, "2.0.0")?;
+        Ok(Response::new())
+    }
+
+    fn not_migratable(_: DepsMut, _: Env, _: bool) -> StdResult<Response> {
+        Err(StdError::generic_err("not migratable"))
+    }
+
+    fn counter(migrate: fn(DepsMut, Env, bool) -> StdResult<Response>) -> ContractType {
+        ContractType::MultiTestContract(Box::new(
+            ContractWrapper::new_with_empty(execute, instantiate, query).with_migrate(migrate),
+        ))
+    }
+
+    fn increment<'a>(robot: &MigrationRobot<'a, MultiTestRunner<'a>>) {
+        robot
+            .wasm()
+            .execute(robot.contract_addr(), &Empty {}, &[], robot.admin())
+            .unwrap();
+    }
+
+    #[test]
+    fn migrate_keeps_state() {
+        let runner = MultiTestRunner::new("osmo");
+        let admin = runner.init_default_account().unwrap();
+
+        let robot = MigrationRobot::new(&runner, counter(not_migratable), counter(migrate), &admin)
+            .instantiate(&1u32, &[])
+            .run(increment)
+            .assert_contract_version(CONTRACT_NAME, "1.0.0")
+            .snapshot_state()
+            .migrate(&false)
+            .assert_contract_version(CONTRACT_NAME, "2.0.0")
+            .assert_state_unchanged(&[CONTRACT_VERSION_KEY]);
+
+        assert_eq!(robot.query_code_id(), robot.new_code_id);
+        assert_eq!(
+            runner
+                .query_wasm_raw(robot.contract_addr(), COUNT_KEY)
+                .unwrap(),
+            Some(b"2".to_vec())
+        );
+        assert_eq!(
+            runner
+                .query_wasm_raw(robot.contract_addr(), b"missing")
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn state_diff() {
+        let runner = MultiTestRunner::new("osmo");
+        let admin = runner.init_default_account().unwrap();
+
+        let robot = MigrationRobot::new(&runner, counter(not_migratable), counter(migrate), &admin)
+            .instantiate(&1u32, &[])
+            .snapshot_state()
+            .migrate(&true);
+
+        let diff = robot.state_diff();
+        assert!(diff.added.is_empty());
+        assert!(diff.removed.is_empty());
+        assert_eq!(
+            diff.changed.keys().collect::<Vec<_>>(),
+            vec![&CONTRACT_VERSION_KEY.to_vec(), &COUNT_KEY.to_vec()]
+        );
+        assert_eq!(diff.changed[COUNT_KEY], (b"1".to_vec(), b"0".to_vec()));
+    }
+
+    #[test]
+    #[should_panic(expected = "contract state changed")]
+    fn assert_state_unchanged_panics() {
+        let runner = MultiTestRunner::new("osmo");
+        let admin = runner.init_default_account().unwrap();
+
+        MigrationRobot::new(&runner, counter(not_migratable), counter(migrate), &admin)
+            .instantiate(&1u32, &[])
+            .snapshot_state()
+            .migrate(&true)
+            .assert_state_unchanged(&[CONTRACT_VERSION_KEY]);
+    }
+
+    #[test]
+    #[should_panic(expected = "not migratable")]
+    fn failed_migration() {
+        let runner = MultiTestRunner::new("osmo");
+        let admin = runner.init_default_account().unwrap();
+
+        MigrationRobot::new(&runner, counter(migrate), counter(not_migratable), &admin)
+            .instantiate(&1u32, &[])
+            .migrate(&false);
+    }
+}
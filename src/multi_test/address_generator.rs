@@ -0,0 +1,105 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use apollo_cw_multi_test::AddressGenerator;
+use cosmwasm_std::{instantiate2_address, Addr, Api, CanonicalAddr, Storage};
+use sha2::{Digest, Sha256};
+
+/// Generates contract addresses the way wasmd does.
+///
+/// Contracts instantiated with `MsgInstantiateContract` get the classic address derived from the
+/// code id and instance id. Contracts instantiated with `MsgInstantiateContract2` get the address
+/// derived from the code checksum, the creator and the salt, so it can be computed beforehand with
+/// [`crate::helpers::predict_address`].
+///
+/// `cw-multi-test` does not execute `WasmMsg::Instantiate2` itself, so [`MultiTestRunner`]
+/// executes the messages of a transaction one at a time, and executes a `WasmMsg::Instantiate2`
+/// as a `WasmMsg::Instantiate` after setting the predicted address with
+/// [`WasmdAddressGenerator::set_predicted`]. This only works for messages sent to the runner, not
+/// for `WasmMsg::Instantiate2` sent by contracts.
+///
+/// [`MultiTestRunner`]: crate::multi_test::MultiTestRunner
+#[derive(Clone, Default)]
+pub struct WasmdAddressGenerator {
+    predicted: Rc<RefCell<Option<(u64, Addr)>>>,
+}
+
+impl WasmdAddressGenerator {
+    /// Makes the next contract instantiated from the code `code_id` get `address` instead of the
+    /// classic address.
+    pub fn set_predicted(&self, code_id: u64, address: Addr) {
+        *self.predicted.borrow_mut() = Some((code_id, address));
+    }
+
+    /// Forgets the predicted address if it was not used, e.g. because the message failed.
+    pub fn clear_predicted(&self) {
+        self.predicted.borrow_mut().take();
+    }
+}
+
+impl AddressGenerator for WasmdAddressGenerator {
+    fn contract_address(
+        &self,
+        api: &dyn Api,
+        _storage: &mut dyn Storage,
+        code_id: u64,
+        instance_id: u64,
+    ) -> anyhow::Result<Addr> {
+        let mut predicted = self.predicted.borrow_mut();
+        if matches!(&*predicted, Some((id, _)) if *id == code_id) {
+            return Ok(predicted.take().unwrap().1);
+        }
+        Ok(api.addr_humanize(&classic_address(code_id, instance_id))?)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn predictable_contract_address(
+        &self,
+        api: &dyn Api,
+        _storage: &mut dyn Storage,
+        _code_id: u64,
+        _instance_id: u64,
+        checksum: &[u8],
+        creator: &CanonicalAddr,
+        salt: &[u8],
+    ) -> anyhow::Result<Addr> {
+        Ok(api.addr_humanize(&instantiate2_address(checksum, creator, salt)?)?)
+    }
+}
+
+/// Returns the address of the `instance_id`th contract of the code `code_id`, i.e. the module
+/// address of `wasm` with the big endian code id and instance id as derivation key.
+fn classic_address(code_id: u64, instance_id: u64) -> CanonicalAddr {
+    let mut key = b"wasm\0".to_vec();
+    key.extend_from_slice(&code_id.to_be_bytes());
+    key.extend_from_slice(&instance_id.to_be_bytes());
+    Sha256::new()
+        .chain_update(Sha256::digest(b"module"))
+        .chain_update(key)
+        .finalize()
+        .to_vec()
+        .into()
+}
+
+/// Returns the checksum of the code `code_id`. Contracts stored on `cw-multi-test` have no wasm
+/// bytes, so this is the sha256 hash of the big endian code id.
+pub fn code_checksum(code_id: u64) -> Vec<u8> {
+    Sha256::digest(code_id.to_be_bytes()).to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::multi_test::api::MockApiBech32;
+
+    #[test]
+    fn classic_address_matches_wasmd() {
+        // The first contract instantiated on a wasmd chain
+        let api = MockApiBech32::new("wasm");
+        let addr = api.addr_humanize(&classic_address(1, 1)).unwrap();
+        assert_eq!(
+            addr.as_str(),
+            "wasm14hj2tavq8fpesdwxxcu44rty3hh90vhujrvcmstl4zr3txmfvw9s0phg4d"
+        );
+    }
+}
@@ -7,7 +7,10 @@ pub mod macros;
 pub mod modules;
 mod runner;
 
+pub mod address_generator;
 pub mod api;
 
 pub use crate::create_contract_wrappers;
+#[cfg(feature = "rpc-facade")]
+pub(crate) use runner::any_to_cosmos_msgs;
 pub use runner::MultiTestRunner;
@@ -1,25 +1,32 @@
+use crate::multi_test::address_generator::{code_checksum, WasmdAddressGenerator};
 use crate::multi_test::api::MockApiBech32;
-use crate::{traits::CwItRunner, ContractType};
+use crate::{
+    artifact::Artifact,
+    traits::{BlockingRunner, CwItRunner},
+    ContractType,
+};
 use anyhow::bail;
 use apollo_cw_multi_test::{
-    BankKeeper, BankSudo, BasicAppBuilder, MockAddressGenerator, WasmKeeper,
+    AppResponse, BankKeeper, BankSudo, BasicAppBuilder, Contract, CosmosRouter, WasmKeeper,
 };
 use cosmrs::{crypto::secp256k1::SigningKey, proto::cosmos::base::abci::v1beta1::GasInfo};
 use cosmwasm_std::{
-    coin, Addr, BankMsg, Binary, Coin, CosmosMsg, Empty, QueryRequest, StakingMsg, WasmMsg,
+    coin, instantiate2_address, Addr, Api, BankMsg, Binary, Coin, CosmosMsg, Empty, Order,
+    QueryRequest, Record, StakingMsg, Storage, WasmMsg,
 };
 use osmosis_std::types::{
     cosmos::{
-        bank::v1beta1::MsgSend,
+        bank::v1beta1::{MsgMultiSend, MsgSend},
         staking::v1beta1::{MsgBeginRedelegate, MsgDelegate, MsgUndelegate},
     },
     cosmwasm::wasm::v1::{
-        MsgClearAdmin, MsgExecuteContract, MsgInstantiateContract, MsgMigrateContract,
-        MsgUpdateAdmin,
+        MsgClearAdmin, MsgExecuteContract, MsgInstantiateContract, MsgInstantiateContract2,
+        MsgMigrateContract, MsgUpdateAdmin,
     },
 };
 use prost::Message;
 use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
 use std::str::FromStr;
 use test_tube::{
     Account, DecodeError, EncodeError, FeeSetting, Runner, RunnerError, SigningAccount,
@@ -28,15 +35,22 @@ use test_tube::{
 pub struct MultiTestRunner<'a> {
     pub app: apollo_cw_multi_test::App<BankKeeper, MockApiBech32<'a>>,
     pub address_prefix: &'a str,
+    address_generator: WasmdAddressGenerator,
+    /// Contracts stored in place of wasm artifacts, see [`MultiTestRunner::with_artifact_contract`].
+    artifact_contracts: Vec<(Artifact, ContractFactory)>,
 }
 
+/// Creates a `cw-multi-test` contract every time the code is stored.
+type ContractFactory = Box<dyn Fn() -> Box<dyn Contract<Empty>>>;
+
 impl<'a> MultiTestRunner<'a> {
     /// Creates a new instance of a `MultiTestRunner`, wrapping a `cw_multi_test::App`
     /// with the given address prefix.
     pub fn new(address_prefix: &'a str) -> Self {
         // Construct app
+        let address_generator = WasmdAddressGenerator::default();
         let wasm_keeper: WasmKeeper<Empty, Empty> =
-            WasmKeeper::new_with_custom_address_generator(MockAddressGenerator);
+            WasmKeeper::new_with_custom_address_generator(address_generator.clone());
 
         let app = BasicAppBuilder::<Empty, Empty>::new()
             .with_api(MockApiBech32::new(address_prefix))
@@ -46,6 +60,8 @@ impl<'a> MultiTestRunner<'a> {
         Self {
             app,
             address_prefix,
+            address_generator,
+            artifact_contracts: vec![],
         }
     }
 
@@ -56,8 +72,9 @@ impl<'a> MultiTestRunner<'a> {
         address_prefix: &'a str,
         stargate_keeper: apollo_cw_multi_test::StargateKeeper<Empty, Empty>,
     ) -> Self {
+        let address_generator = WasmdAddressGenerator::default();
         let wasm_keeper: WasmKeeper<Empty, Empty> =
-            WasmKeeper::new_with_custom_address_generator(MockAddressGenerator);
+            WasmKeeper::new_with_custom_address_generator(address_generator.clone());
 
         // Construct app
         let app = BasicAppBuilder::<Empty, Empty>::new()
@@ -69,10 +86,115 @@ impl<'a> MultiTestRunner<'a> {
         Self {
             app,
             address_prefix,
+            address_generator,
+            artifact_contracts: vec![],
         }
     }
 }
 
+impl MultiTestRunner<'_> {
+    /// Makes [`CwItRunner::store_code`] store the contract created by `contract` when it is given
+    /// `artifact`, since `cw-multi-test` can't run wasm code. This lets tests written for wasm
+    /// artifacts, e.g. with an [`ArtifactSet`](crate::artifact::ArtifactSet), run the Rust code
+    /// of the contracts instead.
+    pub fn with_artifact_contract(
+        mut self,
+        artifact: Artifact,
+        contract: impl Fn() -> Box<dyn Contract<Empty>> + 'static,
+    ) -> Self {
+        self.artifact_contracts.push((artifact, Box::new(contract)));
+        self
+    }
+
+    /// Executes `msgs` sent by `sender` in one atomic operation, like `App::execute_multi`, but
+    /// also supports `WasmMsg::Instantiate2`, which `cw-multi-test` does not.
+    ///
+    /// The messages are executed one at a time, and the address predicted for a
+    /// `WasmMsg::Instantiate2` is only set while its own message is executed, so it can't be taken
+    /// by another instantiate of the same code. The state is rolled back if a message fails.
+    pub(crate) fn execute_multi(
+        &self,
+        sender: Addr,
+        msgs: Vec<CosmosMsg>,
+    ) -> anyhow::Result<Vec<AppResponse>> {
+        let block = self.app.block_info();
+        self.app.init_modules(|router, api, storage| {
+            let snapshot: Vec<Record> = storage.range(None, None, Order::Ascending).collect();
+            let res = msgs
+                .into_iter()
+                .map(|msg| {
+                    let msg =
+                        self.predict_instantiate2(&router.wasm, api, storage, &sender, msg)?;
+                    let res = router.execute(api, storage, &block, sender.clone(), msg);
+                    self.address_generator.clear_predicted();
+                    res
+                })
+                .collect::<anyhow::Result<Vec<_>>>();
+            if res.is_err() {
+                restore(storage, snapshot);
+            }
+            res
+        })
+    }
+
+    /// Converts a `WasmMsg::Instantiate2`, which `cw-multi-test` does not support, into a
+    /// `WasmMsg::Instantiate` of a contract at the address it would get on a chain.
+    fn predict_instantiate2(
+        &self,
+        wasm: &WasmKeeper<Empty, Empty>,
+        api: &dyn Api,
+        storage: &dyn Storage,
+        sender: &Addr,
+        msg: CosmosMsg,
+    ) -> anyhow::Result<CosmosMsg> {
+        let CosmosMsg::Wasm(WasmMsg::Instantiate2 {
+            admin,
+            code_id,
+            label,
+            msg,
+            funds,
+            salt,
+        }) = msg
+        else {
+            return Ok(msg);
+        };
+
+        let creator = api.addr_canonicalize(sender.as_str())?;
+        let address = instantiate2_address(&code_checksum(code_id), &creator, &salt)?;
+        let address = api.addr_humanize(&address)?;
+        if wasm.load_contract(storage, &address).is_ok() {
+            bail!(
+                "contract address {} already exists, try a different combination of creator, \
+                 checksum and salt",
+                address
+            );
+        }
+        self.address_generator.set_predicted(code_id, address);
+
+        Ok(CosmosMsg::Wasm(WasmMsg::Instantiate {
+            admin,
+            code_id,
+            msg,
+            funds,
+            label,
+        }))
+    }
+}
+
+/// Replaces everything in `storage` with `records`.
+fn restore(storage: &mut dyn Storage, records: Vec<Record>) {
+    let keys: Vec<Vec<u8>> = storage
+        .range(None, None, Order::Ascending)
+        .map(|(key, _)| key)
+        .collect();
+    for key in keys {
+        storage.remove(&key);
+    }
+    for (key, value) in records {
+        storage.set(&key, &value);
+    }
+}
+
 impl Runner<'_> for MultiTestRunner<'_> {
     fn execute_cosmos_msgs<S>(
         &self,
@@ -82,12 +204,9 @@ impl Runner<'_> for MultiTestRunner<'_> {
     where
         S: prost::Message + Default,
     {
-        let sender = Addr::unchecked(signer.address());
-
         // Execute messages with multi test app
         let app_responses = self
-            .app
-            .execute_multi(sender, msgs.to_vec())
+            .execute_multi(Addr::unchecked(signer.address()), msgs.to_vec())
             // NB: Must use this syntax to capture full anyhow message.
             // to_string() will only give the outermost error context.
             .map_err(|e| RunnerError::GenericError(format!("{:#}", e)))?;
@@ -156,120 +275,11 @@ impl Runner<'_> for MultiTestRunner<'_> {
     {
         let msgs = msgs
             .iter()
-            .map(|msg| match msg.type_url.as_str() {
-                // WasmMsg
-                MsgExecuteContract::TYPE_URL => {
-                    let msg = MsgExecuteContract::decode(msg.value.as_slice())
-                        .map_err(DecodeError::ProtoDecodeError)?;
-                    Ok(CosmosMsg::<Empty>::Wasm(WasmMsg::Execute {
-                        contract_addr: msg.contract,
-                        msg: Binary(msg.msg),
-                        funds: msg
-                            .funds
-                            .into_iter()
-                            .map(|c| coin(u128::from_str(&c.amount).unwrap(), c.denom))
-                            .collect(),
-                    }))
-                }
-                MsgInstantiateContract::TYPE_URL => {
-                    let msg = MsgInstantiateContract::decode(msg.value.as_slice())
-                        .map_err(DecodeError::ProtoDecodeError)?;
-                    Ok(CosmosMsg::<Empty>::Wasm(WasmMsg::Instantiate {
-                        code_id: msg.code_id,
-                        admin: Some(msg.admin),
-                        msg: Binary(msg.msg),
-                        funds: msg
-                            .funds
-                            .into_iter()
-                            .map(|c| coin(u128::from_str(&c.amount).unwrap(), c.denom))
-                            .collect(),
-                        label: msg.label,
-                    }))
-                }
-                MsgMigrateContract::TYPE_URL => {
-                    let msg = MsgMigrateContract::decode(msg.value.as_slice())
-                        .map_err(DecodeError::ProtoDecodeError)?;
-                    Ok(CosmosMsg::<Empty>::Wasm(WasmMsg::Migrate {
-                        contract_addr: msg.contract,
-                        new_code_id: msg.code_id,
-                        msg: Binary(msg.msg),
-                    }))
-                }
-                MsgUpdateAdmin::TYPE_URL => {
-                    let msg = MsgUpdateAdmin::decode(msg.value.as_slice())
-                        .map_err(DecodeError::ProtoDecodeError)?;
-                    Ok(CosmosMsg::<Empty>::Wasm(WasmMsg::UpdateAdmin {
-                        contract_addr: msg.contract,
-                        admin: msg.new_admin,
-                    }))
-                }
-                MsgClearAdmin::TYPE_URL => {
-                    let msg = MsgClearAdmin::decode(msg.value.as_slice())
-                        .map_err(DecodeError::ProtoDecodeError)?;
-                    Ok(CosmosMsg::<Empty>::Wasm(WasmMsg::ClearAdmin {
-                        contract_addr: msg.contract,
-                    }))
-                }
-                // BankMsg
-                MsgSend::TYPE_URL => {
-                    let msg = MsgSend::decode(msg.value.as_slice())
-                        .map_err(DecodeError::ProtoDecodeError)?;
-                    Ok(CosmosMsg::<Empty>::Bank(BankMsg::Send {
-                        to_address: msg.to_address,
-                        amount: msg
-                            .amount
-                            .into_iter()
-                            .map(|c| coin(u128::from_str(&c.amount).unwrap(), c.denom))
-                            .collect(),
-                    }))
-                }
-                // StakingMsg
-                MsgDelegate::TYPE_URL => {
-                    let msg = MsgDelegate::decode(msg.value.as_slice())
-                        .map_err(DecodeError::ProtoDecodeError)?;
-                    let proto_coin = msg.amount.unwrap_or_default();
-                    Ok(CosmosMsg::<Empty>::Staking(StakingMsg::Delegate {
-                        validator: msg.validator_address,
-                        amount: coin(
-                            u128::from_str(&proto_coin.amount).unwrap(),
-                            proto_coin.denom,
-                        ),
-                    }))
-                }
-                MsgUndelegate::TYPE_URL => {
-                    let msg = MsgUndelegate::decode(msg.value.as_slice())
-                        .map_err(DecodeError::ProtoDecodeError)?;
-                    let proto_coin = msg.amount.unwrap_or_default();
-                    Ok(CosmosMsg::<Empty>::Staking(StakingMsg::Undelegate {
-                        validator: msg.validator_address,
-                        amount: coin(
-                            u128::from_str(&proto_coin.amount).unwrap(),
-                            proto_coin.denom,
-                        ),
-                    }))
-                }
-                MsgBeginRedelegate::TYPE_URL => {
-                    let msg = MsgBeginRedelegate::decode(msg.value.as_slice())
-                        .map_err(DecodeError::ProtoDecodeError)?;
-                    let proto_coin = msg.amount.unwrap_or_default();
-                    Ok(CosmosMsg::<Empty>::Staking(StakingMsg::Redelegate {
-                        src_validator: msg.validator_src_address,
-                        dst_validator: msg.validator_dst_address,
-                        amount: coin(
-                            u128::from_str(&proto_coin.amount).unwrap(),
-                            proto_coin.denom,
-                        ),
-                    }))
-                }
-                _ => {
-                    // Else assume StargateMsg
-                    Ok(CosmosMsg::<Empty>::Stargate {
-                        type_url: msg.type_url.clone(),
-                        value: msg.value.clone().into(),
-                    })
-                }
-            })
-            .collect::<Result<Vec<_>, RunnerError>>()?;
+            .map(any_to_cosmos_msgs)
+            .collect::<Result<Vec<_>, RunnerError>>()?
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
 
         self.execute_cosmos_msgs(&msgs, signer)
     }
@@ -305,7 +315,34 @@ impl<'a> CwItRunner<'a> for MultiTestRunner<'a> {
     ) -> Result<u64, anyhow::Error> {
         match code {
             ContractType::MultiTestContract(contract) => Ok(self.app.store_code(contract)),
-            ContractType::Artifact(_) => bail!("Artifact not supported for MultiTestRunner"),
+            ContractType::Artifact(artifact) => {
+                if let Some((_, contract)) =
+                    self.artifact_contracts.iter().find(|(a, _)| *a == artifact)
+                {
+                    return Ok(self.app.store_code(contract()));
+                }
+                // Incompatible code is rejected with the same error as on the other runners
+                #[cfg(feature = "wasm-inspect")]
+                {
+                    let info =
+                        crate::artifact::inspect::check_wasm(&artifact.get_wasm_byte_code()?)?;
+                    bail!(
+                        "Artifact not supported for MultiTestRunner: the wasm code with entry \
+                         points {} can't be executed, register a contract for it with \
+                         `MultiTestRunner::with_artifact_contract`",
+                        info.entry_points
+                            .iter()
+                            .map(String::as_str)
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )
+                }
+                #[cfg(not(feature = "wasm-inspect"))]
+                bail!(
+                    "Artifact not supported for MultiTestRunner, register a contract for it with \
+                     `MultiTestRunner::with_artifact_contract`"
+                )
+            }
         }
     }
 
@@ -361,15 +398,220 @@ impl<'a> CwItRunner<'a> for MultiTestRunner<'a> {
     fn query_block_time_nanos(&self) -> u64 {
         self.app.block_info().time.nanos()
     }
+
+    fn query_wasm_raw(
+        &self,
+        contract_addr: &str,
+        key: &[u8],
+    ) -> Result<Option<Vec<u8>>, anyhow::Error> {
+        Ok(self
+            .app
+            .wrap()
+            .query_wasm_raw(contract_addr, key.to_vec())?)
+    }
+
+    fn query_wasm_raw_all(
+        &self,
+        contract_addr: &str,
+    ) -> Result<BTreeMap<Vec<u8>, Vec<u8>>, anyhow::Error> {
+        Ok(self
+            .app
+            .dump_wasm_raw(&Addr::unchecked(contract_addr))
+            .into_iter()
+            .collect())
+    }
+
+    fn query_code_checksum(&self, code_id: u64) -> Result<Vec<u8>, anyhow::Error> {
+        Ok(code_checksum(code_id))
+    }
+
+    /// `cw-multi-test` leaves the label out of the `ContractInfo` query, so it is read from the
+    /// contract data instead.
+    fn query_contract_label(&self, contract_addr: &str) -> Result<String, anyhow::Error> {
+        Ok(self
+            .app
+            .contract_data(&Addr::unchecked(contract_addr))?
+            .label)
+    }
+}
+
+impl BlockingRunner for MultiTestRunner<'_> {}
+
+/// Converts a protobuf encoded message into the `CosmosMsg`s executed by `cw-multi-test`.
+///
+/// A `MsgMultiSend` is converted into one `BankMsg::Send` per output, sent by the signer of the
+/// transaction. It must have a single input. Messages that are not natively supported are
+/// passed on as `CosmosMsg::Stargate`.
+pub(crate) fn any_to_cosmos_msgs(msg: &cosmrs::Any) -> Result<Vec<CosmosMsg>, RunnerError> {
+    if msg.type_url == MsgMultiSend::TYPE_URL {
+        let msg =
+            MsgMultiSend::decode(msg.value.as_slice()).map_err(DecodeError::ProtoDecodeError)?;
+        if msg.inputs.len() != 1 {
+            return Err(RunnerError::GenericError(
+                "MsgMultiSend must have exactly one input".to_string(),
+            ));
+        }
+        return Ok(msg
+            .outputs
+            .into_iter()
+            .map(|output| {
+                CosmosMsg::Bank(BankMsg::Send {
+                    to_address: output.address,
+                    amount: output
+                        .coins
+                        .into_iter()
+                        .map(|c| coin(u128::from_str(&c.amount).unwrap(), c.denom))
+                        .collect(),
+                })
+            })
+            .collect());
+    }
+
+    Ok(vec![any_to_cosmos_msg(msg)?])
+}
+
+fn any_to_cosmos_msg(msg: &cosmrs::Any) -> Result<CosmosMsg, RunnerError> {
+    match msg.type_url.as_str() {
+        // WasmMsg
+        MsgExecuteContract::TYPE_URL => {
+            let msg = MsgExecuteContract::decode(msg.value.as_slice())
+                .map_err(DecodeError::ProtoDecodeError)?;
+            Ok(CosmosMsg::<Empty>::Wasm(WasmMsg::Execute {
+                contract_addr: msg.contract,
+                msg: Binary(msg.msg),
+                funds: msg
+                    .funds
+                    .into_iter()
+                    .map(|c| coin(u128::from_str(&c.amount).unwrap(), c.denom))
+                    .collect(),
+            }))
+        }
+        MsgInstantiateContract::TYPE_URL => {
+            let msg = MsgInstantiateContract::decode(msg.value.as_slice())
+                .map_err(DecodeError::ProtoDecodeError)?;
+            Ok(CosmosMsg::<Empty>::Wasm(WasmMsg::Instantiate {
+                code_id: msg.code_id,
+                admin: Some(msg.admin),
+                msg: Binary(msg.msg),
+                funds: msg
+                    .funds
+                    .into_iter()
+                    .map(|c| coin(u128::from_str(&c.amount).unwrap(), c.denom))
+                    .collect(),
+                label: msg.label,
+            }))
+        }
+        MsgInstantiateContract2::TYPE_URL => {
+            let msg = MsgInstantiateContract2::decode(msg.value.as_slice())
+                .map_err(DecodeError::ProtoDecodeError)?;
+            Ok(CosmosMsg::<Empty>::Wasm(WasmMsg::Instantiate2 {
+                code_id: msg.code_id,
+                admin: Some(msg.admin),
+                msg: Binary(msg.msg),
+                funds: msg
+                    .funds
+                    .into_iter()
+                    .map(|c| coin(u128::from_str(&c.amount).unwrap(), c.denom))
+                    .collect(),
+                label: msg.label,
+                salt: Binary(msg.salt),
+            }))
+        }
+        MsgMigrateContract::TYPE_URL => {
+            let msg = MsgMigrateContract::decode(msg.value.as_slice())
+                .map_err(DecodeError::ProtoDecodeError)?;
+            Ok(CosmosMsg::<Empty>::Wasm(WasmMsg::Migrate {
+                contract_addr: msg.contract,
+                new_code_id: msg.code_id,
+                msg: Binary(msg.msg),
+            }))
+        }
+        MsgUpdateAdmin::TYPE_URL => {
+            let msg = MsgUpdateAdmin::decode(msg.value.as_slice())
+                .map_err(DecodeError::ProtoDecodeError)?;
+            Ok(CosmosMsg::<Empty>::Wasm(WasmMsg::UpdateAdmin {
+                contract_addr: msg.contract,
+                admin: msg.new_admin,
+            }))
+        }
+        MsgClearAdmin::TYPE_URL => {
+            let msg = MsgClearAdmin::decode(msg.value.as_slice())
+                .map_err(DecodeError::ProtoDecodeError)?;
+            Ok(CosmosMsg::<Empty>::Wasm(WasmMsg::ClearAdmin {
+                contract_addr: msg.contract,
+            }))
+        }
+        // BankMsg
+        MsgSend::TYPE_URL => {
+            let msg =
+                MsgSend::decode(msg.value.as_slice()).map_err(DecodeError::ProtoDecodeError)?;
+            Ok(CosmosMsg::<Empty>::Bank(BankMsg::Send {
+                to_address: msg.to_address,
+                amount: msg
+                    .amount
+                    .into_iter()
+                    .map(|c| coin(u128::from_str(&c.amount).unwrap(), c.denom))
+                    .collect(),
+            }))
+        }
+        // StakingMsg
+        MsgDelegate::TYPE_URL => {
+            let msg =
+                MsgDelegate::decode(msg.value.as_slice()).map_err(DecodeError::ProtoDecodeError)?;
+            let proto_coin = msg.amount.unwrap_or_default();
+            Ok(CosmosMsg::<Empty>::Staking(StakingMsg::Delegate {
+                validator: msg.validator_address,
+                amount: coin(
+                    u128::from_str(&proto_coin.amount).unwrap(),
+                    proto_coin.denom,
+                ),
+            }))
+        }
+        MsgUndelegate::TYPE_URL => {
+            let msg = MsgUndelegate::decode(msg.value.as_slice())
+                .map_err(DecodeError::ProtoDecodeError)?;
+            let proto_coin = msg.amount.unwrap_or_default();
+            Ok(CosmosMsg::<Empty>::Staking(StakingMsg::Undelegate {
+                validator: msg.validator_address,
+                amount: coin(
+                    u128::from_str(&proto_coin.amount).unwrap(),
+                    proto_coin.denom,
+                ),
+            }))
+        }
+        MsgBeginRedelegate::TYPE_URL => {
+            let msg = MsgBeginRedelegate::decode(msg.value.as_slice())
+                .map_err(DecodeError::ProtoDecodeError)?;
+            let proto_coin = msg.amount.unwrap_or_default();
+            Ok(CosmosMsg::<Empty>::Staking(StakingMsg::Redelegate {
+                src_validator: msg.validator_src_address,
+                dst_validator: msg.validator_dst_address,
+                amount: coin(
+                    u128::from_str(&proto_coin.amount).unwrap(),
+                    proto_coin.denom,
+                ),
+            }))
+        }
+        _ => {
+            // Else assume StargateMsg
+            Ok(CosmosMsg::<Empty>::Stargate {
+                type_url: msg.type_url.clone(),
+                value: msg.value.clone().into(),
+            })
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use cosmrs::proto::cosmos::bank::v1beta1::MsgSendResponse;
-    use cosmwasm_std::{coin, Event, Uint128};
+    use cosmwasm_std::{coin, to_json_binary, Event, Uint128};
 
     use crate::test_helpers::*;
-    use crate::{artifact::Artifact, helpers::upload_wasm_file};
+    use crate::{
+        artifact::Artifact,
+        helpers::{instantiate2_contract, predict_address, upload_wasm_file},
+    };
     use apollo_cw_multi_test::ContractWrapper;
 
     use cw20::MinterResponse;
@@ -428,6 +670,137 @@ mod tests {
         assert_eq!(code_id, 1);
     }
 
+    #[test]
+    fn instantiate2_predictable_address() {
+        let app = MultiTestRunner::new("osmo");
+        let alice = app.init_default_account().unwrap();
+        let code_id = app
+            .store_code(
+                ContractType::MultiTestContract(test_contract::contract()),
+                &alice,
+            )
+            .unwrap();
+
+        let predicted = predict_address(&app, code_id, &alice.address(), b"salt").unwrap();
+        let address: String = instantiate2_contract(
+            &app,
+            &alice,
+            code_id,
+            &test_contract::EmptyMsg {},
+            b"salt",
+            &[],
+        )
+        .unwrap();
+        assert_eq!(address, predicted);
+        assert!(address.starts_with("osmo1"));
+
+        // The same salt can't be used twice for the same code and creator
+        instantiate2_contract::<_, _, String>(
+            &app,
+            &alice,
+            code_id,
+            &test_contract::EmptyMsg {},
+            b"salt",
+            &[],
+        )
+        .unwrap_err();
+        let other: String = instantiate2_contract(
+            &app,
+            &alice,
+            code_id,
+            &test_contract::EmptyMsg {},
+            b"other salt",
+            &[],
+        )
+        .unwrap();
+        assert_ne!(other, address);
+    }
+
+    #[test]
+    fn instantiate2_after_instantiate_of_same_code() {
+        let app = MultiTestRunner::new("osmo");
+        let alice = app.init_default_account().unwrap();
+        let code_id = app
+            .store_code(
+                ContractType::MultiTestContract(test_contract::contract()),
+                &alice,
+            )
+            .unwrap();
+        let predicted = predict_address(&app, code_id, &alice.address(), b"salt").unwrap();
+
+        let instantiate = |salt: Option<&[u8]>| {
+            let msg = to_json_binary(&test_contract::EmptyMsg {}).unwrap();
+            let label = "test".to_string();
+            match salt {
+                Some(salt) => WasmMsg::Instantiate2 {
+                    admin: None,
+                    code_id,
+                    label,
+                    msg,
+                    funds: vec![],
+                    salt: salt.into(),
+                },
+                None => WasmMsg::Instantiate {
+                    admin: None,
+                    code_id,
+                    msg,
+                    funds: vec![],
+                    label,
+                },
+            }
+            .into()
+        };
+        let res = app
+            .execute_cosmos_msgs::<MsgInstantiateContractResponse>(
+                &[instantiate(None), instantiate(Some(b"salt"))],
+                &alice,
+            )
+            .unwrap();
+
+        let addresses: Vec<_> = res
+            .events
+            .iter()
+            .filter(|e| e.ty == "instantiate")
+            .flat_map(|e| &e.attributes)
+            .filter(|a| a.key == "_contract_addr")
+            .map(|a| a.value.clone())
+            .collect();
+        assert_eq!(addresses.len(), 2);
+        // The plain instantiate gets the classic address, not the predicted one
+        assert_ne!(addresses[0], predicted);
+        assert_eq!(addresses[1], predicted);
+    }
+
+    #[test]
+    fn instantiate2_duplicate_salt_in_one_tx() {
+        let app = MultiTestRunner::new("osmo");
+        let alice = app.init_default_account().unwrap();
+        let code_id = app
+            .store_code(
+                ContractType::MultiTestContract(test_contract::contract()),
+                &alice,
+            )
+            .unwrap();
+        let predicted = predict_address(&app, code_id, &alice.address(), b"salt").unwrap();
+
+        let msg: CosmosMsg = WasmMsg::Instantiate2 {
+            admin: None,
+            code_id,
+            label: "test".to_string(),
+            msg: to_json_binary(&test_contract::EmptyMsg {}).unwrap(),
+            funds: vec![],
+            salt: b"salt".into(),
+        }
+        .into();
+        let err = app
+            .execute_cosmos_msgs::<MsgInstantiateContractResponse>(&[msg.clone(), msg], &alice)
+            .unwrap_err();
+        assert!(err.to_string().contains("already exists"));
+
+        // The first instantiate is rolled back with the rest of the transaction
+        app.query_contract_label(&predicted).unwrap_err();
+    }
+
     #[test]
     #[should_panic]
     // This test should panic because we are trying to upload a wasm contract to a MultiTestRunner
@@ -444,6 +817,52 @@ mod tests {
         .unwrap();
     }
 
+    #[test]
+    fn store_artifact() {
+        let artifact = Artifact::Local(counter::WASM_PATH.to_string());
+        let app = MultiTestRunner::new("osmo")
+            .with_artifact_contract(artifact.clone(), test_contract::contract);
+        let alice = app.init_default_account().unwrap();
+
+        // Artifacts with a registered contract are stored as the contract
+        let code_id = app
+            .store_code(ContractType::Artifact(artifact), &alice)
+            .unwrap();
+        assert_eq!(code_id, 1);
+
+        let err = app
+            .store_code(
+                ContractType::Artifact(Artifact::Bytes(b"not wasm".to_vec())),
+                &alice,
+            )
+            .unwrap_err()
+            .to_string();
+        #[cfg(feature = "wasm-inspect")]
+        assert!(err.starts_with("Invalid wasm code"), "{}", err);
+        #[cfg(not(feature = "wasm-inspect"))]
+        assert!(err.contains("with_artifact_contract"), "{}", err);
+    }
+
+    #[cfg(feature = "wasm-inspect")]
+    #[test]
+    fn store_artifact_reports_entry_points() {
+        let app = MultiTestRunner::new("osmo");
+        let alice = app.init_default_account().unwrap();
+
+        let err = app
+            .store_code(
+                ContractType::Artifact(Artifact::Local(counter::WASM_PATH.to_string())),
+                &alice,
+            )
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("entry points execute, instantiate, query"),
+            "{}",
+            err
+        );
+    }
+
     #[test]
     // This test should panic because we are trying to upload a wasm contract to a MultiTestRunner
     // which does not support wasm contracts.
@@ -672,4 +1091,30 @@ mod tests {
         app.increase_time(69).unwrap();
         assert_eq!(app.app.block_info().time.seconds(), time.seconds() + 69);
     }
+
+    #[test]
+    #[cfg(feature = "tokio")]
+    fn async_runner_adapter() {
+        use crate::helpers::block_on;
+        use crate::traits::AsyncCwItRunner;
+        use osmosis_std::types::cosmos::bank::v1beta1::QueryAllBalancesResponse;
+
+        let app = MultiTestRunner::new("osmo");
+        let alice = block_on(AsyncCwItRunner::init_account(&app, &[coin(1000, "uatom")])).unwrap();
+
+        let res: QueryAllBalancesResponse = block_on(AsyncCwItRunner::query(
+            &app,
+            "/cosmos.bank.v1beta1.Query/AllBalances",
+            &QueryAllBalancesRequest {
+                address: alice.address(),
+                pagination: None,
+            },
+        ))
+        .unwrap();
+        assert_eq!(res.balances[0].amount, "1000");
+
+        let time = app.app.block_info().time;
+        block_on(AsyncCwItRunner::increase_time(&app, 10)).unwrap();
+        assert_eq!(app.app.block_info().time.seconds(), time.seconds() + 10);
+    }
 }
@@ -4,7 +4,10 @@ use osmosis_std::{shim::Any, types::osmosis::lockup};
 use osmosis_test_tube::{Module, OsmosisTestApp, SigningAccount, Wasm};
 use prost::Message;
 
-use crate::{traits::CwItRunner, ContractType};
+use crate::{
+    traits::{BlockingRunner, CwItRunner},
+    ContractType,
+};
 
 #[cfg(feature = "multi-test")]
 use anyhow::bail;
@@ -18,8 +21,12 @@ impl CwItRunner<'_> for OsmosisTestApp {
             }
             ContractType::Artifact(artifact) => {
                 let bytes = artifact.get_wasm_byte_code()?;
+                #[cfg(feature = "wasm-inspect")]
+                let info = crate::artifact::inspect::check_wasm(&bytes)?;
                 let wasm = Wasm::new(self);
                 let code_id = wasm.store_code(&bytes, None, signer)?.data.code_id;
+                #[cfg(feature = "wasm-inspect")]
+                crate::artifact::inspect::record_stored_code(&bytes, info);
                 Ok(code_id)
             }
         }
@@ -47,6 +54,8 @@ impl CwItRunner<'_> for OsmosisTestApp {
     }
 }
 
+impl BlockingRunner for OsmosisTestApp {}
+
 /// A trait for enabling the functionality of whitelisting an address for force unlock of a locked
 /// LP position on Osmosis.
 pub trait WhitelistForceUnlock {
@@ -85,6 +94,7 @@ mod tests {
     use test_tube::{Account, Runner, RunnerError};
 
     use crate::artifact::Artifact;
+    use crate::helpers::{instantiate2_contract, predict_address};
 
     use super::*;
 
@@ -106,6 +116,33 @@ mod tests {
         assert_eq!(code_id, 1);
     }
 
+    #[test]
+    fn osmosis_test_app_instantiate2() {
+        let app = OsmosisTestApp::new();
+        let admin = app
+            .init_account(&[Coin::new(1000000000000, "uosmo")])
+            .unwrap();
+        let code_id = app
+            .store_code(
+                ContractType::Artifact(Artifact::Local(TEST_ARTIFACT.to_string())),
+                &admin,
+            )
+            .unwrap();
+
+        let predicted = predict_address(&app, code_id, &admin.address(), b"salt").unwrap();
+        let address: String = instantiate2_contract(
+            &app,
+            &admin,
+            code_id,
+            &serde_json::json!({ "count": 0 }),
+            b"salt",
+            &[],
+        )
+        .unwrap();
+
+        assert_eq!(address, predicted);
+    }
+
     #[test]
     #[should_panic]
     #[cfg(feature = "multi-test")]
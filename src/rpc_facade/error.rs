@@ -0,0 +1,63 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum RpcFacadeError {
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid chain id: {0}")]
+    InvalidChainId(String),
+
+    /// The server thread has stopped, e.g. because creating the backend panicked.
+    #[error("RPC facade is not running")]
+    Stopped,
+}
+
+/// A failed query or transaction, as returned in the `code`, `codespace` and `log` of an ABCI
+/// response.
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+#[error("ABCI error (codespace: {codespace}, code: {code}): {log}")]
+pub struct AbciError {
+    pub code: u32,
+    pub codespace: String,
+    pub log: String,
+}
+
+impl AbciError {
+    /// Cosmos SDK `ErrTxDecode`.
+    pub fn tx_decode(log: impl ToString) -> Self {
+        Self::sdk(2, log)
+    }
+
+    /// Cosmos SDK `ErrUnauthorized`, e.g. for messages not signed by their sender.
+    pub fn unauthorized(log: impl ToString) -> Self {
+        Self::sdk(4, log)
+    }
+
+    /// Cosmos SDK `ErrUnknownRequest`, e.g. for unsupported query paths.
+    pub fn unknown_request(log: impl ToString) -> Self {
+        Self::sdk(6, log)
+    }
+
+    /// Cosmos SDK `ErrInvalidRequest`.
+    pub fn invalid_request(log: impl ToString) -> Self {
+        Self::sdk(18, log)
+    }
+
+    /// An error that is not part of the Cosmos SDK, e.g. a failed contract execution.
+    pub fn internal(log: impl ToString) -> Self {
+        Self {
+            code: 1,
+            codespace: "undefined".to_string(),
+            log: log.to_string(),
+        }
+    }
+
+    fn sdk(code: u32, log: impl ToString) -> Self {
+        Self {
+            code,
+            codespace: "sdk".to_string(),
+            log: log.to_string(),
+        }
+    }
+}
@@ -0,0 +1,75 @@
+//! # RPC Facade
+//! This module contains a small in-process CometBFT compatible JSON-RPC server, backed by one of
+//! the in-process runners of this crate instead of a real node. It lets code that talks to a node,
+//! like [`RpcRunner`](crate::rpc_runner::RpcRunner), on-chain artifact downloads or off-chain
+//! bots, be tested without depending on a public endpoint.
+//!
+//! The server supports the `abci_query`, `broadcast_tx_async`, `broadcast_tx_sync`,
+//! `broadcast_tx_commit`, `block`, `status`, `tx` and `health` methods over HTTP. Websocket
+//! subscriptions are not supported. Every transaction is executed immediately in a new block.
+//!
+//! Backends implement [`FacadeBackend`]. Implementations are provided for `OsmosisTestApp`, which
+//! executes transactions as a real node would, and for `MultiTestRunner` through
+//! [`MultiTestBackend`]. Since the runners are not `Send`, the backend is created on the server
+//! thread and can be accessed with [`RpcFacade::with_backend`].
+//!
+//! ```ignore
+//! let facade = RpcFacade::start("cw-it-1", || {
+//!     let backend = MultiTestBackend::new(MultiTestRunner::new("osmo"));
+//!     backend.runner.app.sudo(BankSudo::Mint { .. }.into()).unwrap();
+//!     backend
+//! })?;
+//! let chain_config = ChainConfig {
+//!     rpc_endpoint: facade.endpoint(),
+//!     ..
+//! };
+//! ```
+use cosmrs::tendermint::abci::types::ExecTxResult;
+
+mod error;
+#[cfg(feature = "multi-test")]
+mod multi_test;
+#[cfg(feature = "osmosis-test-tube")]
+mod osmosis_test_app;
+mod server;
+
+pub use error::{AbciError, RpcFacadeError};
+#[cfg(feature = "multi-test")]
+pub use multi_test::MultiTestBackend;
+pub use server::RpcFacade;
+
+/// Gas used by every simulated transaction, unless the backend overrides
+/// [`FacadeBackend::simulate_tx`].
+pub const SIMULATED_GAS: u64 = 200_000;
+
+/// The chain state behind an [`RpcFacade`].
+pub trait FacadeBackend {
+    /// Answers the gRPC query `path` with the protobuf encoded request `data` and returns the
+    /// protobuf encoded response.
+    fn abci_query(&mut self, path: &str, data: &[u8]) -> Result<Vec<u8>, AbciError>;
+
+    /// Executes the encoded transaction `tx` and returns its result. The backend should move on
+    /// to the next block afterwards.
+    fn deliver_tx(&mut self, tx: &[u8]) -> ExecTxResult;
+
+    /// Returns the time of the current block in nanoseconds.
+    fn block_time_nanos(&self) -> u64;
+
+    /// Returns the gas used by the encoded transaction `tx`, answering
+    /// `/cosmos.tx.v1beta1.Service/Simulate` queries. Returns [`SIMULATED_GAS`] by default.
+    fn simulate_tx(&mut self, _tx: &[u8]) -> Result<u64, AbciError> {
+        Ok(SIMULATED_GAS)
+    }
+}
+
+/// Returns the result of a transaction that failed with `err`.
+#[cfg(any(feature = "multi-test", feature = "osmosis-test-tube"))]
+pub(crate) fn failed_tx(err: AbciError, gas_wanted: i64) -> ExecTxResult {
+    ExecTxResult {
+        code: err.code.into(),
+        codespace: err.codespace,
+        log: err.log,
+        gas_wanted,
+        ..Default::default()
+    }
+}
@@ -0,0 +1,345 @@
+use std::collections::HashMap;
+
+use apollo_cw_multi_test::next_block;
+use cosmrs::proto::cosmos::auth::v1beta1::{
+    BaseAccount, QueryAccountRequest, QueryAccountResponse,
+};
+use cosmrs::proto::cosmos::base::abci::v1beta1::TxMsgData;
+use cosmrs::tendermint::abci::{types::ExecTxResult, Event};
+use cosmrs::tx::{SignerPublicKey, Tx};
+use cosmrs::Any;
+use cosmwasm_std::Addr;
+use osmosis_std::types::cosmos::bank::v1beta1::{
+    MsgMultiSend, MsgSend, QueryAllBalancesRequest, QueryAllBalancesResponse, QueryBalanceRequest,
+    QueryBalanceResponse, QuerySupplyOfRequest, QuerySupplyOfResponse,
+};
+use osmosis_std::types::cosmos::staking::v1beta1::{
+    MsgBeginRedelegate, MsgDelegate, MsgUndelegate,
+};
+use osmosis_std::types::cosmwasm::wasm::v1::{
+    CodeInfoResponse, MsgClearAdmin, MsgExecuteContract, MsgInstantiateContract,
+    MsgInstantiateContract2, MsgMigrateContract, MsgUpdateAdmin, QueryCodeRequest,
+    QueryCodeResponse, QueryContractInfoRequest, QueryContractInfoResponse,
+    QuerySmartContractStateRequest, QuerySmartContractStateResponse,
+};
+use prost::Message;
+use serde::de::DeserializeOwned;
+use test_tube::Runner;
+
+use super::{failed_tx, AbciError, FacadeBackend};
+use crate::multi_test::{any_to_cosmos_msgs, MultiTestRunner};
+use crate::traits::CwItRunner;
+
+/// A [`FacadeBackend`] executing transactions with a [`MultiTestRunner`].
+///
+/// Signatures are not verified. Messages are executed by the account of the first signer, the
+/// same way as [`Runner::execute_multiple_raw`] on the runner, and transactions with a message
+/// sent by another account are rejected. `MsgInstantiateContract2` is supported the same way as on
+/// the runner. Auth account queries are answered with sequences tracked by the backend, and the
+/// bank and wasm queries supported by the runner are answered by it. Other queries fail with an
+/// unknown request error.
+pub struct MultiTestBackend<'a> {
+    pub runner: MultiTestRunner<'a>,
+    /// Account numbers and sequences by address.
+    accounts: HashMap<String, (u64, u64)>,
+}
+
+impl<'a> MultiTestBackend<'a> {
+    pub fn new(runner: MultiTestRunner<'a>) -> Self {
+        Self {
+            runner,
+            accounts: HashMap::new(),
+        }
+    }
+
+    /// Returns the account number and sequence of `address`, creating the account if needed.
+    fn account(&mut self, address: &str) -> &mut (u64, u64) {
+        let account_number = self.accounts.len() as u64;
+        self.accounts
+            .entry(address.to_string())
+            .or_insert((account_number, 0))
+    }
+
+    fn query_account(&mut self, data: &[u8]) -> Result<Vec<u8>, AbciError> {
+        let req = QueryAccountRequest::decode(data).map_err(AbciError::invalid_request)?;
+        let (account_number, sequence) = *self.account(&req.address);
+        let account = BaseAccount {
+            address: req.address,
+            pub_key: None,
+            account_number,
+            sequence,
+        };
+
+        Ok(QueryAccountResponse {
+            account: Some(Any {
+                type_url: "/cosmos.auth.v1beta1.BaseAccount".to_string(),
+                value: account.encode_to_vec(),
+            }),
+        }
+        .encode_to_vec())
+    }
+
+    /// Answers a code query with the checksum the runner gives the code, which is what
+    /// [`crate::helpers::predict_address`] needs. The wasm bytes are left out, as contracts stored
+    /// on `cw-multi-test` have none.
+    fn query_code(&self, data: &[u8]) -> Result<Vec<u8>, AbciError> {
+        let req = QueryCodeRequest::decode(data).map_err(AbciError::invalid_request)?;
+        let data_hash = self
+            .runner
+            .query_code_checksum(req.code_id)
+            .map_err(AbciError::invalid_request)?;
+
+        Ok(QueryCodeResponse {
+            code_info: Some(CodeInfoResponse {
+                code_id: req.code_id,
+                creator: String::new(),
+                data_hash,
+                instantiate_permission: None,
+            }),
+            data: vec![],
+        }
+        .encode_to_vec())
+    }
+
+    /// Answers the query with the runner, which returns JSON encoded responses, and encodes the
+    /// response as protobuf.
+    fn query_runner<Q, R>(&self, path: &str, data: &[u8]) -> Result<Vec<u8>, AbciError>
+    where
+        Q: Message + Default,
+        R: Message + DeserializeOwned + Default,
+    {
+        let req = Q::decode(data).map_err(AbciError::invalid_request)?;
+        let res: R = self
+            .runner
+            .query(path, &req)
+            .map_err(AbciError::invalid_request)?;
+        Ok(res.encode_to_vec())
+    }
+
+    /// Executes the messages of `tx` and returns the encoded `TxMsgData` and the events.
+    fn execute(&mut self, tx: &Tx) -> Result<(Vec<u8>, Vec<Event>), AbciError> {
+        let signer = signer_address(tx, self.runner.address_prefix)?;
+        // The sequence is increased even if the messages fail, as on a real chain
+        self.account(&signer).1 += 1;
+
+        let mut msgs = vec![];
+        let mut msg_counts = vec![];
+        for msg in &tx.body.messages {
+            if let Some(sender) = msg_senders(msg)?
+                .into_iter()
+                .find(|sender| *sender != signer)
+            {
+                return Err(AbciError::unauthorized(format!(
+                    "{} is sent by {}, but the transaction is signed by {}",
+                    msg.type_url, sender, signer
+                )));
+            }
+            let converted = any_to_cosmos_msgs(msg).map_err(AbciError::tx_decode)?;
+            msg_counts.push(converted.len());
+            msgs.extend(converted);
+        }
+
+        let mut app_responses = self
+            .runner
+            .execute_multi(Addr::unchecked(signer), msgs)
+            .map_err(|e| AbciError::internal(format!("{:#}", e)))?
+            .into_iter();
+
+        // Every message of the transaction gets a response, even if it has no data
+        let mut msg_responses = vec![];
+        let mut events = vec![];
+        for (msg, count) in tx.body.messages.iter().zip(msg_counts) {
+            let mut data = None;
+            for res in app_responses.by_ref().take(count) {
+                events.extend(res.events.into_iter().map(|e| {
+                    Event::new(
+                        e.ty,
+                        e.attributes.into_iter().map(|a| (a.key, a.value, true)),
+                    )
+                }));
+                data = res.data.or(data);
+            }
+            msg_responses.push(Any {
+                type_url: format!("{}Response", msg.type_url),
+                value: data.map(|d| d.to_vec()).unwrap_or_default(),
+            });
+        }
+
+        #[allow(deprecated)]
+        let tx_msg_data = TxMsgData {
+            data: vec![],
+            msg_responses,
+        };
+        Ok((tx_msg_data.encode_to_vec(), events))
+    }
+}
+
+impl<'a> From<MultiTestRunner<'a>> for MultiTestBackend<'a> {
+    fn from(runner: MultiTestRunner<'a>) -> Self {
+        Self::new(runner)
+    }
+}
+
+impl FacadeBackend for MultiTestBackend<'_> {
+    fn abci_query(&mut self, path: &str, data: &[u8]) -> Result<Vec<u8>, AbciError> {
+        match path {
+            "/cosmos.auth.v1beta1.Query/Account" => self.query_account(data),
+            "/cosmos.bank.v1beta1.Query/Balance" => {
+                self.query_runner::<QueryBalanceRequest, QueryBalanceResponse>(path, data)
+            }
+            "/cosmos.bank.v1beta1.Query/AllBalances" => {
+                self.query_runner::<QueryAllBalancesRequest, QueryAllBalancesResponse>(path, data)
+            }
+            "/cosmos.bank.v1beta1.Query/SupplyOf" => {
+                self.query_runner::<QuerySupplyOfRequest, QuerySupplyOfResponse>(path, data)
+            }
+            "/cosmwasm.wasm.v1.Query/SmartContractState" => self
+                .query_runner::<QuerySmartContractStateRequest, QuerySmartContractStateResponse>(
+                    path, data,
+                ),
+            "/cosmwasm.wasm.v1.Query/ContractInfo" => {
+                self.query_runner::<QueryContractInfoRequest, QueryContractInfoResponse>(path, data)
+            }
+            "/cosmwasm.wasm.v1.Query/Code" => self.query_code(data),
+            _ => Err(AbciError::unknown_request(format!(
+                "unknown query path {}",
+                path
+            ))),
+        }
+    }
+
+    fn deliver_tx(&mut self, tx: &[u8]) -> ExecTxResult {
+        let tx = match Tx::from_bytes(tx) {
+            Ok(tx) => tx,
+            Err(e) => return failed_tx(AbciError::tx_decode(e), 0),
+        };
+        let gas_wanted = tx.auth_info.fee.gas_limit as i64;
+
+        let res = self.execute(&tx);
+        self.runner.app.update_block(next_block);
+
+        match res {
+            Ok((data, events)) => ExecTxResult {
+                data: data.into(),
+                events,
+                gas_wanted,
+                ..Default::default()
+            },
+            Err(e) => failed_tx(e, gas_wanted),
+        }
+    }
+
+    fn block_time_nanos(&self) -> u64 {
+        self.runner.app.block_info().time.nanos()
+    }
+}
+
+/// Returns the accounts that must sign `msg`, i.e. its sender or the inputs of a `MsgMultiSend`.
+/// Returns no accounts for messages that are passed on to the stargate keeper, whose senders are
+/// not known.
+fn msg_senders(msg: &Any) -> Result<Vec<String>, AbciError> {
+    fn decode<M: Message + Default>(msg: &Any) -> Result<M, AbciError> {
+        M::decode(msg.value.as_slice()).map_err(AbciError::tx_decode)
+    }
+
+    Ok(match msg.type_url.as_str() {
+        MsgExecuteContract::TYPE_URL => vec![decode::<MsgExecuteContract>(msg)?.sender],
+        MsgInstantiateContract::TYPE_URL => vec![decode::<MsgInstantiateContract>(msg)?.sender],
+        MsgInstantiateContract2::TYPE_URL => vec![decode::<MsgInstantiateContract2>(msg)?.sender],
+        MsgMigrateContract::TYPE_URL => vec![decode::<MsgMigrateContract>(msg)?.sender],
+        MsgUpdateAdmin::TYPE_URL => vec![decode::<MsgUpdateAdmin>(msg)?.sender],
+        MsgClearAdmin::TYPE_URL => vec![decode::<MsgClearAdmin>(msg)?.sender],
+        MsgSend::TYPE_URL => vec![decode::<MsgSend>(msg)?.from_address],
+        MsgMultiSend::TYPE_URL => decode::<MsgMultiSend>(msg)?
+            .inputs
+            .into_iter()
+            .map(|input| input.address)
+            .collect(),
+        MsgDelegate::TYPE_URL => vec![decode::<MsgDelegate>(msg)?.delegator_address],
+        MsgUndelegate::TYPE_URL => vec![decode::<MsgUndelegate>(msg)?.delegator_address],
+        MsgBeginRedelegate::TYPE_URL => vec![decode::<MsgBeginRedelegate>(msg)?.delegator_address],
+        _ => vec![],
+    })
+}
+
+/// Returns the address of the first signer of `tx`.
+fn signer_address(tx: &Tx, prefix: &str) -> Result<String, AbciError> {
+    let public_key = match tx
+        .auth_info
+        .signer_infos
+        .first()
+        .and_then(|info| info.public_key.as_ref())
+    {
+        Some(SignerPublicKey::Single(public_key)) => public_key,
+        _ => {
+            return Err(AbciError::tx_decode(
+                "transaction must have a single key signer",
+            ))
+        }
+    };
+
+    public_key
+        .account_id(prefix)
+        .map(|id| id.to_string())
+        .map_err(AbciError::tx_decode)
+}
+
+#[cfg(test)]
+mod tests {
+    use osmosis_std::types::cosmos::bank::v1beta1::{Input, Output};
+
+    use super::*;
+
+    fn any<M: Message>(msg: M, type_url: &str) -> Any {
+        Any {
+            type_url: type_url.to_string(),
+            value: msg.encode_to_vec(),
+        }
+    }
+
+    #[test]
+    fn senders_of_messages() {
+        let send = MsgSend {
+            from_address: "osmo1alice".to_string(),
+            to_address: "osmo1bob".to_string(),
+            amount: vec![],
+        };
+        assert_eq!(
+            msg_senders(&any(send, MsgSend::TYPE_URL)).unwrap(),
+            vec!["osmo1alice"]
+        );
+
+        let multi_send = MsgMultiSend {
+            inputs: vec![
+                Input {
+                    address: "osmo1alice".to_string(),
+                    coins: vec![],
+                },
+                Input {
+                    address: "osmo1bob".to_string(),
+                    coins: vec![],
+                },
+            ],
+            outputs: vec![Output {
+                address: "osmo1carol".to_string(),
+                coins: vec![],
+            }],
+        };
+        assert_eq!(
+            msg_senders(&any(multi_send, MsgMultiSend::TYPE_URL)).unwrap(),
+            vec!["osmo1alice", "osmo1bob"]
+        );
+
+        let other = Any {
+            type_url: "/osmosis.gamm.v1beta1.MsgSwapExactAmountIn".to_string(),
+            value: vec![],
+        };
+        assert!(msg_senders(&other).unwrap().is_empty());
+
+        let invalid = Any {
+            type_url: MsgSend::TYPE_URL.to_string(),
+            value: vec![0xff],
+        };
+        assert_eq!(msg_senders(&invalid).unwrap_err().code, 2);
+    }
+}
@@ -0,0 +1,95 @@
+use cosmrs::tendermint::abci::{types::ExecTxResult, Event};
+use osmosis_test_tube::OsmosisTestApp;
+use prost::bytes::{Buf, BufMut};
+use prost::encoding::{DecodeContext, WireType};
+use prost::{DecodeError, Message};
+use serde::Deserialize;
+use test_tube::Runner;
+
+use super::{failed_tx, AbciError, FacadeBackend};
+
+impl FacadeBackend for OsmosisTestApp {
+    fn abci_query(&mut self, path: &str, data: &[u8]) -> Result<Vec<u8>, AbciError> {
+        let res: RawMessage = self
+            .query(path, &RawMessage(data.to_vec()))
+            .map_err(AbciError::invalid_request)?;
+        Ok(res.0)
+    }
+
+    fn deliver_tx(&mut self, tx: &[u8]) -> ExecTxResult {
+        let res = match self.execute_tx(tx) {
+            Ok(res) => res,
+            Err(e) => return failed_tx(AbciError::internal(e), 0),
+        };
+
+        ExecTxResult {
+            code: res.code.into(),
+            data: res.data,
+            log: res.log,
+            info: res.info,
+            gas_wanted: res.gas_wanted,
+            gas_used: res.gas_used,
+            events: res
+                .events
+                .into_iter()
+                .filter_map(|e| Event::try_from(e).ok())
+                .collect(),
+            codespace: res.codespace,
+        }
+    }
+
+    fn block_time_nanos(&self) -> u64 {
+        self.get_block_time_nanos() as u64
+    }
+}
+
+/// A message that is already protobuf encoded. Used to pass queries and their responses through
+/// the app without knowing their types.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+struct RawMessage(Vec<u8>);
+
+impl Message for RawMessage {
+    fn encode_raw<B: BufMut>(&self, buf: &mut B) {
+        buf.put_slice(&self.0);
+    }
+
+    fn merge<B>(&mut self, mut buf: B) -> Result<(), DecodeError>
+    where
+        B: Buf,
+    {
+        self.0
+            .extend_from_slice(&buf.copy_to_bytes(buf.remaining()));
+        Ok(())
+    }
+
+    fn merge_field<B: Buf>(
+        &mut self,
+        tag: u32,
+        wire_type: WireType,
+        buf: &mut B,
+        ctx: DecodeContext,
+    ) -> Result<(), DecodeError> {
+        // Fields are only merged by the default `merge`, which is overridden
+        prost::encoding::skip_field(wire_type, tag, buf, ctx)
+    }
+
+    fn encoded_len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raw_message_passthrough() {
+        let bytes = vec![0x0a, 0x03, b'f', b'o', b'o', 0x10, 0x2a];
+        let msg = RawMessage::decode(bytes.as_slice()).unwrap();
+        assert_eq!(msg.encode_to_vec(), bytes);
+    }
+}
@@ -0,0 +1,634 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use cosmrs::proto::cosmos::base::abci::v1beta1::GasInfo;
+use cosmrs::proto::cosmos::tx::v1beta1::{SimulateRequest, SimulateResponse};
+use cosmrs::rpc::endpoint::{abci_query, block as block_endpoint, broadcast, tx as tx_endpoint};
+use cosmrs::tendermint::abci::{response::CheckTx, types::ExecTxResult};
+use cosmrs::tendermint::block::{self, parts, Commit, Header, Height};
+use cosmrs::tendermint::{account, chain, evidence, AppHash, Block, Hash, Time};
+use prost::Message;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use super::{AbciError, FacadeBackend, RpcFacadeError};
+
+/// How long the server thread sleeps when there are no connections or jobs.
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// How long the server waits for a client to send its request.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Version of the block protocol reported in block headers and the node status.
+const BLOCK_PROTOCOL_VERSION: u64 = 11;
+
+const SIMULATE_PATH: &str = "/cosmos.tx.v1beta1.Service/Simulate";
+
+type Job<B> = Box<dyn FnOnce(&mut B) + Send>;
+
+/// Handle to a running JSON-RPC server. The server is stopped when the handle is dropped.
+pub struct RpcFacade<B> {
+    addr: SocketAddr,
+    jobs: mpsc::Sender<Job<B>>,
+    shutdown: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl<B: FacadeBackend + 'static> RpcFacade<B> {
+    /// Starts a server for the chain `chain_id` on a random local port. The backend is created
+    /// by calling `backend` on the server thread.
+    pub fn start<F>(chain_id: &str, backend: F) -> Result<Self, RpcFacadeError>
+    where
+        F: FnOnce() -> B + Send + 'static,
+    {
+        let chain_id = chain::Id::try_from(chain_id.to_string())
+            .map_err(|e| RpcFacadeError::InvalidChainId(e.to_string()))?;
+
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        listener.set_nonblocking(true)?;
+        let addr = listener.local_addr()?;
+
+        let (jobs, job_receiver) = mpsc::channel();
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let handle = thread::Builder::new()
+            .name("cw-it-rpc-facade".to_string())
+            .spawn({
+                let shutdown = shutdown.clone();
+                move || {
+                    let mut node = Node::new(backend(), chain_id, addr);
+                    node.serve(listener, job_receiver, &shutdown);
+                }
+            })?;
+
+        Ok(Self {
+            addr,
+            jobs,
+            shutdown,
+            handle: Some(handle),
+        })
+    }
+
+    /// Runs `f` with the backend on the server thread and returns its result, e.g. to fund
+    /// accounts or store code. No requests are served while `f` runs.
+    pub fn with_backend<T, F>(&self, f: F) -> Result<T, RpcFacadeError>
+    where
+        F: FnOnce(&mut B) -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+        self.jobs
+            .send(Box::new(move |backend| {
+                // The receiver is only dropped if the caller is gone
+                let _ = sender.send(f(backend));
+            }))
+            .map_err(|_| RpcFacadeError::Stopped)?;
+
+        receiver.recv().map_err(|_| RpcFacadeError::Stopped)
+    }
+}
+
+impl<B> RpcFacade<B> {
+    /// Returns the address the server listens on.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Returns the URL of the server, to be used as the `rpc_endpoint` of a chain config.
+    pub fn endpoint(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+}
+
+impl<B> Drop for RpcFacade<B> {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// A block produced by the facade.
+struct FacadeBlock {
+    time: Time,
+    txs: Vec<Vec<u8>>,
+}
+
+/// A transaction included in a block.
+struct TxRecord {
+    height: u64,
+    tx: Vec<u8>,
+    result: ExecTxResult,
+}
+
+/// The node state, owned by the server thread.
+struct Node<B> {
+    backend: B,
+    chain_id: chain::Id,
+    addr: SocketAddr,
+    /// Blocks by height - 1. The first block is produced when the server is started.
+    blocks: Vec<FacadeBlock>,
+    txs: HashMap<Hash, TxRecord>,
+}
+
+impl<B: FacadeBackend> Node<B> {
+    fn new(backend: B, chain_id: chain::Id, addr: SocketAddr) -> Self {
+        let mut node = Self {
+            backend,
+            chain_id,
+            addr,
+            blocks: vec![],
+            txs: HashMap::new(),
+        };
+        node.push_block(vec![]);
+        node
+    }
+
+    fn serve(
+        &mut self,
+        listener: TcpListener,
+        jobs: mpsc::Receiver<Job<B>>,
+        shutdown: &AtomicBool,
+    ) {
+        while !shutdown.load(Ordering::Relaxed) {
+            while let Ok(job) = jobs.try_recv() {
+                job(&mut self.backend);
+            }
+
+            match listener.accept() {
+                // A failed connection only affects its client
+                Ok((stream, _)) => {
+                    let _ = self.handle_connection(stream);
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+                Err(_) => return,
+            }
+        }
+    }
+
+    /// Reads one HTTP request from `stream`, answers it and closes the connection.
+    fn handle_connection(&mut self, mut stream: TcpStream) -> io::Result<()> {
+        // Accepted sockets inherit the non-blocking flag of the listener on some platforms
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+
+        let body = read_http_body(&mut stream)?;
+        let response = serde_json::to_vec(&self.handle_request(&body))?;
+
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            response.len()
+        )?;
+        stream.write_all(&response)?;
+        stream.flush()
+    }
+
+    /// Answers the JSON-RPC request `body`.
+    fn handle_request(&mut self, body: &[u8]) -> Value {
+        let request: Value = match serde_json::from_slice(body) {
+            Ok(request) => request,
+            Err(e) => return error_response(Value::Null, RpcError::parse(e)),
+        };
+        let id = request.get("id").cloned().unwrap_or(Value::Null);
+        let method = request.get("method").and_then(Value::as_str).unwrap_or("");
+        let params = request.get("params").cloned().unwrap_or_else(|| json!({}));
+
+        let result = match method {
+            "abci_query" => params_as(params).and_then(|req| self.abci_query(req)),
+            "broadcast_tx_async" | "broadcast_tx_sync" => {
+                params_as(params).and_then(|req| self.broadcast_tx_sync(req))
+            }
+            "broadcast_tx_commit" => {
+                params_as(params).and_then(|req| self.broadcast_tx_commit(req))
+            }
+            "block" => params_as(params).and_then(|req| self.block(req)),
+            "tx" => params_as(params).and_then(|req| self.tx(req)),
+            "status" => self.status(),
+            "health" => Ok(json!({})),
+            method => Err(RpcError::method_not_found(method)),
+        };
+
+        match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(e) => error_response(id, e),
+        }
+    }
+
+    fn abci_query(&mut self, req: abci_query::Request) -> Result<Value, RpcError> {
+        let path = req.path.unwrap_or_default();
+        let res = if path == SIMULATE_PATH {
+            self.simulate(&req.data)
+        } else {
+            self.backend.abci_query(&path, &req.data)
+        };
+
+        let query = match res {
+            Ok(value) => abci_query::AbciQuery {
+                value,
+                height: self.latest_height()?,
+                ..Default::default()
+            },
+            Err(e) => abci_query::AbciQuery {
+                code: e.code.into(),
+                codespace: e.codespace,
+                log: e.log,
+                height: self.latest_height()?,
+                ..Default::default()
+            },
+        };
+
+        to_value(abci_query::Response { response: query })
+    }
+
+    fn simulate(&mut self, data: &[u8]) -> Result<Vec<u8>, AbciError> {
+        let req = SimulateRequest::decode(data).map_err(AbciError::tx_decode)?;
+        let gas_used = self.backend.simulate_tx(&req.tx_bytes)?;
+
+        Ok(SimulateResponse {
+            gas_info: Some(GasInfo {
+                gas_wanted: 0,
+                gas_used,
+            }),
+            result: Some(Default::default()),
+        }
+        .encode_to_vec())
+    }
+
+    /// Executes `tx` in a new block and returns its hash and the height of the block.
+    fn deliver(&mut self, tx: Vec<u8>) -> (Hash, u64) {
+        let hash = Hash::Sha256(Sha256::digest(&tx).into());
+        let time = self.backend.block_time_nanos();
+        let result = self.backend.deliver_tx(&tx);
+
+        let height = self.push_block_at(time, vec![tx.clone()]);
+        self.txs.insert(hash, TxRecord { height, tx, result });
+
+        (hash, height)
+    }
+
+    fn broadcast_tx_sync(&mut self, req: broadcast::tx_sync::Request) -> Result<Value, RpcError> {
+        let (hash, _) = self.deliver(req.tx);
+        let result = &self.txs[&hash].result;
+
+        to_value(broadcast::tx_sync::Response {
+            code: result.code,
+            data: result.data.clone(),
+            log: result.log.clone(),
+            hash,
+        })
+    }
+
+    fn broadcast_tx_commit(
+        &mut self,
+        req: broadcast::tx_commit::Request,
+    ) -> Result<Value, RpcError> {
+        let (hash, height) = self.deliver(req.tx);
+
+        // CometBFT 0.37 nodes still call the result `deliver_tx`
+        Ok(json!({
+            "check_tx": to_value(CheckTx::default())?,
+            "deliver_tx": to_value(&self.txs[&hash].result)?,
+            "hash": hash,
+            "height": height.to_string(),
+        }))
+    }
+
+    fn tx(&self, req: tx_endpoint::Request) -> Result<Value, RpcError> {
+        let record = self
+            .txs
+            .get(&req.hash)
+            .ok_or_else(|| RpcError::internal(format!("tx ({}) not found", req.hash)))?;
+
+        to_value(tx_endpoint::Response {
+            hash: req.hash,
+            height: to_height(record.height)?,
+            index: 0,
+            tx_result: record.result.clone(),
+            tx: record.tx.clone(),
+            proof: None,
+        })
+    }
+
+    fn block(&self, req: block_endpoint::Request) -> Result<Value, RpcError> {
+        let latest = self.blocks.len() as u64;
+        let height = req.height.map_or(latest, |h| h.value());
+        if height == 0 || height > latest {
+            return Err(RpcError::internal(format!(
+                "height {} must be less than or equal to the current blockchain height {}",
+                height, latest
+            )));
+        }
+
+        to_value(block_endpoint::Response {
+            block_id: self.block_id(height),
+            block: self.build_block(height)?,
+        })
+    }
+
+    fn status(&self) -> Result<Value, RpcError> {
+        let latest = self.blocks.len() as u64;
+        let time = |height: u64| to_value(self.blocks[height as usize - 1].time);
+
+        Ok(json!({
+            "node_info": {
+                "protocol_version": {
+                    "p2p": "8",
+                    "block": BLOCK_PROTOCOL_VERSION.to_string(),
+                    "app": "0",
+                },
+                "id": "0000000000000000000000000000000000000000",
+                "listen_addr": format!("tcp://{}", self.addr),
+                "network": self.chain_id.as_str(),
+                "version": "0.37.2",
+                "channels": "40202122233038606100",
+                "moniker": "cw-it-rpc-facade",
+                "other": {
+                    "tx_index": "on",
+                    "rpc_address": format!("tcp://{}", self.addr),
+                },
+            },
+            "sync_info": {
+                "latest_block_hash": self.block_id(latest).hash,
+                "latest_app_hash": "",
+                "latest_block_height": latest.to_string(),
+                "latest_block_time": time(latest)?,
+                "earliest_block_hash": self.block_id(1).hash,
+                "earliest_app_hash": "",
+                "earliest_block_height": "1",
+                "earliest_block_time": time(1)?,
+                "catching_up": false,
+            },
+            "validator_info": {
+                "address": "0000000000000000000000000000000000000000",
+                "pub_key": {
+                    "type": "tendermint/PubKeyEd25519",
+                    "value": "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=",
+                },
+                "voting_power": "10",
+            },
+        }))
+    }
+
+    fn latest_height(&self) -> Result<Height, RpcError> {
+        to_height(self.blocks.len() as u64)
+    }
+
+    fn push_block(&mut self, txs: Vec<Vec<u8>>) -> u64 {
+        let time = self.backend.block_time_nanos();
+        self.push_block_at(time, txs)
+    }
+
+    fn push_block_at(&mut self, time_nanos: u64, txs: Vec<Vec<u8>>) -> u64 {
+        let time = Time::from_unix_timestamp(
+            (time_nanos / 1_000_000_000) as i64,
+            (time_nanos % 1_000_000_000) as u32,
+        )
+        .unwrap_or_else(|_| Time::unix_epoch());
+        self.blocks.push(FacadeBlock { time, txs });
+        self.blocks.len() as u64
+    }
+
+    /// Returns a deterministic ID for the block at `height`. The facade doesn't compute real
+    /// block hashes.
+    fn block_id(&self, height: u64) -> block::Id {
+        let hash = Sha256::new()
+            .chain_update(self.chain_id.as_str())
+            .chain_update(height.to_be_bytes())
+            .finalize();
+        block::Id {
+            hash: Hash::Sha256(hash.into()),
+            part_set_header: parts::Header::default(),
+        }
+    }
+
+    fn build_block(&self, height: u64) -> Result<Block, RpcError> {
+        let stored = &self.blocks[height as usize - 1];
+        let header = Header {
+            version: block::header::Version {
+                block: BLOCK_PROTOCOL_VERSION,
+                app: 0,
+            },
+            chain_id: self.chain_id.clone(),
+            height: to_height(height)?,
+            time: stored.time,
+            last_block_id: (height > 1).then(|| self.block_id(height - 1)),
+            last_commit_hash: None,
+            data_hash: None,
+            validators_hash: Hash::None,
+            next_validators_hash: Hash::None,
+            consensus_hash: Hash::None,
+            app_hash: AppHash::default(),
+            last_results_hash: None,
+            evidence_hash: None,
+            proposer_address: account::Id::new([0; 20]),
+        };
+        // Only the first block has no last commit
+        let last_commit = if height > 1 {
+            Some(Commit {
+                height: to_height(height - 1)?,
+                round: Default::default(),
+                block_id: self.block_id(height - 1),
+                signatures: vec![],
+            })
+        } else {
+            None
+        };
+
+        Block::new(
+            header,
+            stored.txs.clone(),
+            evidence::List::default(),
+            last_commit,
+        )
+        .map_err(RpcError::internal)
+    }
+}
+
+/// Reads an HTTP request from `stream` and returns its body.
+fn read_http_body(stream: &mut TcpStream) -> io::Result<Vec<u8>> {
+    let mut reader = BufReader::new(stream);
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value
+                    .trim()
+                    .parse()
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            }
+        }
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+    Ok(body)
+}
+
+fn to_height(height: u64) -> Result<Height, RpcError> {
+    Height::try_from(height).map_err(RpcError::internal)
+}
+
+fn params_as<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(RpcError::invalid_params)
+}
+
+fn to_value<T: Serialize>(value: T) -> Result<Value, RpcError> {
+    serde_json::to_value(value).map_err(RpcError::internal)
+}
+
+fn error_response(id: Value, error: RpcError) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": error })
+}
+
+/// A JSON-RPC error, in the format returned by CometBFT.
+#[derive(Debug, Serialize)]
+struct RpcError {
+    code: i32,
+    message: &'static str,
+    data: String,
+}
+
+impl RpcError {
+    fn parse(data: impl Display) -> Self {
+        Self {
+            code: -32700,
+            message: "Parse error",
+            data: data.to_string(),
+        }
+    }
+
+    fn method_not_found(method: &str) -> Self {
+        Self {
+            code: -32601,
+            message: "Method not found",
+            data: format!("method {} is not supported by the RPC facade", method),
+        }
+    }
+
+    fn invalid_params(data: impl Display) -> Self {
+        Self {
+            code: -32602,
+            message: "Invalid params",
+            data: data.to_string(),
+        }
+    }
+
+    fn internal(data: impl Display) -> Self {
+        Self {
+            code: -32603,
+            message: "Internal error",
+            data: data.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmrs::rpc::{Client, HttpClient};
+
+    use super::*;
+    use crate::helpers::block_on;
+
+    /// Echoes queries and returns transactions as their result data.
+    struct EchoBackend;
+
+    impl FacadeBackend for EchoBackend {
+        fn abci_query(&mut self, path: &str, data: &[u8]) -> Result<Vec<u8>, AbciError> {
+            match path {
+                "/echo" => Ok(data.to_vec()),
+                _ => Err(AbciError::unknown_request(path)),
+            }
+        }
+
+        fn deliver_tx(&mut self, tx: &[u8]) -> ExecTxResult {
+            ExecTxResult {
+                data: tx.to_vec().into(),
+                ..Default::default()
+            }
+        }
+
+        fn block_time_nanos(&self) -> u64 {
+            1_700_000_000_000_000_000
+        }
+    }
+
+    fn start() -> (RpcFacade<EchoBackend>, HttpClient) {
+        let facade = RpcFacade::start("cw-it-1", || EchoBackend).unwrap();
+        let client = HttpClient::new(facade.endpoint().as_str()).unwrap();
+        (facade, client)
+    }
+
+    #[test]
+    fn status_and_blocks() {
+        let (_facade, client) = start();
+
+        block_on(client.health()).unwrap();
+        let status = block_on(client.status()).unwrap();
+        assert_eq!(status.node_info.network.as_str(), "cw-it-1");
+        assert_eq!(status.sync_info.latest_block_height.value(), 1);
+
+        let res = block_on(client.broadcast_tx_commit(b"tx".to_vec())).unwrap();
+        assert_eq!(res.height.value(), 2);
+        assert_eq!(res.tx_result.data.as_ref(), b"tx");
+
+        let block = block_on(client.latest_block()).unwrap().block;
+        assert_eq!(block.header.height.value(), 2);
+        assert_eq!(block.data, vec![b"tx".to_vec()]);
+        assert_eq!(
+            block.header.time,
+            Time::from_unix_timestamp(1_700_000_000, 0).unwrap()
+        );
+
+        let first = block_on(client.block(1u32)).unwrap();
+        assert!(first.block.last_commit.is_none());
+        assert_eq!(
+            block.header.last_block_id.unwrap().hash,
+            first.block_id.hash
+        );
+    }
+
+    #[test]
+    fn queries_and_txs() {
+        let (_facade, client) = start();
+
+        let res =
+            block_on(client.abci_query(Some("/echo".to_string()), b"data".to_vec(), None, false))
+                .unwrap();
+        assert!(res.code.is_ok());
+        assert_eq!(res.value, b"data");
+
+        let res =
+            block_on(client.abci_query(Some("/unknown".to_string()), vec![], None, false)).unwrap();
+        assert_eq!(res.code.value(), 6);
+        assert_eq!(res.codespace, "sdk");
+
+        let hash = block_on(client.broadcast_tx_sync(b"tx".to_vec()))
+            .unwrap()
+            .hash;
+        assert_eq!(hash, Hash::Sha256(Sha256::digest(b"tx").into()));
+
+        let res = block_on(client.tx(hash, false)).unwrap();
+        assert_eq!(res.height.value(), 2);
+        assert_eq!(res.tx, b"tx");
+
+        let missing = Hash::Sha256([0; 32]);
+        assert!(block_on(client.tx(missing, false)).is_err());
+    }
+}
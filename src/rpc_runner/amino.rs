@@ -0,0 +1,712 @@
+//! Legacy amino JSON encoding of transactions, used to sign transactions with
+//! `SIGN_MODE_LEGACY_AMINO_JSON`.
+//!
+//! Only the messages listed in [`msg_to_amino_json`] are supported, since amino JSON needs a
+//! hand written encoding per message type.
+
+use base64::Engine;
+use cosmrs::proto::cosmos::authz::v1beta1::{GenericAuthorization, MsgExec, MsgGrant, MsgRevoke};
+use cosmrs::proto::cosmos::bank::v1beta1::{MsgMultiSend, MsgSend};
+use cosmrs::proto::cosmos::base::v1beta1::Coin as ProtoCoin;
+use cosmrs::proto::cosmwasm::wasm::v1::{
+    AccessConfig, MsgClearAdmin, MsgExecuteContract, MsgInstantiateContract,
+    MsgInstantiateContract2, MsgMigrateContract, MsgStoreCode, MsgUpdateAdmin,
+};
+use cosmrs::tendermint::Time;
+use cosmrs::tx::Fee;
+use cosmrs::Any;
+use prost::Message;
+use serde_json::{json, Map, Value};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum AminoError {
+    #[error("Message type {0} is not supported in amino JSON sign mode")]
+    UnsupportedMsg(String),
+
+    #[error("Authorization type {0} is not supported in amino JSON sign mode")]
+    UnsupportedAuthorization(String),
+
+    #[error("SIGN_MODE_LEGACY_AMINO_JSON does not support extension options")]
+    ExtensionOptions,
+
+    #[error("Invalid timestamp: {0}")]
+    InvalidTimestamp(String),
+
+    #[error("{0}")]
+    DecodeError(#[from] prost::DecodeError),
+
+    #[error("Invalid contract message: {0}")]
+    InvalidContractMsg(#[from] serde_json::Error),
+}
+
+/// Returns the amino JSON `StdSignDoc` for a transaction with the given fields.
+pub fn std_sign_doc(
+    chain_id: &str,
+    account_number: u64,
+    sequence: u64,
+    fee: &Fee,
+    memo: &str,
+    timeout_height: u64,
+    msgs: &[Any],
+) -> Result<Value, AminoError> {
+    let mut fee_json = Map::new();
+    fee_json.insert(
+        "amount".to_string(),
+        Value::Array(
+            fee.amount
+                .iter()
+                .map(|c| json!({ "amount": c.amount.to_string(), "denom": c.denom.to_string() }))
+                .collect(),
+        ),
+    );
+    fee_json.insert("gas".to_string(), fee.gas_limit.to_string().into());
+    if let Some(payer) = &fee.payer {
+        fee_json.insert("payer".to_string(), payer.to_string().into());
+    }
+    if let Some(granter) = &fee.granter {
+        fee_json.insert("granter".to_string(), granter.to_string().into());
+    }
+
+    let mut sign_doc = json!({
+        "account_number": account_number.to_string(),
+        "chain_id": chain_id,
+        "fee": fee_json,
+        "memo": memo,
+        "msgs": msgs.iter().map(msg_to_amino_json).collect::<Result<Vec<_>, _>>()?,
+        "sequence": sequence.to_string(),
+    });
+    if timeout_height != 0 {
+        sign_doc["timeout_height"] = timeout_height.to_string().into();
+    }
+
+    Ok(sign_doc)
+}
+
+/// Returns the bytes to sign for the amino JSON `sign_doc`, i.e. the compact JSON encoding with
+/// sorted keys and the same escaping of `&`, `<` and `>` as Go's `json.Marshal`.
+pub fn sign_bytes(sign_doc: &Value) -> Vec<u8> {
+    sort_json(sign_doc.clone())
+        .to_string()
+        .replace('&', "\\u0026")
+        .replace('<', "\\u003c")
+        .replace('>', "\\u003e")
+        .into_bytes()
+}
+
+/// Returns the value with the keys of all objects sorted. The order of `serde_json::Map`
+/// depends on the `preserve_order` feature, so we don't rely on it.
+fn sort_json(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries = map.into_iter().collect::<Vec<_>>();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| (k, sort_json(v)))
+                    .collect(),
+            )
+        }
+        Value::Array(values) => Value::Array(values.into_iter().map(sort_json).collect()),
+        value => value,
+    }
+}
+
+/// Returns the amino JSON encoding of `msg`. Supported messages are:
+/// - bank: `MsgSend` and `MsgMultiSend`
+/// - wasm: `MsgStoreCode`, `MsgInstantiateContract`, `MsgInstantiateContract2`,
+///   `MsgExecuteContract`, `MsgMigrateContract`, `MsgUpdateAdmin` and `MsgClearAdmin`
+/// - authz: `MsgExec`, `MsgGrant` with a `GenericAuthorization` and `MsgRevoke`
+pub fn msg_to_amino_json(msg: &Any) -> Result<Value, AminoError> {
+    let value = msg.value.as_slice();
+    let (amino_type, value) = match msg.type_url.as_str() {
+        "/cosmos.bank.v1beta1.MsgSend" => {
+            let msg = MsgSend::decode(value)?;
+            (
+                "cosmos-sdk/MsgSend",
+                fields(
+                    vec![
+                        ("from_address", msg.from_address.into()),
+                        ("to_address", msg.to_address.into()),
+                        ("amount", coins(&msg.amount)),
+                    ],
+                    &["amount"],
+                ),
+            )
+        }
+        "/cosmos.bank.v1beta1.MsgMultiSend" => {
+            let msg = MsgMultiSend::decode(value)?;
+            let io = |address: String, c: &[ProtoCoin]| {
+                fields(
+                    vec![("address", address.into()), ("coins", coins(c))],
+                    &["coins"],
+                )
+            };
+            (
+                "cosmos-sdk/MsgMultiSend",
+                fields(
+                    vec![
+                        (
+                            "inputs",
+                            msg.inputs
+                                .into_iter()
+                                .map(|i| io(i.address, &i.coins))
+                                .collect(),
+                        ),
+                        (
+                            "outputs",
+                            msg.outputs
+                                .into_iter()
+                                .map(|o| io(o.address, &o.coins))
+                                .collect(),
+                        ),
+                    ],
+                    &["inputs", "outputs"],
+                ),
+            )
+        }
+        "/cosmwasm.wasm.v1.MsgStoreCode" => {
+            let msg = MsgStoreCode::decode(value)?;
+            (
+                "wasm/MsgStoreCode",
+                fields(
+                    vec![
+                        ("sender", msg.sender.into()),
+                        ("wasm_byte_code", base64_json(&msg.wasm_byte_code)),
+                        (
+                            "instantiate_permission",
+                            msg.instantiate_permission
+                                .map(access_config)
+                                .unwrap_or(Value::Null),
+                        ),
+                    ],
+                    &[],
+                ),
+            )
+        }
+        "/cosmwasm.wasm.v1.MsgInstantiateContract" => {
+            let msg = MsgInstantiateContract::decode(value)?;
+            (
+                "wasm/MsgInstantiateContract",
+                fields(
+                    vec![
+                        ("sender", msg.sender.into()),
+                        ("admin", msg.admin.into()),
+                        ("code_id", uint(msg.code_id)),
+                        ("label", msg.label.into()),
+                        ("msg", contract_msg(&msg.msg)?),
+                        ("funds", coins(&msg.funds)),
+                    ],
+                    &["funds"],
+                ),
+            )
+        }
+        "/cosmwasm.wasm.v1.MsgInstantiateContract2" => {
+            let msg = MsgInstantiateContract2::decode(value)?;
+            (
+                "wasm/MsgInstantiateContract2",
+                fields(
+                    vec![
+                        ("sender", msg.sender.into()),
+                        ("admin", msg.admin.into()),
+                        ("code_id", uint(msg.code_id)),
+                        ("label", msg.label.into()),
+                        ("msg", contract_msg(&msg.msg)?),
+                        ("funds", coins(&msg.funds)),
+                        ("salt", base64_json(&msg.salt)),
+                        ("fix_msg", boolean(msg.fix_msg)),
+                    ],
+                    &["funds"],
+                ),
+            )
+        }
+        "/cosmwasm.wasm.v1.MsgExecuteContract" => {
+            let msg = MsgExecuteContract::decode(value)?;
+            (
+                "wasm/MsgExecuteContract",
+                fields(
+                    vec![
+                        ("sender", msg.sender.into()),
+                        ("contract", msg.contract.into()),
+                        ("msg", contract_msg(&msg.msg)?),
+                        ("funds", coins(&msg.funds)),
+                    ],
+                    &["funds"],
+                ),
+            )
+        }
+        "/cosmwasm.wasm.v1.MsgMigrateContract" => {
+            let msg = MsgMigrateContract::decode(value)?;
+            (
+                "wasm/MsgMigrateContract",
+                fields(
+                    vec![
+                        ("sender", msg.sender.into()),
+                        ("contract", msg.contract.into()),
+                        ("code_id", uint(msg.code_id)),
+                        ("msg", contract_msg(&msg.msg)?),
+                    ],
+                    &[],
+                ),
+            )
+        }
+        "/cosmwasm.wasm.v1.MsgUpdateAdmin" => {
+            let msg = MsgUpdateAdmin::decode(value)?;
+            (
+                "wasm/MsgUpdateAdmin",
+                fields(
+                    vec![
+                        ("sender", msg.sender.into()),
+                        ("new_admin", msg.new_admin.into()),
+                        ("contract", msg.contract.into()),
+                    ],
+                    &[],
+                ),
+            )
+        }
+        "/cosmwasm.wasm.v1.MsgClearAdmin" => {
+            let msg = MsgClearAdmin::decode(value)?;
+            (
+                "wasm/MsgClearAdmin",
+                fields(
+                    vec![
+                        ("sender", msg.sender.into()),
+                        ("contract", msg.contract.into()),
+                    ],
+                    &[],
+                ),
+            )
+        }
+        "/cosmos.authz.v1beta1.MsgExec" => {
+            let msg = MsgExec::decode(value)?;
+            (
+                "cosmos-sdk/MsgExec",
+                fields(
+                    vec![
+                        ("grantee", msg.grantee.into()),
+                        (
+                            "msgs",
+                            Value::Array(
+                                msg.msgs
+                                    .iter()
+                                    .map(msg_to_amino_json)
+                                    .collect::<Result<_, _>>()?,
+                            ),
+                        ),
+                    ],
+                    &[],
+                ),
+            )
+        }
+        "/cosmos.authz.v1beta1.MsgGrant" => {
+            let msg = MsgGrant::decode(value)?;
+            let grant = match msg.grant {
+                Some(grant) => {
+                    let authorization = match grant.authorization {
+                        Some(authorization) => generic_authorization(&authorization)?,
+                        None => Value::Null,
+                    };
+                    let expiration = match grant.expiration {
+                        Some(expiration) => {
+                            Time::try_from(cosmrs::proto::tendermint::google::protobuf::Timestamp {
+                                seconds: expiration.seconds,
+                                nanos: expiration.nanos,
+                            })
+                            .map_err(|e| AminoError::InvalidTimestamp(e.to_string()))?
+                            .to_rfc3339()
+                            .into()
+                        }
+                        None => Value::Null,
+                    };
+                    fields(
+                        vec![("authorization", authorization), ("expiration", expiration)],
+                        &[],
+                    )
+                }
+                None => Value::Null,
+            };
+            (
+                "cosmos-sdk/MsgGrant",
+                fields(
+                    vec![
+                        ("granter", msg.granter.into()),
+                        ("grantee", msg.grantee.into()),
+                        ("grant", grant),
+                    ],
+                    &["grant"],
+                ),
+            )
+        }
+        "/cosmos.authz.v1beta1.MsgRevoke" => {
+            let msg = MsgRevoke::decode(value)?;
+            (
+                "cosmos-sdk/MsgRevoke",
+                fields(
+                    vec![
+                        ("granter", msg.granter.into()),
+                        ("grantee", msg.grantee.into()),
+                        ("msg_type_url", msg.msg_type_url.into()),
+                    ],
+                    &[],
+                ),
+            )
+        }
+        type_url => return Err(AminoError::UnsupportedMsg(type_url.to_string())),
+    };
+
+    Ok(json!({ "type": amino_type, "value": value }))
+}
+
+/// Builds a JSON object from `fields`, omitting empty values like amino JSON does for fields
+/// with `omitempty`. Numeric and bool fields are built with [`uint`] and [`boolean`], which
+/// return null for zero and false, so they are omitted too. Fields listed in `dont_omit` are
+/// always included.
+fn fields(fields: Vec<(&str, Value)>, dont_omit: &[&str]) -> Value {
+    Value::Object(
+        fields
+            .into_iter()
+            .filter(|(key, value)| dont_omit.contains(key) || !is_empty(value))
+            .map(|(key, value)| (key.to_string(), value))
+            .collect(),
+    )
+}
+
+fn is_empty(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::String(s) => s.is_empty(),
+        Value::Array(values) => values.is_empty(),
+        Value::Object(_) | Value::Number(_) | Value::Bool(_) => false,
+    }
+}
+
+/// Returns the amino JSON of a `uint64` field, which is a string, or null if it is zero.
+fn uint(value: u64) -> Value {
+    if value == 0 {
+        Value::Null
+    } else {
+        value.to_string().into()
+    }
+}
+
+/// Returns the amino JSON of a `bool` field, or null if it is false.
+fn boolean(value: bool) -> Value {
+    if value {
+        true.into()
+    } else {
+        Value::Null
+    }
+}
+
+fn coins(coins: &[ProtoCoin]) -> Value {
+    Value::Array(
+        coins
+            .iter()
+            .map(|c| json!({ "amount": c.amount, "denom": c.denom }))
+            .collect(),
+    )
+}
+
+fn base64_json(bytes: &[u8]) -> Value {
+    base64::engine::general_purpose::STANDARD
+        .encode(bytes)
+        .into()
+}
+
+/// Contract messages are embedded as JSON, not as base64 encoded bytes.
+fn contract_msg(msg: &[u8]) -> Result<Value, AminoError> {
+    Ok(serde_json::from_slice(msg)?)
+}
+
+fn access_config(config: AccessConfig) -> Value {
+    // wasmd encodes the access type with its short name in amino JSON
+    let permission = match config.permission {
+        1 => "Nobody",
+        2 => "OnlyAddress",
+        3 => "Everybody",
+        4 => "AnyOfAddresses",
+        _ => "Unspecified",
+    };
+    fields(
+        vec![
+            ("permission", permission.into()),
+            ("address", config.address.into()),
+            (
+                "addresses",
+                config.addresses.into_iter().map(Value::from).collect(),
+            ),
+        ],
+        &[],
+    )
+}
+
+fn generic_authorization(authorization: &Any) -> Result<Value, AminoError> {
+    if authorization.type_url != "/cosmos.authz.v1beta1.GenericAuthorization" {
+        return Err(AminoError::UnsupportedAuthorization(
+            authorization.type_url.clone(),
+        ));
+    }
+    let authorization = GenericAuthorization::decode(authorization.value.as_slice())?;
+
+    Ok(json!({
+        "type": "cosmos-sdk/GenericAuthorization",
+        "value": fields(vec![("msg", authorization.msg.into())], &[]),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmrs::proto::cosmos::authz::v1beta1::Grant;
+    use prost::Message;
+
+    use super::*;
+
+    const SENDER: &str = "osmo12smx2wdlyttvyzvzg54y2vnqwq2qjateuf7thj";
+    const CONTRACT: &str = "osmo1cyyzpxplxdzkeea7kwsydadg87357qnahakaks";
+
+    fn any<M: Message>(type_url: &str, msg: M) -> Any {
+        Any {
+            type_url: type_url.to_string(),
+            value: msg.encode_to_vec(),
+        }
+    }
+
+    fn coin(amount: &str, denom: &str) -> ProtoCoin {
+        ProtoCoin {
+            denom: denom.to_string(),
+            amount: amount.to_string(),
+        }
+    }
+
+    #[test]
+    fn bank_msg_send() {
+        let msg = any(
+            "/cosmos.bank.v1beta1.MsgSend",
+            MsgSend {
+                from_address: SENDER.to_string(),
+                to_address: CONTRACT.to_string(),
+                amount: vec![coin("100", "uosmo")],
+            },
+        );
+
+        assert_eq!(
+            msg_to_amino_json(&msg).unwrap(),
+            json!({
+                "type": "cosmos-sdk/MsgSend",
+                "value": {
+                    "from_address": SENDER,
+                    "to_address": CONTRACT,
+                    "amount": [{ "amount": "100", "denom": "uosmo" }],
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn wasm_instantiate_and_execute() {
+        let instantiate = any(
+            "/cosmwasm.wasm.v1.MsgInstantiateContract",
+            MsgInstantiateContract {
+                sender: SENDER.to_string(),
+                admin: "".to_string(),
+                code_id: 1,
+                label: "counter".to_string(),
+                msg: br#"{"count":0}"#.to_vec(),
+                funds: vec![],
+            },
+        );
+        assert_eq!(
+            msg_to_amino_json(&instantiate).unwrap(),
+            json!({
+                "type": "wasm/MsgInstantiateContract",
+                "value": {
+                    "sender": SENDER,
+                    "code_id": "1",
+                    "label": "counter",
+                    "msg": { "count": 0 },
+                    "funds": [],
+                }
+            })
+        );
+
+        let execute = any(
+            "/cosmwasm.wasm.v1.MsgExecuteContract",
+            MsgExecuteContract {
+                sender: SENDER.to_string(),
+                contract: CONTRACT.to_string(),
+                msg: br#"{"increment":{}}"#.to_vec(),
+                funds: vec![coin("5", "uosmo")],
+            },
+        );
+        assert_eq!(
+            msg_to_amino_json(&execute).unwrap(),
+            json!({
+                "type": "wasm/MsgExecuteContract",
+                "value": {
+                    "sender": SENDER,
+                    "contract": CONTRACT,
+                    "msg": { "increment": {} },
+                    "funds": [{ "amount": "5", "denom": "uosmo" }],
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn zero_and_false_omitted_only_for_numbers_and_bools() {
+        let instantiate = |code_id, fix_msg| {
+            any(
+                "/cosmwasm.wasm.v1.MsgInstantiateContract2",
+                MsgInstantiateContract2 {
+                    sender: SENDER.to_string(),
+                    admin: "".to_string(),
+                    code_id,
+                    label: "0".to_string(),
+                    msg: b"false".to_vec(),
+                    funds: vec![],
+                    salt: vec![],
+                    fix_msg,
+                },
+            )
+        };
+
+        assert_eq!(
+            msg_to_amino_json(&instantiate(0, false)).unwrap(),
+            json!({
+                "type": "wasm/MsgInstantiateContract2",
+                "value": {
+                    "sender": SENDER,
+                    "label": "0",
+                    "msg": false,
+                    "funds": [],
+                }
+            })
+        );
+        assert_eq!(
+            msg_to_amino_json(&instantiate(3, true)).unwrap()["value"],
+            json!({
+                "sender": SENDER,
+                "code_id": "3",
+                "label": "0",
+                "msg": false,
+                "funds": [],
+                "fix_msg": true,
+            })
+        );
+    }
+
+    #[test]
+    fn authz_exec_and_grant() {
+        let exec = any(
+            "/cosmos.authz.v1beta1.MsgExec",
+            MsgExec {
+                grantee: CONTRACT.to_string(),
+                msgs: vec![any(
+                    "/cosmwasm.wasm.v1.MsgClearAdmin",
+                    MsgClearAdmin {
+                        sender: SENDER.to_string(),
+                        contract: CONTRACT.to_string(),
+                    },
+                )],
+            },
+        );
+        assert_eq!(
+            msg_to_amino_json(&exec).unwrap(),
+            json!({
+                "type": "cosmos-sdk/MsgExec",
+                "value": {
+                    "grantee": CONTRACT,
+                    "msgs": [{
+                        "type": "wasm/MsgClearAdmin",
+                        "value": { "sender": SENDER, "contract": CONTRACT },
+                    }],
+                }
+            })
+        );
+
+        let grant = any(
+            "/cosmos.authz.v1beta1.MsgGrant",
+            MsgGrant {
+                granter: SENDER.to_string(),
+                grantee: CONTRACT.to_string(),
+                grant: Some(Grant {
+                    authorization: Some(any(
+                        "/cosmos.authz.v1beta1.GenericAuthorization",
+                        GenericAuthorization {
+                            msg: "/cosmwasm.wasm.v1.MsgExecuteContract".to_string(),
+                        },
+                    )),
+                    expiration: Some(prost_types::Timestamp {
+                        seconds: 1_700_000_000,
+                        nanos: 0,
+                    }),
+                }),
+            },
+        );
+        assert_eq!(
+            msg_to_amino_json(&grant).unwrap(),
+            json!({
+                "type": "cosmos-sdk/MsgGrant",
+                "value": {
+                    "granter": SENDER,
+                    "grantee": CONTRACT,
+                    "grant": {
+                        "authorization": {
+                            "type": "cosmos-sdk/GenericAuthorization",
+                            "value": { "msg": "/cosmwasm.wasm.v1.MsgExecuteContract" },
+                        },
+                        "expiration": "2023-11-14T22:13:20Z",
+                    },
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn unsupported_msg() {
+        let msg = Any {
+            type_url: "/osmosis.gamm.v1beta1.MsgSwapExactAmountIn".to_string(),
+            value: vec![],
+        };
+        assert!(matches!(
+            msg_to_amino_json(&msg).unwrap_err(),
+            AminoError::UnsupportedMsg(type_url) if type_url == msg.type_url
+        ));
+    }
+
+    #[test]
+    fn sign_bytes_sorted_and_escaped() {
+        let fee = Fee::from_amount_and_gas(
+            cosmrs::Coin {
+                denom: "uosmo".parse().unwrap(),
+                amount: 1000,
+            },
+            200_000u64,
+        );
+        let msgs = vec![any(
+            "/cosmos.bank.v1beta1.MsgSend",
+            MsgSend {
+                from_address: SENDER.to_string(),
+                to_address: CONTRACT.to_string(),
+                amount: vec![coin("100", "uosmo")],
+            },
+        )];
+
+        let sign_doc = std_sign_doc("localosmosis", 1, 7, &fee, "<memo>", 0, &msgs).unwrap();
+
+        assert_eq!(
+            String::from_utf8(sign_bytes(&sign_doc)).unwrap(),
+            format!(
+                concat!(
+                    r#"{{"account_number":"1","chain_id":"localosmosis","#,
+                    r#""fee":{{"amount":[{{"amount":"1000","denom":"uosmo"}}],"gas":"200000"}},"#,
+                    r#""memo":"\u003cmemo\u003e","#,
+                    r#""msgs":[{{"type":"cosmos-sdk/MsgSend","value":{{"amount":[{{"amount":"100","denom":"uosmo"}}],"from_address":"{}","to_address":"{}"}}}}],"#,
+                    r#""sequence":"7"}}"#
+                ),
+                SENDER, CONTRACT
+            )
+        );
+    }
+}
@@ -1,29 +1,120 @@
+use std::future::Future;
+use std::sync::Mutex;
 use std::time::Duration;
 
-use cosmrs::rpc::{Client, HttpClient};
-use cosmwasm_std::{Coin, Uint128};
+use cosmrs::proto::cosmos::base::abci::v1beta1::GasInfo;
+use cosmrs::proto::cosmos::tx::v1beta1::{SimulateRequest, SimulateResponse};
+use cosmrs::rpc::event::{Event as RpcEvent, EventData};
+use cosmrs::rpc::query::Query;
+use cosmrs::rpc::{Client, HttpClient, Order, SubscriptionClient, WebSocketClient};
+use cosmrs::tendermint::Hash;
+use cosmwasm_std::{Coin, Decimal, Uint128};
+use futures::StreamExt;
+use osmosis_std::types::osmosis::txfees::v1beta1::{
+    QueryEipBaseFeeRequest, QueryEipBaseFeeResponse,
+};
+use prost::Message;
+use sha2::{Digest, Sha256};
 // use futures_time::{task::sleep, time::Duration};
 use serde::Deserialize;
 use thiserror::Error;
 
-use cosmrs::rpc::error::Error as RpcError;
-
-use config::Config;
+use cosmrs::rpc::error::{Error as RpcError, ErrorDetail};
 
 use crate::helpers::block_on;
 
-use super::config::FeeSetting;
+use super::config::{from_yaml_with_env, FeeSetting};
+use super::gas_price::{self, GasPriceSource};
+use super::transport::{Transport, TxResult};
+
+#[cfg(feature = "grpc")]
+use {
+    super::transport::GrpcClient,
+    cosmrs::proto::cosmos::tx::v1beta1::{
+        BroadcastMode, BroadcastTxRequest, BroadcastTxResponse, GetTxRequest, GetTxResponse,
+    },
+};
 
 #[derive(Debug, Error)]
 pub enum ChainError {
     #[error("{0}")]
     RpcError(#[from] RpcError),
+
+    #[error("{0}")]
+    DecodeError(#[from] prost::DecodeError),
+
+    /// Boxed because `tonic::Status` is large compared to the other variants.
+    #[cfg(feature = "grpc")]
+    #[error("{0}")]
+    GrpcStatus(Box<tonic::Status>),
+
+    #[cfg(feature = "grpc")]
+    #[error("{0}")]
+    GrpcTransport(#[from] tonic::transport::Error),
+
+    #[error("The grpc transport requires the `grpc` feature to be enabled")]
+    GrpcNotEnabled,
+
+    #[error("ABCI error (codespace: {codespace}, code: {code}): {log}")]
+    Abci {
+        code: u32,
+        codespace: String,
+        log: String,
+    },
+
+    #[error("Timed out: {0}")]
+    Timeout(String),
+
+    #[error("Invalid chain registry entry: {0}")]
+    ChainRegistry(String),
+
+    #[error("Failed to get gas price: {0}")]
+    GasPrice(String),
+
+    #[error("{0}")]
+    Generic(String),
+}
+
+#[cfg(feature = "grpc")]
+impl From<tonic::Status> for ChainError {
+    fn from(status: tonic::Status) -> Self {
+        ChainError::GrpcStatus(Box::new(status))
+    }
+}
+
+impl ChainError {
+    /// Returns true if the error is caused by a connection problem or a timeout, i.e. if the
+    /// request may succeed when it is retried.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ChainError::RpcError(e) => matches!(
+                e.detail(),
+                ErrorDetail::Io(_)
+                    | ErrorDetail::Http(_)
+                    | ErrorDetail::HttpRequestFailed(_)
+                    | ErrorDetail::Timeout(_)
+            ),
+            #[cfg(feature = "grpc")]
+            ChainError::GrpcStatus(status) => matches!(
+                status.code(),
+                tonic::Code::Unavailable | tonic::Code::DeadlineExceeded
+            ),
+            #[cfg(feature = "grpc")]
+            ChainError::GrpcTransport(_) => true,
+            ChainError::Timeout(_) => true,
+            _ => false,
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct Chain {
     http_client: HttpClient,
+    #[cfg(feature = "grpc")]
+    grpc_client: Option<GrpcClient>,
     chain_cfg: ChainConfig,
+    /// The last gas price queried from the chain and the height of the block it was queried at.
+    gas_price_cache: Mutex<Option<(u64, Decimal)>>,
 }
 
 #[allow(clippy::module_name_repetitions)]
@@ -33,20 +124,86 @@ pub struct ChainConfig {
     pub denom: String,
     pub prefix: String,
     pub chain_id: String,
-    pub gas_price: u64,
+    /// The gas price in the fee denom, e.g. `0.025`.
+    #[serde(deserialize_with = "gas_price::deserialize_decimal")]
+    pub gas_price: Decimal,
+    /// Where to get the gas price of transactions from. Defaults to the fixed `gas_price`.
+    #[serde(default)]
+    pub gas_price_source: GasPriceSource,
     pub gas_adjustment: f64,
     pub derivation_path: String,
     pub rpc_endpoint: String,
     pub grpc_endpoint: String,
+    /// The Tendermint RPC websocket endpoint, used to subscribe to events. Defaults to the
+    /// `/websocket` path of `rpc_endpoint` with the `ws` or `wss` scheme.
+    #[serde(default)]
+    pub websocket_endpoint: Option<String>,
+    /// The transport to use for queries and transactions. Defaults to `rpc`.
+    #[serde(default)]
+    pub transport: Transport,
+    /// Timeouts and retries for requests to the chain.
+    #[serde(default)]
+    pub timeouts: TimeoutConfig,
+    /// Balance of accounts created with `init_default_account` and `init_default_accounts`.
+    /// Defaults to [`initial_coins`](crate::traits::initial_coins), which only exist on local
+    /// test chains.
+    #[serde(default)]
+    pub default_funding: Vec<Coin>,
+}
+
+/// Timeouts and retries for requests made by [`Chain`]. All fields have defaults and can be
+/// omitted from the config file.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct TimeoutConfig {
+    /// Timeout of a single query or simulation request, in seconds.
+    pub request_timeout_secs: u64,
+    /// Number of times a query or simulation is retried after a connection error or timeout.
+    /// Broadcasting a transaction is never retried.
+    pub max_retries: u32,
+    /// Delay between retries and between polls for new blocks or transactions, in milliseconds.
+    pub poll_interval_ms: u64,
+    /// How long to wait for a broadcast transaction to be included in a block, in seconds.
+    pub tx_timeout_secs: u64,
+    /// How long to wait for the next block when waiting for blocks, in seconds.
+    pub block_timeout_secs: u64,
+}
+
+impl Default for TimeoutConfig {
+    fn default() -> Self {
+        Self {
+            request_timeout_secs: 30,
+            max_retries: 3,
+            poll_interval_ms: 500,
+            tx_timeout_secs: 60,
+            block_timeout_secs: 30,
+        }
+    }
+}
+
+impl TimeoutConfig {
+    pub fn request_timeout(&self) -> Duration {
+        Duration::from_secs(self.request_timeout_secs)
+    }
+
+    pub fn poll_interval(&self) -> Duration {
+        Duration::from_millis(self.poll_interval_ms)
+    }
+
+    pub fn tx_timeout(&self) -> Duration {
+        Duration::from_secs(self.tx_timeout_secs)
+    }
+
+    pub fn block_timeout(&self) -> Duration {
+        Duration::from_secs(self.block_timeout_secs)
+    }
 }
 
 impl ChainConfig {
-    pub fn from_yaml(file: &str) -> Self {
-        let settings = Config::builder()
-            .add_source(config::File::with_name(file))
-            .build()
-            .unwrap();
-        settings.try_deserialize::<Self>().unwrap()
+    /// Reads the chain config from the yaml file at `file`. Values can be overridden with the
+    /// environment variables listed in [`CHAIN_CONFIG_ENV_OVERRIDES`](super::config::CHAIN_CONFIG_ENV_OVERRIDES).
+    pub fn from_yaml(file: &str) -> Result<Self, config::ConfigError> {
+        from_yaml_with_env(file, "", |var| std::env::var(var).ok())
     }
 
     pub fn denom(&self) -> &str {
@@ -57,11 +214,30 @@ impl ChainConfig {
         &self.prefix
     }
 
+    /// Returns the configured websocket endpoint, or the one derived from `rpc_endpoint`.
+    pub fn websocket_endpoint(&self) -> String {
+        if let Some(endpoint) = &self.websocket_endpoint {
+            return endpoint.clone();
+        }
+
+        let rpc_endpoint = self.rpc_endpoint.trim_end_matches('/');
+        let endpoint = if let Some(rest) = rpc_endpoint.strip_prefix("https://") {
+            format!("wss://{}", rest)
+        } else if let Some(rest) = rpc_endpoint.strip_prefix("http://") {
+            format!("ws://{}", rest)
+        } else {
+            rpc_endpoint.to_string()
+        };
+        format!("{}/websocket", endpoint)
+    }
+
+    /// Returns the fee setting with the chain's gas price. The gas price of a fee setting is a
+    /// coin, so it is rounded up to a whole amount.
     pub fn auto_fee_setting(&self) -> FeeSetting {
         FeeSetting::Auto {
             gas_price: Coin {
                 denom: self.denom.clone(),
-                amount: Uint128::new(self.gas_price as u128),
+                amount: Uint128::one().mul_ceil(self.gas_price),
             },
             gas_adjustment: self.gas_adjustment,
         }
@@ -76,9 +252,22 @@ impl Chain {
         //let rpc_endpoint="http://localhost:26657".to_string();
         let http_client = HttpClient::new(chain_cfg.rpc_endpoint.as_str())?;
 
+        #[cfg(feature = "grpc")]
+        let grpc_client = match chain_cfg.transport {
+            Transport::Grpc => Some(GrpcClient::new(&chain_cfg.grpc_endpoint)?),
+            Transport::Rpc => None,
+        };
+        #[cfg(not(feature = "grpc"))]
+        if chain_cfg.transport == Transport::Grpc {
+            return Err(ChainError::GrpcNotEnabled);
+        }
+
         Ok(Self {
             http_client,
+            #[cfg(feature = "grpc")]
+            grpc_client,
             chain_cfg,
+            gas_price_cache: Mutex::new(None),
         })
     }
 
@@ -90,54 +279,744 @@ impl Chain {
         &self.chain_cfg
     }
 
-    pub fn current_height(&self) -> Result<u64, RpcError> {
-        block_on(self.http_client.latest_block()).map(|res| res.block.header.height.into())
+    #[cfg(feature = "grpc")]
+    fn grpc_client(&self) -> Result<&GrpcClient, ChainError> {
+        self.grpc_client
+            .as_ref()
+            .ok_or_else(|| ChainError::Generic("gRPC client not initialized".to_string()))
     }
 
-    pub fn wait(&self, n_block: u64) -> Result<(), RpcError> {
-        block_on(self.poll_for_n_blocks(n_block, false))
+    /// Sends the encoded query `data` to the gRPC query method at `path` using the configured
+    /// transport and returns the encoded response.
+    /// Queries and simulations are retried according to the configured [`TimeoutConfig`].
+    pub async fn query_raw(&self, path: &str, data: Vec<u8>) -> Result<Vec<u8>, ChainError> {
+        self.with_retries(|| self.query_raw_once(path, data.clone()))
+            .await
     }
 
-    pub async fn poll_for_n_blocks(&self, n: u64, is_first_block: bool) -> Result<(), RpcError> {
-        if is_first_block {
-            self.client()
-                .wait_until_healthy(Duration::from_secs(5))
+    async fn query_raw_once(&self, path: &str, data: Vec<u8>) -> Result<Vec<u8>, ChainError> {
+        match self.chain_cfg.transport {
+            Transport::Rpc => {
+                let res = self
+                    .http_client
+                    .abci_query(Some(path.to_string()), data, None, false)
+                    .await?;
+                if res.code.is_err() {
+                    return Err(ChainError::Abci {
+                        code: res.code.value(),
+                        codespace: res.codespace,
+                        log: res.log,
+                    });
+                }
+                Ok(res.value)
+            }
+            #[cfg(feature = "grpc")]
+            Transport::Grpc => Ok(self.grpc_client()?.unary_raw(path, data).await?),
+            #[cfg(not(feature = "grpc"))]
+            Transport::Grpc => Err(ChainError::GrpcNotEnabled),
+        }
+    }
+
+    /// Runs the request created by `f` with the configured request timeout, retrying it after
+    /// errors for which [`ChainError::is_retryable`] returns true.
+    async fn with_retries<T, F, Fut>(&self, mut f: F) -> Result<T, ChainError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, ChainError>>,
+    {
+        let timeouts = &self.chain_cfg.timeouts;
+        let mut attempt = 0;
+        loop {
+            let res = tokio::time::timeout(timeouts.request_timeout(), f())
                 .await
-                .unwrap();
+                .unwrap_or_else(|_| {
+                    Err(ChainError::Timeout(format!(
+                        "no response after {}s",
+                        timeouts.request_timeout_secs
+                    )))
+                });
 
-            while let Err(e) = self.client().latest_block().await {
-                if !matches!(e.detail(), cosmrs::rpc::error::ErrorDetail::Serde(_)) {
-                    return Err(e);
+            match res {
+                Err(e) if e.is_retryable() && attempt < timeouts.max_retries => {
+                    attempt += 1;
+                    tokio::time::sleep(timeouts.poll_interval()).await;
                 }
-                tokio::time::sleep(Duration::from_millis(500)).await;
+                res => return res,
             }
         }
+    }
+
+    /// Simulates the signed transaction `tx_bytes` and returns the gas info.
+    pub async fn simulate_tx(&self, tx_bytes: Vec<u8>) -> Result<GasInfo, ChainError> {
+        #[allow(deprecated)]
+        let req = SimulateRequest { tx: None, tx_bytes };
+        let res = SimulateResponse::decode(
+            self.query_raw("/cosmos.tx.v1beta1.Service/Simulate", req.encode_to_vec())
+                .await?
+                .as_slice(),
+        )?;
+
+        res.gas_info
+            .ok_or_else(|| ChainError::Generic("simulation returned no gas info".to_string()))
+    }
 
-        let mut curr_height: u64 = self
-            .client()
-            .latest_block()
+    /// Broadcasts the signed transaction `tx_bytes` and waits for it to be included in a block.
+    /// Returns [`ChainError::Timeout`] if this takes longer than the configured `tx_timeout_secs`.
+    pub async fn broadcast_tx_commit(&self, tx_bytes: Vec<u8>) -> Result<TxResult, ChainError> {
+        let tx_timeout = self.chain_cfg.timeouts.tx_timeout();
+        tokio::time::timeout(tx_timeout, self.broadcast_tx_commit_inner(tx_bytes))
             .await
-            .unwrap()
-            .block
-            .header
-            .height
-            .into();
-        let target_height: u64 = curr_height + n;
+            .unwrap_or_else(|_| {
+                Err(ChainError::Timeout(format!(
+                    "transaction was not included in a block after {}s",
+                    tx_timeout.as_secs()
+                )))
+            })
+    }
 
-        while curr_height < target_height {
-            tokio::time::sleep(Duration::from_millis(500)).await;
+    async fn broadcast_tx_commit_inner(&self, tx_bytes: Vec<u8>) -> Result<TxResult, ChainError> {
+        match self.chain_cfg.transport {
+            Transport::Rpc => Ok(self.http_client.broadcast_tx_commit(tx_bytes).await?.into()),
+            #[cfg(feature = "grpc")]
+            Transport::Grpc => {
+                let req = BroadcastTxRequest {
+                    tx_bytes,
+                    mode: BroadcastMode::Sync as i32,
+                };
+                let tx_response = BroadcastTxResponse::decode(
+                    self.grpc_client()?
+                        .unary_raw(
+                            "/cosmos.tx.v1beta1.Service/BroadcastTx",
+                            req.encode_to_vec(),
+                        )
+                        .await?
+                        .as_slice(),
+                )?
+                .tx_response
+                .ok_or_else(|| ChainError::Generic("empty BroadcastTx response".to_string()))?;
+
+                // A non-zero code means CheckTx failed and the tx will not be included in a block
+                if tx_response.code != 0 {
+                    return tx_response.try_into();
+                }
+
+                self.wait_for_tx_grpc(&tx_response.txhash).await
+            }
+            #[cfg(not(feature = "grpc"))]
+            Transport::Grpc => Err(ChainError::GrpcNotEnabled),
+        }
+    }
+
+    /// Polls the `GetTx` gRPC method until the transaction with the given hash is found. The
+    /// caller is responsible for timing out.
+    #[cfg(feature = "grpc")]
+    async fn wait_for_tx_grpc(&self, hash: &str) -> Result<TxResult, ChainError> {
+        let req = GetTxRequest {
+            hash: hash.to_string(),
+        };
+        loop {
+            match self
+                .grpc_client()?
+                .unary_raw("/cosmos.tx.v1beta1.Service/GetTx", req.encode_to_vec())
+                .await
+            {
+                Ok(res) => {
+                    return GetTxResponse::decode(res.as_slice())?
+                        .tx_response
+                        .ok_or_else(|| ChainError::Generic("empty GetTx response".to_string()))?
+                        .try_into()
+                }
+                Err(status) if status.code() == tonic::Code::NotFound => {
+                    tokio::time::sleep(self.chain_cfg.timeouts.poll_interval()).await;
+                }
+                Err(status) => return Err(status.into()),
+            }
+        }
+    }
+
+    /// Returns the result of the transaction with the given hex encoded hash.
+    pub async fn tx(&self, hash: &str) -> Result<TxResult, ChainError> {
+        let hash = hash
+            .to_uppercase()
+            .parse::<Hash>()
+            .map_err(|e| ChainError::Generic(format!("invalid tx hash {}: {}", hash, e)))?;
+
+        self.with_retries(|| async { Ok(self.http_client.tx(hash, false).await?.into()) })
+            .await
+    }
+
+    /// Returns the results of all transactions matching the Tendermint event `query`, e.g.
+    /// `wasm._contract_address='osmo1...' AND wasm.action='increment'`, ordered by height.
+    pub async fn search_txs(&self, query: &str) -> Result<Vec<TxResult>, ChainError> {
+        const PER_PAGE: u8 = 100;
+
+        let query = query.parse::<Query>()?;
+        let mut txs = vec![];
+        for page in 1.. {
+            let res = self
+                .with_retries(|| async {
+                    Ok(self
+                        .http_client
+                        .tx_search(query.clone(), false, page, PER_PAGE, Order::Ascending)
+                        .await?)
+                })
+                .await?;
+
+            let page_len = res.txs.len();
+            txs.extend(res.txs.into_iter().map(TxResult::from));
+            if page_len < PER_PAGE as usize || txs.len() >= res.total_count as usize {
+                break;
+            }
+        }
+
+        Ok(txs)
+    }
+
+    /// Subscribes to the Tendermint event `query` via the websocket endpoint and returns the
+    /// first matching event. Returns [`ChainError::Timeout`] if no event matches within
+    /// `timeout`. Only events that happen after the subscription are returned.
+    pub async fn wait_for_event(
+        &self,
+        query: &str,
+        timeout: Duration,
+    ) -> Result<RpcEvent, ChainError> {
+        let query = query.parse::<Query>()?;
+        let (client, driver) =
+            WebSocketClient::new(self.chain_cfg.websocket_endpoint().as_str()).await?;
+        let driver_handle = tokio::spawn(driver.run());
+
+        let res = tokio::time::timeout(timeout, async {
+            let mut subscription = client.subscribe(query.clone()).await?;
+            subscription
+                .next()
+                .await
+                .ok_or_else(|| {
+                    ChainError::Generic(format!("subscription to `{}` was closed", query))
+                })?
+                .map_err(ChainError::from)
+        })
+        .await
+        .unwrap_or_else(|_| {
+            Err(ChainError::Timeout(format!(
+                "no event matching `{}` after {}s",
+                query,
+                timeout.as_secs()
+            )))
+        });
+
+        client.close()?;
+        let _ = driver_handle.await;
+
+        res
+    }
+
+    /// Waits for a transaction matching the Tendermint event `query`, e.g.
+    /// `wasm._contract_address='osmo1...' AND wasm.action='increment'`, and returns its result.
+    /// Only transactions that are included in a block after this is called are returned.
+    pub async fn wait_for_tx(
+        &self,
+        query: &str,
+        timeout: Duration,
+    ) -> Result<TxResult, ChainError> {
+        let event = self.wait_for_event(&tx_query(query), timeout).await?;
+        let tx_info = match event.data {
+            EventData::Tx { tx_result } => tx_result,
+            data => {
+                return Err(ChainError::Generic(format!(
+                    "expected a Tx event, got {:?}",
+                    data
+                )))
+            }
+        };
+        let hash = hex_upper(&Sha256::digest(&tx_info.tx));
+        let index = tx_info
+            .index
+            .ok_or_else(|| ChainError::Generic(format!("Tx event of {} without index", hash)))?;
+
+        // The event is missing the code, codespace and data, so get them from the block results.
+        // Unlike `tx`, these do not depend on the node indexing transactions.
+        let height = u32::try_from(tx_info.height)
+            .map_err(|e| ChainError::Generic(format!("invalid height of tx {}: {}", hash, e)))?;
+        let block_result = self
+            .with_retries(|| async { Ok(self.http_client.block_results(height).await?) })
+            .await?
+            .txs_results
+            .and_then(|results| results.into_iter().nth(index as usize))
+            .ok_or_else(|| {
+                ChainError::Generic(format!("no result of tx {} in block {}", hash, height))
+            })?;
+
+        Ok(TxResult::from_tx_event(hash, tx_info, block_result))
+    }
+
+    /// Returns the gas price to use for transactions in the chain's fee denom. Prices queried
+    /// from the chain are cached until the next block.
+    pub async fn gas_price(&self) -> Result<Decimal, ChainError> {
+        let source = self.chain_cfg.gas_price_source;
+        if !source.is_dynamic() {
+            return Ok(self.chain_cfg.gas_price);
+        }
+
+        let height = self.latest_height().await?;
+        if let Some((cached_height, price)) = *self.gas_price_cache.lock().unwrap() {
+            if cached_height == height {
+                return Ok(price);
+            }
+        }
+
+        let price = match source {
+            GasPriceSource::Fixed => unreachable!(),
+            GasPriceSource::OsmosisTxFees => {
+                let res = self
+                    .query_raw(
+                        gas_price::OSMOSIS_EIP_BASE_FEE_PATH,
+                        QueryEipBaseFeeRequest {}.encode_to_vec(),
+                    )
+                    .await?;
+                gas_price::parse_legacy_dec(
+                    &QueryEipBaseFeeResponse::decode(res.as_slice())?.base_fee,
+                )?
+            }
+            GasPriceSource::FeeMarket => {
+                let req = gas_price::GasPriceRequest {
+                    denom: self.chain_cfg.denom.clone(),
+                };
+                let res = self
+                    .query_raw(gas_price::FEE_MARKET_GAS_PRICE_PATH, req.encode_to_vec())
+                    .await?;
+                let price = gas_price::GasPriceResponse::decode(res.as_slice())?
+                    .price
+                    .ok_or_else(|| ChainError::GasPrice("empty feemarket response".to_string()))?;
+                gas_price::parse_legacy_dec(&price.amount)?
+            }
+            GasPriceSource::NodeMinGasPrices => {
+                let res = self
+                    .query_raw(
+                        gas_price::NODE_CONFIG_PATH,
+                        gas_price::ConfigRequest {}.encode_to_vec(),
+                    )
+                    .await?;
+                let config = gas_price::ConfigResponse::decode(res.as_slice())?;
+                gas_price::parse_min_gas_price(&config.minimum_gas_price, &self.chain_cfg.denom)?
+            }
+        };
+
+        *self.gas_price_cache.lock().unwrap() = Some((height, price));
+        Ok(price)
+    }
 
-            curr_height = self
+    pub fn current_height(&self) -> Result<u64, ChainError> {
+        block_on(self.latest_height())
+    }
+
+    pub fn wait(&self, n_block: u64) -> Result<(), ChainError> {
+        block_on(self.poll_for_n_blocks(n_block, false))
+    }
+
+    async fn latest_height(&self) -> Result<u64, ChainError> {
+        self.with_retries(|| async {
+            Ok(self
                 .client()
                 .latest_block()
-                .await
-                .unwrap()
+                .await?
                 .block
                 .header
                 .height
-                .into();
+                .into())
+        })
+        .await
+    }
+
+    /// Waits until `n` new blocks have been produced. If `is_first_block` is true, first waits
+    /// for the node to become healthy and produce its first block. Returns
+    /// [`ChainError::Timeout`] if no new block is produced within the configured
+    /// `block_timeout_secs`.
+    pub async fn poll_for_n_blocks(&self, n: u64, is_first_block: bool) -> Result<(), ChainError> {
+        let timeouts = &self.chain_cfg.timeouts;
+
+        if is_first_block {
+            self.client()
+                .wait_until_healthy(timeouts.block_timeout())
+                .await?;
+
+            let started = tokio::time::Instant::now();
+            while let Err(e) = self.client().latest_block().await {
+                if !matches!(e.detail(), ErrorDetail::Serde(_)) {
+                    return Err(e.into());
+                }
+                if started.elapsed() > timeouts.block_timeout() {
+                    return Err(ChainError::Timeout(format!(
+                        "no first block after {}s",
+                        timeouts.block_timeout_secs
+                    )));
+                }
+                tokio::time::sleep(timeouts.poll_interval()).await;
+            }
+        }
+
+        let mut curr_height = self.latest_height().await?;
+        let target_height = curr_height + n;
+        let mut last_new_block = tokio::time::Instant::now();
+
+        while curr_height < target_height {
+            tokio::time::sleep(timeouts.poll_interval()).await;
+
+            let height = self.latest_height().await?;
+            if height > curr_height {
+                curr_height = height;
+                last_new_block = tokio::time::Instant::now();
+            } else if last_new_block.elapsed() > timeouts.block_timeout() {
+                return Err(ChainError::Timeout(format!(
+                    "no new block after height {} for {}s",
+                    curr_height, timeouts.block_timeout_secs
+                )));
+            }
         }
 
         Ok(())
     }
 }
+
+/// Returns a query for `Tx` events matching `query`. If `query` already filters on `tm.event`
+/// it is returned unchanged.
+fn tx_query(query: &str) -> String {
+    if query.contains("tm.event") {
+        query.to_string()
+    } else if query.trim().is_empty() {
+        "tm.event='Tx'".to_string()
+    } else {
+        format!("tm.event='Tx' AND {}", query)
+    }
+}
+
+fn hex_upper(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chain_config(rpc_endpoint: &str) -> ChainConfig {
+        ChainConfig {
+            name: "osmosis".to_string(),
+            denom: "uosmo".to_string(),
+            prefix: "osmo".to_string(),
+            chain_id: "localosmosis".to_string(),
+            gas_price: Decimal::one(),
+            gas_price_source: Default::default(),
+            gas_adjustment: 1.2,
+            derivation_path: "m/44'/118'/0'/0/0".to_string(),
+            rpc_endpoint: rpc_endpoint.to_string(),
+            grpc_endpoint: "http://localhost:9090".to_string(),
+            websocket_endpoint: None,
+            transport: Default::default(),
+            timeouts: Default::default(),
+            default_funding: vec![],
+        }
+    }
+
+    #[test]
+    fn websocket_endpoint_from_rpc_endpoint() {
+        assert_eq!(
+            chain_config("http://localhost:26657").websocket_endpoint(),
+            "ws://localhost:26657/websocket"
+        );
+        assert_eq!(
+            chain_config("https://rpc.osmosis.zone:443/").websocket_endpoint(),
+            "wss://rpc.osmosis.zone:443/websocket"
+        );
+
+        let mut config = chain_config("http://localhost:26657");
+        config.websocket_endpoint = Some("ws://localhost:36657/websocket".to_string());
+        assert_eq!(
+            config.websocket_endpoint(),
+            "ws://localhost:36657/websocket"
+        );
+    }
+
+    #[test]
+    fn tx_query_adds_event_type() {
+        assert_eq!(
+            tx_query("wasm._contract_address='osmo1' AND wasm.action='increment'"),
+            "tm.event='Tx' AND wasm._contract_address='osmo1' AND wasm.action='increment'"
+        );
+        assert_eq!(tx_query("tm.event='Tx'"), "tm.event='Tx'");
+        assert_eq!(tx_query(""), "tm.event='Tx'");
+        assert!(tx_query("wasm.action='increment'").parse::<Query>().is_ok());
+    }
+
+    #[test]
+    fn fixed_gas_price_is_not_queried() {
+        // The endpoint is never contacted for a fixed gas price
+        let mut config = chain_config("http://localhost:1");
+        config.gas_price = Decimal::permille(25);
+        let chain = Chain::new(config).unwrap();
+
+        assert_eq!(block_on(chain.gas_price()).unwrap(), Decimal::permille(25));
+    }
+
+    #[test]
+    #[cfg(all(feature = "rpc-facade", feature = "multi-test"))]
+    fn queried_gas_price_is_cached_per_block() {
+        use crate::multi_test::MultiTestRunner;
+        use crate::rpc_facade::{MultiTestBackend, RpcFacade};
+
+        // The facade does not answer gas price queries, so only a cached price is returned
+        let facade = RpcFacade::start("localosmosis", || {
+            MultiTestBackend::new(MultiTestRunner::new("osmo"))
+        })
+        .unwrap();
+        let mut config = chain_config(&facade.endpoint());
+        config.gas_price_source = GasPriceSource::OsmosisTxFees;
+        config.timeouts.max_retries = 0;
+        let chain = Chain::new(config).unwrap();
+        let height = block_on(chain.latest_height()).unwrap();
+        let price = Decimal::percent(25);
+
+        *chain.gas_price_cache.lock().unwrap() = Some((height, price));
+        assert_eq!(block_on(chain.gas_price()).unwrap(), price);
+
+        *chain.gas_price_cache.lock().unwrap() = Some((height - 1, price));
+        assert!(block_on(chain.gas_price()).is_err());
+    }
+
+    #[test]
+    fn tx_hash_is_upper_hex_sha256() {
+        assert_eq!(
+            hex_upper(&Sha256::digest(b"tx")),
+            "1B5B9CCB3E8D006A5230DE9BDA23FF91EDC794D4F56410560830B418528E446C"
+        );
+    }
+
+    /// A gRPC server standing in for a node. Answers bank balance queries, simulations,
+    /// broadcasts and `GetTx`, which returns `NotFound` until it has been called twice.
+    #[cfg(feature = "grpc")]
+    mod grpc_stand_in {
+        use std::convert::Infallible;
+        use std::marker::PhantomData;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        use cosmrs::proto::cosmos::bank::v1beta1::{QueryBalanceRequest, QueryBalanceResponse};
+        use cosmrs::proto::cosmos::base::abci::v1beta1::TxResponse;
+        use cosmrs::proto::cosmos::base::v1beta1::Coin as ProtoCoin;
+        use tonic::body::BoxBody;
+        use tonic::codegen::{http, BoxFuture, Context, Poll, Service};
+        use tonic::server::{NamedService, UnaryService};
+        use tonic::transport::Body;
+        use tonic::Status;
+
+        use super::*;
+        use crate::rpc_runner::transport::RawCodec;
+
+        pub const BALANCE_PATH: &str = "/cosmos.bank.v1beta1.Query/Balance";
+        pub const GAS_USED: u64 = 1234;
+        pub const TX_HASH: &str = "ABCD";
+
+        pub trait ServiceName: Send + Sync + 'static {
+            const NAME: &'static str;
+        }
+
+        pub struct BankQuery;
+        impl ServiceName for BankQuery {
+            const NAME: &'static str = "cosmos.bank.v1beta1.Query";
+        }
+
+        pub struct TxService;
+        impl ServiceName for TxService {
+            const NAME: &'static str = "cosmos.tx.v1beta1.Service";
+        }
+
+        pub struct StandIn<N> {
+            get_tx_calls: Arc<AtomicUsize>,
+            name: PhantomData<N>,
+        }
+
+        impl<N> StandIn<N> {
+            pub fn new(get_tx_calls: Arc<AtomicUsize>) -> Self {
+                Self {
+                    get_tx_calls,
+                    name: PhantomData,
+                }
+            }
+        }
+
+        impl<N> Clone for StandIn<N> {
+            fn clone(&self) -> Self {
+                Self::new(self.get_tx_calls.clone())
+            }
+        }
+
+        impl<N: ServiceName> NamedService for StandIn<N> {
+            const NAME: &'static str = N::NAME;
+        }
+
+        impl<N: ServiceName> Service<http::Request<Body>> for StandIn<N> {
+            type Response = http::Response<BoxBody>;
+            type Error = Infallible;
+            type Future = BoxFuture<Self::Response, Self::Error>;
+
+            fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+                Poll::Ready(Ok(()))
+            }
+
+            fn call(&mut self, req: http::Request<Body>) -> Self::Future {
+                let method = Method {
+                    path: req.uri().path().to_string(),
+                    get_tx_calls: self.get_tx_calls.clone(),
+                };
+                Box::pin(
+                    async move { Ok(tonic::server::Grpc::new(RawCodec).unary(method, req).await) },
+                )
+            }
+        }
+
+        struct Method {
+            path: String,
+            get_tx_calls: Arc<AtomicUsize>,
+        }
+
+        impl UnaryService<Vec<u8>> for Method {
+            type Response = Vec<u8>;
+            type Future = BoxFuture<tonic::Response<Vec<u8>>, Status>;
+
+            fn call(&mut self, req: tonic::Request<Vec<u8>>) -> Self::Future {
+                let res = self.respond(&req.into_inner());
+                Box::pin(async move { res.map(tonic::Response::new) })
+            }
+        }
+
+        impl Method {
+            // Status is the error type of tonic services
+            #[allow(clippy::result_large_err)]
+            fn respond(&self, data: &[u8]) -> Result<Vec<u8>, Status> {
+                let decode_err = |e: prost::DecodeError| Status::invalid_argument(e.to_string());
+                match self.path.as_str() {
+                    BALANCE_PATH => {
+                        let req = QueryBalanceRequest::decode(data).map_err(decode_err)?;
+                        Ok(QueryBalanceResponse {
+                            balance: Some(ProtoCoin {
+                                denom: req.denom,
+                                amount: "100".to_string(),
+                            }),
+                        }
+                        .encode_to_vec())
+                    }
+                    "/cosmos.tx.v1beta1.Service/Simulate" => Ok(SimulateResponse {
+                        gas_info: Some(GasInfo {
+                            gas_wanted: 0,
+                            gas_used: GAS_USED,
+                        }),
+                        result: None,
+                    }
+                    .encode_to_vec()),
+                    "/cosmos.tx.v1beta1.Service/BroadcastTx" => Ok(BroadcastTxResponse {
+                        tx_response: Some(TxResponse {
+                            txhash: TX_HASH.to_string(),
+                            ..Default::default()
+                        }),
+                    }
+                    .encode_to_vec()),
+                    "/cosmos.tx.v1beta1.Service/GetTx" => {
+                        let req = GetTxRequest::decode(data).map_err(decode_err)?;
+                        // The tx is only found once it has been polled for
+                        if self.get_tx_calls.fetch_add(1, Ordering::SeqCst) < 1 {
+                            return Err(Status::not_found(format!("tx {} not found", req.hash)));
+                        }
+                        Ok(GetTxResponse {
+                            tx: None,
+                            tx_response: Some(TxResponse {
+                                height: 5,
+                                txhash: req.hash,
+                                gas_used: GAS_USED as i64,
+                                ..Default::default()
+                            }),
+                        }
+                        .encode_to_vec())
+                    }
+                    path => Err(Status::unimplemented(path.to_string())),
+                }
+            }
+        }
+
+        /// Starts the stand-in server on a free local port and returns its endpoint and the
+        /// number of `GetTx` calls it received.
+        pub fn start() -> (String, Arc<AtomicUsize>) {
+            let get_tx_calls = Arc::new(AtomicUsize::new(0));
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            listener.set_nonblocking(true).unwrap();
+            let endpoint = format!("http://{}", listener.local_addr().unwrap());
+
+            let runtime = crate::helpers::runtime();
+            let listener = {
+                let _guard = runtime.enter();
+                tokio::net::TcpListener::from_std(listener).unwrap()
+            };
+            let incoming = futures::stream::unfold(listener, |listener| async move {
+                let conn = listener.accept().await.map(|(stream, _)| stream);
+                Some((conn, listener))
+            });
+            runtime.spawn(
+                tonic::transport::Server::builder()
+                    .add_service(StandIn::<BankQuery>::new(get_tx_calls.clone()))
+                    .add_service(StandIn::<TxService>::new(get_tx_calls.clone()))
+                    .serve_with_incoming(incoming),
+            );
+
+            (endpoint, get_tx_calls)
+        }
+    }
+
+    #[cfg(feature = "grpc")]
+    #[test]
+    fn grpc_transport_against_stand_in_server() {
+        use std::sync::atomic::Ordering;
+
+        use cosmrs::proto::cosmos::bank::v1beta1::{QueryBalanceRequest, QueryBalanceResponse};
+
+        let (grpc_endpoint, get_tx_calls) = grpc_stand_in::start();
+        let mut config = chain_config("http://localhost:1");
+        config.grpc_endpoint = grpc_endpoint;
+        config.transport = Transport::Grpc;
+        config.timeouts.poll_interval_ms = 10;
+        let chain = Chain::new(config).unwrap();
+
+        // Queries reuse the connection of the client
+        for _ in 0..2 {
+            let req = QueryBalanceRequest {
+                address: "osmo1".to_string(),
+                denom: "uosmo".to_string(),
+            };
+            let res = block_on(chain.query_raw(grpc_stand_in::BALANCE_PATH, req.encode_to_vec()))
+                .unwrap();
+            let balance = QueryBalanceResponse::decode(res.as_slice())
+                .unwrap()
+                .balance
+                .unwrap();
+            assert_eq!(
+                (balance.denom.as_str(), balance.amount.as_str()),
+                ("uosmo", "100")
+            );
+        }
+
+        let gas_info = block_on(chain.simulate_tx(b"tx".to_vec())).unwrap();
+        assert_eq!(gas_info.gas_used, grpc_stand_in::GAS_USED);
+
+        // The broadcast tx is polled for with GetTx until it is found
+        let tx_result = block_on(chain.broadcast_tx_commit(b"tx".to_vec())).unwrap();
+        assert!(tx_result.is_ok());
+        assert_eq!(tx_result.hash, grpc_stand_in::TX_HASH);
+        assert_eq!(tx_result.height, 5);
+        assert_eq!(tx_result.gas_used, grpc_stand_in::GAS_USED);
+        assert_eq!(get_tx_calls.load(Ordering::SeqCst), 2);
+
+        // Unknown methods are reported as gRPC errors
+        match block_on(chain.query_raw("/cosmos.bank.v1beta1.Query/Unknown", vec![])) {
+            Err(ChainError::GrpcStatus(status)) => {
+                assert_eq!(status.code(), tonic::Code::Unimplemented)
+            }
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+}
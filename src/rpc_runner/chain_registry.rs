@@ -0,0 +1,323 @@
+//! Import of [`ChainConfig`] from the [cosmos chain-registry](https://github.com/cosmos/chain-registry)
+//! `chain.json` and `assetlist.json` format.
+
+use std::path::Path;
+use std::str::FromStr;
+
+use cosmwasm_std::Decimal;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use super::chain::{ChainConfig, ChainError};
+
+/// Gas adjustment used for configs imported from the chain registry, since the registry does
+/// not specify one.
+pub const DEFAULT_GAS_ADJUSTMENT: f64 = 1.3;
+
+/// The fields of a chain-registry `chain.json` that are used to build a [`ChainConfig`].
+#[derive(Clone, Debug, Deserialize)]
+pub struct RegistryChain {
+    pub chain_name: String,
+    pub chain_id: String,
+    pub bech32_prefix: String,
+    #[serde(default = "default_slip44")]
+    pub slip44: u32,
+    #[serde(default)]
+    pub fees: Option<RegistryFees>,
+    #[serde(default)]
+    pub staking: Option<RegistryStaking>,
+    #[serde(default)]
+    pub apis: RegistryApis,
+}
+
+fn default_slip44() -> u32 {
+    118
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct RegistryFees {
+    #[serde(default)]
+    pub fee_tokens: Vec<RegistryFeeToken>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct RegistryFeeToken {
+    pub denom: String,
+    pub fixed_min_gas_price: Option<f64>,
+    pub low_gas_price: Option<f64>,
+    pub average_gas_price: Option<f64>,
+    pub high_gas_price: Option<f64>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct RegistryStaking {
+    #[serde(default)]
+    pub staking_tokens: Vec<RegistryDenom>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct RegistryDenom {
+    pub denom: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct RegistryApis {
+    #[serde(default)]
+    pub rpc: Vec<RegistryEndpoint>,
+    #[serde(default)]
+    pub grpc: Vec<RegistryEndpoint>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct RegistryEndpoint {
+    pub address: String,
+    pub provider: Option<String>,
+}
+
+/// The fields of a chain-registry `assetlist.json` that are used to build a [`ChainConfig`].
+#[derive(Clone, Debug, Deserialize)]
+pub struct RegistryAssetList {
+    pub chain_name: String,
+    #[serde(default)]
+    pub assets: Vec<RegistryAsset>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct RegistryAsset {
+    pub base: String,
+    pub symbol: Option<String>,
+}
+
+impl ChainConfig {
+    /// Reads a chain config from a chain-registry `chain.json` and `assetlist.json`.
+    ///
+    /// - The fee denom is the first of `fees.fee_tokens`. If there are none, the first staking
+    ///   token or the first asset of the asset list is used. The denom must be in the asset list.
+    /// - The gas price is the `average_gas_price` of the fee token, falling back to
+    ///   `low_gas_price` and `fixed_min_gas_price`.
+    /// - The derivation path is `m/44'/{slip44}'/0'/0/0`.
+    /// - The endpoints are the first of `apis.rpc` and `apis.grpc`. gRPC addresses without a
+    ///   scheme get `https://` if they use port 443 and `http://` otherwise.
+    pub fn from_chain_registry(
+        chain_json_path: impl AsRef<Path>,
+        assetlist_path: impl AsRef<Path>,
+    ) -> Result<Self, ChainError> {
+        let chain: RegistryChain = read_json(chain_json_path)?;
+        let assetlist: RegistryAssetList = read_json(assetlist_path)?;
+        Self::from_registry_entries(chain, assetlist)
+    }
+
+    /// Builds a chain config from already parsed chain-registry entries. See
+    /// [`ChainConfig::from_chain_registry`].
+    pub fn from_registry_entries(
+        chain: RegistryChain,
+        assetlist: RegistryAssetList,
+    ) -> Result<Self, ChainError> {
+        if chain.chain_name != assetlist.chain_name {
+            return Err(ChainError::ChainRegistry(format!(
+                "asset list is for chain {}, expected {}",
+                assetlist.chain_name, chain.chain_name
+            )));
+        }
+
+        let fee_token = chain.fees.as_ref().and_then(|fees| fees.fee_tokens.first());
+        let denom = fee_token
+            .map(|token| token.denom.clone())
+            .or_else(|| {
+                chain
+                    .staking
+                    .as_ref()
+                    .and_then(|staking| staking.staking_tokens.first())
+                    .map(|token| token.denom.clone())
+            })
+            .or_else(|| assetlist.assets.first().map(|asset| asset.base.clone()))
+            .ok_or_else(|| ChainError::ChainRegistry("no fee denom found".to_string()))?;
+        if !assetlist.assets.iter().any(|asset| asset.base == denom) {
+            return Err(ChainError::ChainRegistry(format!(
+                "fee denom {} is not in the asset list",
+                denom
+            )));
+        }
+
+        let gas_price = fee_token
+            .and_then(|token| {
+                token
+                    .average_gas_price
+                    .or(token.low_gas_price)
+                    .or(token.fixed_min_gas_price)
+            })
+            .map(|price| {
+                Decimal::from_str(&price.to_string()).map_err(|e| {
+                    ChainError::ChainRegistry(format!("invalid gas price {}: {}", price, e))
+                })
+            })
+            .transpose()?
+            .unwrap_or_default();
+
+        let rpc_endpoint = chain
+            .apis
+            .rpc
+            .first()
+            .map(|endpoint| endpoint.address.trim_end_matches('/').to_string())
+            .ok_or_else(|| ChainError::ChainRegistry("no rpc endpoint found".to_string()))?;
+        let grpc_endpoint = chain
+            .apis
+            .grpc
+            .first()
+            .map(|endpoint| grpc_url(&endpoint.address))
+            .unwrap_or_default();
+
+        Ok(Self {
+            name: chain.chain_name,
+            denom,
+            prefix: chain.bech32_prefix,
+            chain_id: chain.chain_id,
+            gas_price,
+            gas_price_source: Default::default(),
+            gas_adjustment: DEFAULT_GAS_ADJUSTMENT,
+            derivation_path: format!("m/44'/{}'/0'/0/0", chain.slip44),
+            rpc_endpoint,
+            grpc_endpoint,
+            websocket_endpoint: None,
+            transport: Default::default(),
+            timeouts: Default::default(),
+            default_funding: vec![],
+        })
+    }
+}
+
+fn read_json<T: DeserializeOwned>(path: impl AsRef<Path>) -> Result<T, ChainError> {
+    let path = path.as_ref();
+    let file = std::fs::read(path).map_err(|e| {
+        ChainError::ChainRegistry(format!("failed to read {}: {}", path.display(), e))
+    })?;
+    serde_json::from_slice(&file).map_err(|e| {
+        ChainError::ChainRegistry(format!("failed to parse {}: {}", path.display(), e))
+    })
+}
+
+/// Chain-registry gRPC addresses usually don't include a scheme, which tonic requires.
+fn grpc_url(address: &str) -> String {
+    let address = address.trim_end_matches('/');
+    if address.contains("://") {
+        address.to_string()
+    } else if address.ends_with(":443") {
+        format!("https://{}", address)
+    } else {
+        format!("http://{}", address)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHAIN_JSON: &str = r#"{
+        "$schema": "../chain.schema.json",
+        "chain_name": "osmosistestnet",
+        "status": "live",
+        "network_type": "testnet",
+        "chain_id": "osmo-test-5",
+        "bech32_prefix": "osmo",
+        "slip44": 118,
+        "fees": {
+            "fee_tokens": [
+                {
+                    "denom": "uosmo",
+                    "fixed_min_gas_price": 0.0025,
+                    "low_gas_price": 0.0025,
+                    "average_gas_price": 0.025,
+                    "high_gas_price": 0.04
+                }
+            ]
+        },
+        "staking": { "staking_tokens": [{ "denom": "uosmo" }] },
+        "apis": {
+            "rpc": [{ "address": "https://rpc.osmotest5.osmosis.zone/", "provider": "Osmosis" }],
+            "rest": [{ "address": "https://lcd.osmotest5.osmosis.zone", "provider": "Osmosis" }],
+            "grpc": [{ "address": "grpc.osmotest5.osmosis.zone:443", "provider": "Osmosis" }]
+        }
+    }"#;
+
+    const ASSETLIST_JSON: &str = r#"{
+        "$schema": "../assetlist.schema.json",
+        "chain_name": "osmosistestnet",
+        "assets": [
+            {
+                "description": "The native token of Osmosis",
+                "denom_units": [
+                    { "denom": "uosmo", "exponent": 0 },
+                    { "denom": "osmo", "exponent": 6 }
+                ],
+                "base": "uosmo",
+                "name": "Osmosis Testnet",
+                "display": "osmo",
+                "symbol": "OSMO"
+            }
+        ]
+    }"#;
+
+    fn write_registry_files(name: &str, chain_json: &str) -> (String, String) {
+        let dir = std::env::temp_dir().join(format!("cw_it_chain_registry_{}", name));
+        std::fs::create_dir_all(&dir).unwrap();
+        let chain_path = dir.join("chain.json");
+        let assetlist_path = dir.join("assetlist.json");
+        std::fs::write(&chain_path, chain_json).unwrap();
+        std::fs::write(&assetlist_path, ASSETLIST_JSON).unwrap();
+        (
+            chain_path.to_str().unwrap().to_string(),
+            assetlist_path.to_str().unwrap().to_string(),
+        )
+    }
+
+    #[test]
+    fn from_chain_registry() {
+        let (chain_path, assetlist_path) = write_registry_files("osmosis", CHAIN_JSON);
+
+        let config = ChainConfig::from_chain_registry(chain_path, assetlist_path).unwrap();
+
+        assert_eq!(config.name, "osmosistestnet");
+        assert_eq!(config.chain_id, "osmo-test-5");
+        assert_eq!(config.denom, "uosmo");
+        assert_eq!(config.prefix, "osmo");
+        assert_eq!(config.gas_price, Decimal::permille(25));
+        assert_eq!(config.gas_adjustment, DEFAULT_GAS_ADJUSTMENT);
+        assert_eq!(config.derivation_path, "m/44'/118'/0'/0/0");
+        assert_eq!(config.rpc_endpoint, "https://rpc.osmotest5.osmosis.zone");
+        assert_eq!(
+            config.grpc_endpoint,
+            "https://grpc.osmotest5.osmosis.zone:443"
+        );
+    }
+
+    #[test]
+    fn from_chain_registry_denom_not_in_assetlist() {
+        let chain_json = CHAIN_JSON.replace(r#""denom": "uosmo","#, r#""denom": "uion","#);
+        let (chain_path, assetlist_path) = write_registry_files("uion", &chain_json);
+
+        let err = ChainConfig::from_chain_registry(chain_path, assetlist_path).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid chain registry entry: fee denom uion is not in the asset list"
+        );
+    }
+
+    #[test]
+    fn from_chain_registry_missing_file() {
+        let err =
+            ChainConfig::from_chain_registry("does/not/exist.json", "assetlist.json").unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("failed to read does/not/exist.json"));
+    }
+
+    #[test]
+    fn grpc_url_scheme() {
+        assert_eq!(
+            grpc_url("grpc.osmosis.zone:443"),
+            "https://grpc.osmosis.zone:443"
+        );
+        assert_eq!(grpc_url("localhost:9090"), "http://localhost:9090");
+        assert_eq!(grpc_url("http://localhost:9090/"), "http://localhost:9090");
+    }
+}
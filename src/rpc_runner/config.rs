@@ -1,16 +1,166 @@
-use config::Config;
+use config::{Config, ConfigError};
 use cosmwasm_std::Coin;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
 
 use super::chain::ChainConfig;
-use crate::helpers::get_current_working_dir;
+use super::error::RpcRunnerError;
 
+/// Environment variables that override values of a [`ChainConfig`] read from a file, and the
+/// config keys they override. E.g. `CW_IT_RPC_ENDPOINT=http://localhost:26657` makes the
+/// runner connect to a local node regardless of the endpoint in the config file.
+pub const CHAIN_CONFIG_ENV_OVERRIDES: &[(&str, &str)] = &[
+    ("CW_IT_RPC_ENDPOINT", "rpc_endpoint"),
+    ("CW_IT_GRPC_ENDPOINT", "grpc_endpoint"),
+    ("CW_IT_CHAIN_ID", "chain_id"),
+    ("CW_IT_GAS_PRICE", "gas_price"),
+    ("CW_IT_TRANSPORT", "transport"),
+];
+
+/// Environment variable that overrides `funding_account_mnemonic` of a [`RpcRunnerConfig`].
+pub const FUNDING_ACCOUNT_MNEMONIC_ENV: &str = "CW_IT_FUNDING_ACCOUNT_MNEMONIC";
+
+/// Reads a config of type `T` from the yaml file at `file`, applying the overrides in
+/// [`CHAIN_CONFIG_ENV_OVERRIDES`] to the chain config found under `chain_config_key`. `env` is
+/// used to look up environment variables.
+pub(crate) fn from_yaml_with_env<T, F>(
+    file: &str,
+    chain_config_key: &str,
+    env: F,
+) -> Result<T, ConfigError>
+where
+    T: DeserializeOwned,
+    F: Fn(&str) -> Option<String>,
+{
+    let mut builder = Config::builder().add_source(config::File::with_name(file));
+    for (var, key) in CHAIN_CONFIG_ENV_OVERRIDES {
+        let key = if chain_config_key.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", chain_config_key, key)
+        };
+        builder = builder.set_override_option(key, env(var))?;
+    }
+    if !chain_config_key.is_empty() {
+        builder = builder.set_override_option(
+            "funding_account_mnemonic",
+            env(FUNDING_ACCOUNT_MNEMONIC_ENV),
+        )?;
+    }
+
+    builder.build()?.try_deserialize()
+}
+
+/// An account with a known mnemonic that can be loaded by name with
+/// [`RpcRunner::account`](super::RpcRunner::account).
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ImportedAccount {
     pub name: String,
+    /// The expected address of the account. Used to verify that the mnemonic and derivation
+    /// path resolve to the intended account.
     pub address: String,
     pub mnemonic: String,
+    #[serde(default)]
     pub pubkey: String,
+    /// Derivation path to use for this account. Defaults to the derivation path of the chain
+    /// config if not set.
+    #[serde(default)]
+    pub derivation_path: Option<String>,
+}
+
+/// Format of a file containing a list of named accounts, e.g:
+/// ```yaml
+/// accounts:
+///   - name: admin
+///     address: osmo1...
+///     mnemonic: "..."
+/// ```
+#[derive(Clone, Debug, Deserialize)]
+struct ImportedAccountsFile {
+    accounts: Vec<ImportedAccount>,
+}
+
+/// Environment variable the keyring password is read from if no other variable is configured.
+pub const DEFAULT_KEYRING_PASSWORD_ENV: &str = "CW_IT_KEYRING_PASSWORD";
+
+/// Where to load the private key of the funding account from instead of
+/// `funding_account_mnemonic`. Requires the `keyring` feature.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FundingAccountKey {
+    /// A key in a Cosmos SDK keyring directory.
+    Keyring(KeyringConfig),
+    /// An ASCII armored private key exported with `<chaind> keys export`.
+    ArmoredKey(ArmoredKeyConfig),
+}
+
+/// The keyring backends that can be read.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyringBackend {
+    /// Entries are encrypted with the password `test`.
+    Test,
+    /// Entries are encrypted with a user chosen password.
+    File,
+}
+
+impl KeyringBackend {
+    fn dir_name(&self) -> &'static str {
+        match self {
+            KeyringBackend::Test => "keyring-test",
+            KeyringBackend::File => "keyring-file",
+        }
+    }
+}
+
+/// A key in a Cosmos SDK keyring directory, e.g:
+/// ```yaml
+/// funding_account_key:
+///   keyring:
+///     backend: test
+///     dir: /home/user/.osmosisd
+///     name: validator
+/// ```
+#[derive(Clone, Debug, Deserialize)]
+pub struct KeyringConfig {
+    pub backend: KeyringBackend,
+    /// The home directory of the chain binary, or the `keyring-<backend>` directory in it.
+    pub dir: String,
+    /// The name of the key, i.e. the name used with `<chaind> keys add`.
+    pub name: String,
+    /// Environment variable to read the password of the `file` backend from. Defaults to
+    /// [`DEFAULT_KEYRING_PASSWORD_ENV`].
+    #[serde(default)]
+    pub password_env: Option<String>,
+}
+
+impl KeyringConfig {
+    /// Returns the path of the `<name>.info` file of the key.
+    pub fn info_path(&self) -> PathBuf {
+        let dir = Path::new(&self.dir);
+        let keyring_dir = if dir.ends_with(self.backend.dir_name()) {
+            dir.to_path_buf()
+        } else {
+            dir.join(self.backend.dir_name())
+        };
+        keyring_dir.join(format!("{}.info", self.name))
+    }
+}
+
+/// An ASCII armored private key exported with `<chaind> keys export`, e.g:
+/// ```yaml
+/// funding_account_key:
+///   armored_key:
+///     path: keys/funder.armor
+///     password_env: FUNDER_PASSWORD
+/// ```
+#[derive(Clone, Debug, Deserialize)]
+pub struct ArmoredKeyConfig {
+    pub path: String,
+    /// Environment variable to read the password from. Defaults to [`DEFAULT_KEYRING_PASSWORD_ENV`].
+    #[serde(default)]
+    pub password_env: Option<String>,
 }
 
 /// This enum exactly matches the `FeeSetting` enum in `test-tube` and is only needed
@@ -47,18 +197,230 @@ impl From<FeeSetting> for test_tube::account::FeeSetting {
 #[derive(Clone, Debug, Deserialize)]
 pub struct RpcRunnerConfig {
     pub chain_config: ChainConfig,
+    /// Mnemonic of the account used to fund new accounts. Can be left empty if
+    /// `funding_account_key` is set.
+    #[serde(default)]
     pub funding_account_mnemonic: String,
+    /// Load the funding account from a keyring or armored key file instead of the mnemonic.
+    #[serde(default)]
+    pub funding_account_key: Option<FundingAccountKey>,
     pub fee_setting: Option<FeeSetting>,
+    /// Named accounts that can be loaded with [`RpcRunner::account`](super::RpcRunner::account).
+    #[serde(default)]
+    pub accounts: Vec<ImportedAccount>,
+    /// Optional path to a file with more named accounts. See [`RpcRunnerConfig::accounts`].
+    #[serde(default)]
+    pub accounts_file: Option<String>,
+    /// Send the remaining balances of accounts created by the runner back to the funding account
+    /// when the runner is dropped. See [`RpcRunner::sweep_accounts`](super::RpcRunner::sweep_accounts).
+    /// Only dropping the [`RpcRunner`](super::RpcRunner) itself sweeps, not dropping a
+    /// [`TestRunner`](crate::TestRunner) that borrows it.
+    #[serde(default)]
+    pub sweep_on_drop: bool,
+    /// Gzip wasm files in `store_code` before uploading them. The chain decompresses them, and
+    /// the smaller transactions use less gas.
+    #[serde(default)]
+    pub compress_uploads: bool,
 }
 
 impl RpcRunnerConfig {
-    pub fn from_yaml(file: &str) -> Self {
-        println!("Working directory [{}]", get_current_working_dir());
-        println!("Reading {}", file);
-        let settings = Config::builder()
-            .add_source(config::File::with_name(file))
-            .build()
+    /// Reads the config from the yaml file at `file`. Values of the chain config can be
+    /// overridden with the environment variables in [`CHAIN_CONFIG_ENV_OVERRIDES`] and the
+    /// funding account mnemonic with [`FUNDING_ACCOUNT_MNEMONIC_ENV`].
+    pub fn from_yaml(file: &str) -> Result<Self, ConfigError> {
+        from_yaml_with_env(file, "chain_config", |var| std::env::var(var).ok())
+    }
+
+    /// Returns all named accounts of the config, i.e. the accounts listed in `accounts` followed
+    /// by the accounts in `accounts_file`, if set. Returns an error if a name is used twice.
+    pub fn imported_accounts(&self) -> Result<Vec<ImportedAccount>, RpcRunnerError> {
+        let mut accounts = self.accounts.clone();
+        if let Some(file) = &self.accounts_file {
+            let settings = Config::builder()
+                .add_source(config::File::with_name(file))
+                .build()?;
+            accounts.extend(settings.try_deserialize::<ImportedAccountsFile>()?.accounts);
+        }
+
+        for (i, account) in accounts.iter().enumerate() {
+            if accounts[..i].iter().any(|a| a.name == account.name) {
+                return Err(RpcRunnerError::Generic(format!(
+                    "Duplicate imported account name: {}",
+                    account.name
+                )));
+            }
+        }
+
+        Ok(accounts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chain_config() -> ChainConfig {
+        ChainConfig {
+            name: "osmosis".to_string(),
+            denom: "uosmo".to_string(),
+            prefix: "osmo".to_string(),
+            chain_id: "localosmosis".to_string(),
+            gas_price: cosmwasm_std::Decimal::one(),
+            gas_price_source: Default::default(),
+            gas_adjustment: 1.2,
+            derivation_path: "m/44'/118'/0'/0/0".to_string(),
+            rpc_endpoint: "http://localhost:26657".to_string(),
+            grpc_endpoint: "http://localhost:9090".to_string(),
+            websocket_endpoint: None,
+            transport: Default::default(),
+            timeouts: Default::default(),
+            default_funding: vec![],
+        }
+    }
+
+    fn imported_account(name: &str) -> ImportedAccount {
+        ImportedAccount {
+            name: name.to_string(),
+            address: "osmo12smx2wdlyttvyzvzg54y2vnqwq2qjateuf7thj".to_string(),
+            mnemonic: "bottom loan skill merry east cradle onion journey palm apology verb edit desert impose absurd oil bubble sweet glove shallow size build burst effort".to_string(),
+            pubkey: "".to_string(),
+            derivation_path: None,
+        }
+    }
+
+    fn rpc_runner_config(accounts_file: Option<String>) -> RpcRunnerConfig {
+        RpcRunnerConfig {
+            chain_config: chain_config(),
+            funding_account_mnemonic: imported_account("funder").mnemonic,
+            funding_account_key: None,
+            fee_setting: None,
+            accounts: vec![imported_account("admin")],
+            accounts_file,
+            sweep_on_drop: false,
+            compress_uploads: false,
+        }
+    }
+
+    #[test]
+    fn imported_accounts_from_config_and_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("imported_accounts.yaml");
+        std::fs::write(
+            &path,
+            format!(
+                "accounts:\n  - name: keeper\n    address: {}\n    mnemonic: \"{}\"\n",
+                imported_account("keeper").address,
+                imported_account("keeper").mnemonic
+            ),
+        )
+        .unwrap();
+
+        let accounts = rpc_runner_config(Some(path.to_str().unwrap().to_string()))
+            .imported_accounts()
             .unwrap();
-        settings.try_deserialize::<Self>().unwrap()
+
+        let names = accounts.iter().map(|a| a.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["admin", "keeper"]);
+        assert_eq!(accounts[1].derivation_path, None);
+    }
+
+    #[test]
+    fn imported_accounts_duplicate_name() {
+        let mut config = rpc_runner_config(None);
+        config.accounts.push(imported_account("admin"));
+
+        let err = config.imported_accounts().unwrap_err();
+        assert_eq!(err.to_string(), "Duplicate imported account name: admin");
+    }
+
+    #[test]
+    fn from_yaml_with_env_overrides() {
+        use crate::rpc_runner::chain::TimeoutConfig;
+        use crate::rpc_runner::transport::Transport;
+        use std::collections::HashMap;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rpc_runner_config.yaml");
+        std::fs::write(
+            &path,
+            "chain_config:
+  name: osmosis
+  denom: uosmo
+  prefix: osmo
+  chain_id: osmosis-1
+  gas_price: 1
+  gas_adjustment: 1.2
+  derivation_path: m/44'/118'/0'/0/0
+  rpc_endpoint: https://rpc.osmosis.zone:443
+  grpc_endpoint: https://grpc.osmosis.zone:443
+  timeouts:
+    max_retries: 5
+funding_account_mnemonic: from file
+",
+        )
+        .unwrap();
+        let file = path.to_str().unwrap();
+
+        let config: RpcRunnerConfig = from_yaml_with_env(file, "chain_config", |_| None).unwrap();
+        assert_eq!(
+            config.chain_config.rpc_endpoint,
+            "https://rpc.osmosis.zone:443"
+        );
+        assert_eq!(config.chain_config.transport, Transport::Rpc);
+        assert_eq!(
+            config.chain_config.timeouts,
+            TimeoutConfig {
+                max_retries: 5,
+                ..Default::default()
+            }
+        );
+
+        let env: HashMap<&str, &str> = [
+            ("CW_IT_RPC_ENDPOINT", "http://localhost:26657"),
+            ("CW_IT_CHAIN_ID", "localosmosis"),
+            ("CW_IT_GAS_PRICE", "25"),
+            ("CW_IT_TRANSPORT", "grpc"),
+            ("CW_IT_FUNDING_ACCOUNT_MNEMONIC", "from env"),
+        ]
+        .into_iter()
+        .collect();
+        let config: RpcRunnerConfig = from_yaml_with_env(file, "chain_config", |var| {
+            env.get(var).map(|v| v.to_string())
+        })
+        .unwrap();
+        assert_eq!(config.chain_config.rpc_endpoint, "http://localhost:26657");
+        assert_eq!(
+            config.chain_config.grpc_endpoint,
+            "https://grpc.osmosis.zone:443"
+        );
+        assert_eq!(config.chain_config.chain_id, "localosmosis");
+        assert_eq!(
+            config.chain_config.gas_price,
+            cosmwasm_std::Decimal::percent(2500)
+        );
+        assert_eq!(config.chain_config.transport, Transport::Grpc);
+        assert_eq!(config.funding_account_mnemonic, "from env");
+    }
+
+    #[test]
+    fn from_yaml_missing_file() {
+        assert!(RpcRunnerConfig::from_yaml("does/not/exist.yaml").is_err());
+    }
+
+    #[test]
+    fn funding_account_key_keyring() {
+        let key: FundingAccountKey = serde_json::from_str(
+            r#"{"keyring": {"backend": "test", "dir": "/home/user/.osmosisd", "name": "validator"}}"#,
+        )
+        .unwrap();
+
+        let FundingAccountKey::Keyring(keyring) = key else {
+            panic!("expected keyring config");
+        };
+        assert_eq!(keyring.backend, KeyringBackend::Test);
+        assert_eq!(
+            keyring.info_path(),
+            PathBuf::from("/home/user/.osmosisd/keyring-test/validator.info")
+        );
     }
 }
@@ -1,13 +1,69 @@
+use cosmwasm_std::Uint128;
 use thiserror::Error;
 
+use super::chain::ChainError;
+
 #[derive(Debug, Error)]
 pub enum RpcRunnerError {
     #[error("{0}")]
     Bip32(#[from] bip32::Error),
 
     #[error("{0}")]
-    ChainError(#[from] super::chain::ChainError),
+    ChainError(ChainError),
+
+    /// A query or transaction failed on chain. Contains the ABCI response code, codespace and
+    /// log returned by the node.
+    #[error("ABCI error (codespace: {codespace}, code: {code}): {log}")]
+    Abci {
+        code: u32,
+        codespace: String,
+        log: String,
+    },
+
+    #[error("{0}")]
+    ConfigError(#[from] config::ConfigError),
+
+    #[error("Imported account not found: {0}")]
+    AccountNotFound(String),
+
+    #[error(
+        "Address of imported account {name} does not match. Expected {expected}, got {actual}"
+    )]
+    AccountAddressMismatch {
+        name: String,
+        expected: String,
+        actual: String,
+    },
+
+    #[error("Funding account {address} has {available}{denom}, but {required}{denom} is needed")]
+    InsufficientFunds {
+        address: String,
+        denom: String,
+        required: Uint128,
+        available: Uint128,
+    },
+
+    #[cfg(feature = "keyring")]
+    #[error("{0}")]
+    Keyring(#[from] super::keyring::KeyringError),
 
     #[error("{0}")]
     Generic(String),
 }
+
+impl From<ChainError> for RpcRunnerError {
+    fn from(err: ChainError) -> Self {
+        match err {
+            ChainError::Abci {
+                code,
+                codespace,
+                log,
+            } => RpcRunnerError::Abci {
+                code,
+                codespace,
+                log,
+            },
+            err => RpcRunnerError::ChainError(err),
+        }
+    }
+}
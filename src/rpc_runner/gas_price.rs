@@ -0,0 +1,201 @@
+//! Discovery of the chain's current minimum gas price, used by `RpcRunner` to pay fees that are
+//! accepted during congestion instead of the fixed `gas_price` of the [`ChainConfig`].
+//!
+//! [`ChainConfig`]: super::chain::ChainConfig
+
+use std::str::FromStr;
+
+use cosmwasm_std::{Decimal, Uint128};
+use serde::{Deserialize, Deserializer};
+
+use super::chain::ChainError;
+
+/// Number of decimals of the atomics of a Cosmos SDK `LegacyDec` when encoded in protobuf.
+const LEGACY_DEC_DECIMALS: u32 = 18;
+
+/// Where to get the gas price of transactions from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GasPriceSource {
+    /// Always use the `gas_price` of the chain config.
+    #[default]
+    Fixed,
+    /// The EIP-1559 base fee of the Osmosis `txfees` module.
+    OsmosisTxFees,
+    /// The gas price of the fee denom from the `feemarket` module.
+    FeeMarket,
+    /// The `minimum-gas-prices` of the node, from the node service config endpoint.
+    NodeMinGasPrices,
+}
+
+impl GasPriceSource {
+    /// Returns true if the gas price is queried from the chain.
+    pub fn is_dynamic(&self) -> bool {
+        *self != GasPriceSource::Fixed
+    }
+}
+
+pub const OSMOSIS_EIP_BASE_FEE_PATH: &str = "/osmosis.txfees.v1beta1.Query/GetEipBaseFee";
+pub const FEE_MARKET_GAS_PRICE_PATH: &str = "/feemarket.feemarket.v1.Query/GasPrice";
+pub const NODE_CONFIG_PATH: &str = "/cosmos.base.node.v1beta1.Service/Config";
+
+/// `feemarket.feemarket.v1.GasPriceRequest`
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct GasPriceRequest {
+    #[prost(string, tag = "1")]
+    pub denom: String,
+}
+
+/// `feemarket.feemarket.v1.GasPriceResponse`
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct GasPriceResponse {
+    #[prost(message, optional, tag = "1")]
+    pub price: Option<DecCoin>,
+}
+
+/// `cosmos.base.v1beta1.DecCoin`
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct DecCoin {
+    #[prost(string, tag = "1")]
+    pub denom: String,
+    #[prost(string, tag = "2")]
+    pub amount: String,
+}
+
+/// `cosmos.base.node.v1beta1.ConfigRequest`
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ConfigRequest {}
+
+/// `cosmos.base.node.v1beta1.ConfigResponse`
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ConfigResponse {
+    #[prost(string, tag = "1")]
+    pub minimum_gas_price: String,
+}
+
+/// Deserializes a decimal from a number, e.g. `0.025` in a yaml file, or a string, e.g. from an
+/// environment variable.
+pub fn deserialize_decimal<'de, D>(deserializer: D) -> Result<Decimal, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum DecimalValue {
+        Int(u64),
+        Float(f64),
+        Str(String),
+    }
+
+    let value = match DecimalValue::deserialize(deserializer)? {
+        DecimalValue::Int(value) => return Ok(Decimal::from_ratio(value, 1u128)),
+        DecimalValue::Float(value) => value.to_string(),
+        DecimalValue::Str(value) => value,
+    };
+    Decimal::from_str(value.trim()).map_err(serde::de::Error::custom)
+}
+
+/// Parses a `LegacyDec` as encoded in protobuf messages, i.e. as an integer of atomics with
+/// 18 decimals.
+pub fn parse_legacy_dec(atomics: &str) -> Result<Decimal, ChainError> {
+    let invalid = |e: String| ChainError::GasPrice(format!("invalid decimal {}: {}", atomics, e));
+    let atomics = Uint128::from_str(atomics).map_err(|e| invalid(e.to_string()))?;
+    Decimal::from_atomics(atomics, LEGACY_DEC_DECIMALS).map_err(|e| invalid(e.to_string()))
+}
+
+/// Returns the price of `denom` from a node's `minimum-gas-prices` setting, e.g.
+/// `0.0025uosmo,0.1uion`. Returns zero if the node doesn't set a price for `denom`.
+pub fn parse_min_gas_price(min_gas_prices: &str, denom: &str) -> Result<Decimal, ChainError> {
+    for price in min_gas_prices.split(',').map(str::trim) {
+        let Some(amount) = price.strip_suffix(denom) else {
+            continue;
+        };
+        // Make sure the suffix is the whole denom, e.g. not `osmo` of `uosmo`
+        if amount.ends_with(|c: char| c.is_ascii_alphabetic()) {
+            continue;
+        }
+        return Decimal::from_str(amount).map_err(|e| {
+            ChainError::GasPrice(format!("invalid minimum gas price {}: {}", price, e))
+        });
+    }
+    Ok(Decimal::zero())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decimal_from_number_or_string() {
+        #[derive(Deserialize)]
+        struct Config {
+            #[serde(deserialize_with = "deserialize_decimal")]
+            gas_price: Decimal,
+        }
+
+        let gas_price = |json: &str| {
+            serde_json::from_str::<Config>(json)
+                .map(|config| config.gas_price)
+                .ok()
+        };
+        assert_eq!(
+            gas_price(r#"{"gas_price": 25}"#),
+            Some(Decimal::percent(2500))
+        );
+        assert_eq!(
+            gas_price(r#"{"gas_price": 0.025}"#),
+            Some(Decimal::permille(25))
+        );
+        assert_eq!(
+            gas_price(r#"{"gas_price": "0.025"}"#),
+            Some(Decimal::permille(25))
+        );
+        assert_eq!(gas_price(r#"{"gas_price": "cheap"}"#), None);
+        assert_eq!(gas_price(r#"{"gas_price": -1}"#), None);
+    }
+
+    #[test]
+    fn legacy_dec() {
+        assert_eq!(
+            parse_legacy_dec("2500000000000000").unwrap(),
+            Decimal::from_str("0.0025").unwrap()
+        );
+        assert_eq!(
+            parse_legacy_dec("1000000000000000000").unwrap(),
+            Decimal::one()
+        );
+        assert!(parse_legacy_dec("0.0025").is_err());
+    }
+
+    #[test]
+    fn min_gas_price() {
+        let prices =
+            "0.0025uosmo, 0.1ibc/27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2";
+
+        assert_eq!(
+            parse_min_gas_price(prices, "uosmo").unwrap(),
+            Decimal::from_str("0.0025").unwrap()
+        );
+        assert_eq!(
+            parse_min_gas_price(
+                prices,
+                "ibc/27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2"
+            )
+            .unwrap(),
+            Decimal::from_str("0.1").unwrap()
+        );
+        assert_eq!(
+            parse_min_gas_price(prices, "osmo").unwrap(),
+            Decimal::zero()
+        );
+        assert_eq!(parse_min_gas_price("", "uosmo").unwrap(), Decimal::zero());
+    }
+
+    #[test]
+    fn gas_price_source_deserialize() {
+        let source: GasPriceSource = serde_json::from_str(r#""osmosis_tx_fees""#).unwrap();
+        assert_eq!(source, GasPriceSource::OsmosisTxFees);
+        assert!(source.is_dynamic());
+        assert!(!GasPriceSource::default().is_dynamic());
+    }
+}
@@ -4,15 +4,39 @@
 //! There are some fundamental differences between this runner and the others.
 //!
 //! 1. Since we are only interacting with the blockchain via RPC and are not controlling the entire chain.
-//! This means that to initialize accounts with the `init_account` method a funding account is required.
-//! This funding account is used to send tokens to the account that is being initialized, which means that the
-//! funding account must have enough tokens to initialize all the accounts that are being initialized.
+//!    This means that to initialize accounts with the `init_account` method a funding account is required.
+//!    This funding account is used to send tokens to the account that is being initialized, which means that the
+//!    funding account must have enough tokens to initialize all the accounts that are being initialized.
 //!
-//! 2. The `increase_time` function will panic as it is not implementable via RPC.
+//! 2. The time of the chain can't be changed via RPC, so the `increase_time` function waits until
+//!    a block at least the given number of seconds later has been produced instead.
+//!
+//! Queries, simulations and transactions are sent via Tendermint RPC by default. Set
+//! `transport: grpc` in the chain config to use the chain's gRPC endpoint instead. This requires
+//! the `grpc` feature.
+//!
+//! Values of the chain config can be overridden with environment variables, e.g.
+//! `CW_IT_RPC_ENDPOINT`, so the same config file can be used against different nodes in CI.
+//! See [`config::CHAIN_CONFIG_ENV_OVERRIDES`] for the full list. Timeouts and retries are set in
+//! the `timeouts` section of the chain config.
+//!
+//! Instead of a mnemonic, the funding account can be loaded from a Cosmos SDK keyring directory
+//! or an armored private key file by setting `funding_account_key` in the config. This requires
+//! the `keyring` feature.
+//!
+//! By default transactions pay the fixed `gas_price` of the chain config. Set
+//! `gas_price_source` to query the current gas price from the chain instead, see
+//! [`gas_price::GasPriceSource`].
+pub mod amino;
 pub mod chain;
+pub mod chain_registry;
 pub mod config;
 pub mod error;
+pub mod gas_price;
 mod helpers;
+#[cfg(feature = "keyring")]
+pub mod keyring;
 mod runner;
+pub mod transport;
 
 pub use runner::*;
@@ -2,53 +2,84 @@ use std::num::ParseIntError;
 
 use anyhow::bail;
 
+use bip32::secp256k1::elliptic_curve::rand_core::{OsRng, RngCore};
 use cosmrs::crypto::secp256k1;
 use cosmrs::proto::cosmos::auth::v1beta1::BaseAccount;
 use cosmrs::proto::cosmos::auth::v1beta1::{QueryAccountRequest, QueryAccountResponse};
+use cosmrs::tendermint::block::Height;
 use cosmrs::tendermint::Time;
 use cosmwasm_std::{
     from_json, Coin, ContractResult, Empty, Querier, QuerierResult, QueryRequest, SystemResult,
-    WasmQuery,
+    Uint128, WasmQuery,
 };
+use osmosis_std::types::cosmos::bank::v1beta1::{
+    Input, MsgMultiSend, MsgMultiSendResponse, MsgSend, MsgSendResponse, Output,
+    QueryAllBalancesRequest, QueryAllBalancesResponse, QueryBalanceRequest, QueryBalanceResponse,
+};
+use osmosis_std::types::cosmos::base::v1beta1::Coin as ProtoCoin;
 use osmosis_std::types::cosmwasm::wasm::v1::{
-    QuerySmartContractStateRequest, QuerySmartContractStateResponse,
+    MsgStoreCode, MsgStoreCodeResponse, QuerySmartContractStateRequest,
+    QuerySmartContractStateResponse,
 };
+#[cfg(feature = "wasm-inspect")]
+use osmosis_std::types::cosmwasm::wasm::v1::{QueryCodeRequest, QueryCodeResponse};
 use test_tube::{
     account::FeeSetting, Account, DecodeError, EncodeError, Module, Runner, RunnerError,
     RunnerExecuteResult, RunnerResult, SigningAccount, Wasm,
 };
 
-use super::chain::Chain;
-use super::config::RpcRunnerConfig;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use super::amino::{self, AminoError};
+use super::chain::{Chain, ChainError};
+#[cfg(feature = "keyring")]
+use super::config::FundingAccountKey;
+use super::config::{ImportedAccount, RpcRunnerConfig};
 use super::error::RpcRunnerError;
 use super::helpers;
-use crate::helpers::{bank_send, block_on};
-use crate::traits::CwItRunner;
+use super::transport::TxResult;
+use crate::artifact::gzip;
+use crate::helpers::block_on;
+use crate::traits::{
+    initial_coins, AsyncCwItRunner, CwItRunner, SignMode, TxOptions, TxOptionsRunner,
+};
 use crate::ContractType;
 
-use cosmrs::rpc::endpoint::abci_query::AbciQuery;
-use cosmrs::rpc::endpoint::broadcast::tx_commit::Response as TxCommitResponse;
+use cosmrs::proto::cosmos::tx::v1beta1::TxRaw;
 use cosmrs::rpc::Client;
 use cosmrs::tx::{self, Raw};
-use cosmrs::tx::{Fee, SignerInfo};
+use cosmrs::tx::{Fee, ModeInfo, SignerInfo};
 use cosmrs::AccountId;
 use prost::Message;
+use serde::de::DeserializeOwned;
 
 pub struct RpcRunner {
     chain: Chain,
     funding_account: SigningAccount,
+    imported_accounts: HashMap<String, ImportedAccount>,
+    /// Transaction options set per signer address with `TxOptionsRunner::set_tx_options`.
+    tx_options: Mutex<HashMap<String, TxOptions>>,
+    /// Private keys of the accounts created by the runner, kept if `sweep_on_drop` is set.
+    created_accounts: Mutex<Vec<[u8; 32]>>,
     pub config: RpcRunnerConfig,
 }
 
+/// Fee amount, in the chain's fee denom, paid for every transaction sent by the runner.
+const TX_FEE_AMOUNT: u128 = 4_000_000;
+
+/// Gas limit of every transaction sent by the runner.
+const TX_GAS_LIMIT: u64 = 25_000_000;
+
 impl RpcRunner {
     pub fn new(rpc_runner_config: RpcRunnerConfig) -> Result<Self, RpcRunnerError> {
         // Setup chain and app
         let chain = Chain::new(rpc_runner_config.chain_config.clone())?;
 
-        let signing_key = helpers::mnemonic_to_signing_key(
-            &rpc_runner_config.funding_account_mnemonic,
-            &rpc_runner_config.chain_config.derivation_path.parse()?,
-        )?;
+        let signing_key = funding_account_signing_key(&rpc_runner_config)?;
 
         let funding_account = SigningAccount::new(
             rpc_runner_config.chain_config.prefix.clone(),
@@ -60,20 +91,102 @@ impl RpcRunner {
                 .into(),
         );
 
+        let imported_accounts = rpc_runner_config
+            .imported_accounts()?
+            .into_iter()
+            .map(|account| (account.name.clone(), account))
+            .collect();
+
         Ok(Self {
             chain,
             config: rpc_runner_config,
             funding_account,
+            imported_accounts,
+            tx_options: Mutex::new(HashMap::new()),
+            created_accounts: Mutex::new(Vec::new()),
         })
     }
+
+    /// Returns the fee setting to use for accounts created by this runner.
+    fn fee_setting(&self) -> FeeSetting {
+        self.config
+            .fee_setting
+            .clone()
+            .unwrap_or(self.chain.chain_cfg().auto_fee_setting())
+            .into()
+    }
+
+    /// Returns the chain this runner is connected to.
+    pub fn chain(&self) -> &Chain {
+        &self.chain
+    }
+
+    /// Returns the result of the transaction with the given hex encoded hash.
+    pub fn query_tx(&self, hash: &str) -> Result<TxResult, RpcRunnerError> {
+        Ok(block_on(self.chain.tx(hash))?)
+    }
+
+    /// Returns the results of all transactions matching the Tendermint event `query`, e.g.
+    /// `wasm._contract_address='osmo1...' AND wasm.action='increment'`.
+    pub fn search_txs(&self, query: &str) -> Result<Vec<TxResult>, RpcRunnerError> {
+        Ok(block_on(self.chain.search_txs(query))?)
+    }
+
+    /// Waits until a transaction matching the Tendermint event `query` is included in a block
+    /// and returns its result. See [`Chain::wait_for_tx`].
+    pub fn wait_for_tx(&self, query: &str, timeout: Duration) -> Result<TxResult, RpcRunnerError> {
+        Ok(block_on(self.chain.wait_for_tx(query, timeout))?)
+    }
+
+    /// Returns the names of all imported accounts.
+    pub fn account_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.imported_accounts.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Returns a `SigningAccount` for the imported account with the given name. The account is
+    /// derived from its mnemonic using its own derivation path, or the derivation path of the
+    /// chain config if it has none. Returns an error if the derived address does not match the
+    /// address stored in the config.
+    pub fn account(&self, name: &str) -> Result<SigningAccount, RpcRunnerError> {
+        let imported = self
+            .imported_accounts
+            .get(name)
+            .ok_or_else(|| RpcRunnerError::AccountNotFound(name.to_string()))?;
+
+        let derivation_path = imported
+            .derivation_path
+            .as_deref()
+            .unwrap_or(&self.config.chain_config.derivation_path);
+        let signing_key =
+            helpers::mnemonic_to_signing_key(&imported.mnemonic, &derivation_path.parse()?)?;
+
+        let account = SigningAccount::new(
+            self.chain.chain_cfg().prefix().to_string(),
+            signing_key,
+            self.fee_setting(),
+        );
+
+        if account.address() != imported.address {
+            return Err(RpcRunnerError::AccountAddressMismatch {
+                name: name.to_string(),
+                expected: imported.address.clone(),
+                actual: account.address(),
+            });
+        }
+
+        Ok(account)
+    }
 }
 
 impl Querier for RpcRunner {
     fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
         let x = match from_json::<QueryRequest<Empty>>(&bin_request).unwrap() {
             QueryRequest::Wasm(wasm_query) => match wasm_query {
-                WasmQuery::Smart { contract_addr, msg } => self
-                    .query::<_, QuerySmartContractStateResponse>(
+                WasmQuery::Smart { contract_addr, msg } => {
+                    Runner::query::<_, QuerySmartContractStateResponse>(
+                        self,
                         "/cosmwasm.wasm.v1.Query/SmartContractState",
                         &QuerySmartContractStateRequest {
                             address: contract_addr,
@@ -82,7 +195,8 @@ impl Querier for RpcRunner {
                     )
                     .unwrap()
                     .data
-                    .into(),
+                    .into()
+                }
                 _ => todo!("unsupported WasmQuery variant"),
             },
             _ => todo!("unsupported QueryRequest variant"),
@@ -93,80 +207,84 @@ impl Querier for RpcRunner {
 }
 
 impl RpcRunner {
-    fn create_signed_tx<I>(
+    async fn create_signed_tx(
         &self,
-        msgs: I,
+        msgs: Vec<cosmrs::Any>,
         signer: &SigningAccount,
         fee: Fee,
-    ) -> RunnerResult<Vec<u8>>
-    where
-        I: IntoIterator<Item = cosmrs::Any>,
-    {
-        // println!("create_signed_tx");
-        let account: BaseAccount = self.base_account(signer.account_id())?;
-        let tx_body = tx::Body::new(msgs, "MEMO", 0u32);
-
-        // println!("accountId -> {:?}", signer.account_id());
-        // println!("account -> {:?}", account);
-
-        let signer_info =
-            SignerInfo::single_direct(Some(signer.signing_key().public_key()), account.sequence);
-        let auth_info = signer_info.auth_info(fee);
-        let sign_doc = tx::SignDoc::new(
-            &tx_body,
-            &auth_info,
-            &self
-                .chain
-                .chain_cfg()
-                .chain_id
-                .parse()
-                .expect("parse const str of chain id should never fail"),
+        options: &TxOptions,
+    ) -> RunnerResult<Vec<u8>> {
+        let account: BaseAccount = self.base_account(signer.account_id()).await?;
+        sign_tx(
+            msgs,
+            signer,
+            fee,
+            options,
+            &self.chain.chain_cfg().chain_id,
             account.account_number,
+            account.sequence,
         )
-        .map_err(|e| match e.downcast::<prost::EncodeError>() {
-            Ok(encode_err) => EncodeError::ProtoEncodeError(encode_err),
-            Err(e) => panic!("expect `prost::EncodeError` but got {:?}", e),
-        })?;
-
-        let tx_raw: Raw = sign_doc.sign(signer.signing_key())?;
-
-        tx_raw
-            .to_bytes()
-            .map_err(|e| match e.downcast::<prost::EncodeError>() {
-                Ok(encode_err) => EncodeError::ProtoEncodeError(encode_err),
-                Err(e) => panic!("expect `prost::EncodeError` but got {:?}", e),
-            })
-            .map_err(RunnerError::EncodeError)
     }
 
-    #[allow(deprecated)]
-    fn simulate_tx<I>(
+    /// Returns the transaction options set for `signer`, or the default options if none are set.
+    fn tx_options_for(&self, signer: &SigningAccount) -> TxOptions {
+        self.tx_options
+            .lock()
+            .unwrap()
+            .get(&signer.address())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    async fn simulate_tx(
         &self,
-        _msgs: I,
-        _signer: &SigningAccount,
-    ) -> RunnerResult<cosmrs::proto::cosmos::base::abci::v1beta1::GasInfo>
-    where
-        I: IntoIterator<Item = cosmrs::Any>,
-    {
-        todo!()
+        msgs: Vec<cosmrs::Any>,
+        signer: &SigningAccount,
+        options: &TxOptions,
+    ) -> RunnerResult<cosmrs::proto::cosmos::base::abci::v1beta1::GasInfo> {
+        // The fee is not checked during simulation
+        let fee = Fee::from_amount_and_gas(
+            cosmrs::Coin {
+                denom: self.chain.chain_cfg().denom().parse()?,
+                amount: 0,
+            },
+            0u64,
+        );
+        let tx_raw = self.create_signed_tx(msgs, signer, fee, options).await?;
+
+        self.chain
+            .simulate_tx(tx_raw)
+            .await
+            .map_err(|e| RunnerError::GenericError(format!("simulation failed: {}", e)))
     }
 
-    fn estimate_fee<I>(&self, msgs: I, signer: &SigningAccount) -> RunnerResult<Fee>
-    where
-        I: IntoIterator<Item = cosmrs::Any>,
-    {
+    async fn estimate_fee(
+        &self,
+        msgs: Vec<cosmrs::Any>,
+        signer: &SigningAccount,
+        options: &TxOptions,
+    ) -> RunnerResult<Fee> {
         match &signer.fee_setting() {
             FeeSetting::Auto {
                 gas_price,
                 gas_adjustment,
             } => {
-                let gas_info = self.simulate_tx(msgs, signer)?;
+                let gas_info = self.simulate_tx(msgs, signer, options).await?;
                 let gas_limit = ((gas_info.gas_used as f64) * gas_adjustment).ceil() as u64;
 
+                let amount = if self.chain.chain_cfg().gas_price_source.is_dynamic() {
+                    let gas_price = self
+                        .chain
+                        .gas_price()
+                        .await
+                        .map_err(|e| RunnerError::GenericError(e.to_string()))?;
+                    Uint128::from(gas_limit).mul_ceil(gas_price).u128()
+                } else {
+                    ((gas_limit as f64) * (gas_price.amount.u128() as f64)).ceil() as u128
+                };
                 let amount = cosmrs::Coin {
                     denom: self.chain.chain_cfg().denom().parse()?,
-                    amount: (((gas_limit as f64) * (gas_price.amount.u128() as f64)).ceil() as u64)
-                        .into(),
+                    amount,
                 };
 
                 Ok(Fee::from_amount_and_gas(amount, gas_limit))
@@ -179,17 +297,17 @@ impl RpcRunner {
         }
     }
 
-    fn base_account(&self, account_id: AccountId) -> RunnerResult<BaseAccount> {
+    async fn base_account(&self, account_id: AccountId) -> RunnerResult<BaseAccount> {
         // TODO: find out a race here
-        let abci_query = self.abci_query(
-            QueryAccountRequest {
-                address: account_id.as_ref().into(),
-            },
-            "/cosmos.auth.v1beta1.Query/Account",
-        )?;
-
-        let res = QueryAccountResponse::decode(abci_query.value.as_slice())
-            .map_err(DecodeError::ProtoDecodeError)?
+        let res = self
+            .try_query_async::<_, QueryAccountResponse>(
+                "/cosmos.auth.v1beta1.Query/Account",
+                &QueryAccountRequest {
+                    address: account_id.as_ref().into(),
+                },
+            )
+            .await
+            .map_err(|e| RunnerError::QueryError { msg: e.to_string() })?
             .account
             .ok_or(RunnerError::QueryError {
                 msg: "account query failed".to_string(),
@@ -201,19 +319,402 @@ impl RpcRunner {
         Ok(base_account)
     }
 
-    fn abci_query<T: Message>(&self, req: T, path: &str) -> RunnerResult<AbciQuery> {
-        let mut buf = Vec::with_capacity(req.encoded_len());
-        req.encode(&mut buf)
-            .map_err(EncodeError::ProtoEncodeError)?;
-        Ok(block_on(self.chain.client().abci_query(
-            Some(path.to_string()),
-            buf,
-            None,
-            false,
-        ))?)
+    /// Same as [`Runner::query`], but returns an [`RpcRunnerError`] which preserves the ABCI
+    /// code, codespace and log of a failed query.
+    pub fn try_query<Q, R>(&self, path: &str, msg: &Q) -> Result<R, RpcRunnerError>
+    where
+        Q: ::prost::Message,
+        R: ::prost::Message + Default,
+    {
+        block_on(self.try_query_async(path, msg))
+    }
+
+    /// Async version of [`RpcRunner::try_query`].
+    pub async fn try_query_async<Q, R>(&self, path: &str, msg: &Q) -> Result<R, RpcRunnerError>
+    where
+        Q: ::prost::Message,
+        R: ::prost::Message + Default,
+    {
+        let res = self.chain.query_raw(path, msg.encode_to_vec()).await?;
+
+        R::decode(res.as_slice()).map_err(|e| ChainError::from(e).into())
+    }
+
+    /// Returns the time of the latest block in nanoseconds.
+    async fn block_time_nanos_async(&self) -> Result<u64, RpcRunnerError> {
+        let block = self
+            .chain
+            .client()
+            .latest_block()
+            .await
+            .map_err(ChainError::from)?
+            .block;
+        let time = block
+            .header
+            .time
+            .duration_since(Time::unix_epoch())
+            .map_err(|e| RpcRunnerError::Generic(e.to_string()))?;
+        Ok(time.as_nanos() as u64)
+    }
+
+    /// Returns the fee that a transaction with `msgs` signed by `signer` pays. This is the fee
+    /// estimated with the chain's gas price if the price is queried from the chain, otherwise
+    /// the fixed fee.
+    async fn tx_fee(
+        &self,
+        msgs: Vec<cosmrs::Any>,
+        signer: &SigningAccount,
+        options: &TxOptions,
+    ) -> RunnerResult<Fee> {
+        // TODO: Fix this, sadly estimation goes to the moon and provides no real value
+        // there must be a trick somewhere. The estimate is only used if the gas price is
+        // queried from the chain, since a fixed fee may be too low during congestion.
+        if !self.chain.chain_cfg().gas_price_source.is_dynamic() {
+            return Ok(Fee::from_amount_and_gas(
+                cosmrs::Coin {
+                    denom: self.chain.chain_cfg().denom().parse()?,
+                    amount: TX_FEE_AMOUNT,
+                },
+                TX_GAS_LIMIT,
+            ));
+        }
+
+        Ok(match &signer.fee_setting() {
+            FeeSetting::Auto { .. } => self.estimate_fee(msgs, signer, options).await?,
+            FeeSetting::Custom { amount, gas_limit } => Fee::from_amount_and_gas(
+                cosmrs::Coin {
+                    denom: amount.denom.parse()?,
+                    amount: amount
+                        .amount
+                        .to_string()
+                        .parse()
+                        .map_err(|e: ParseIntError| RunnerError::GenericError(e.to_string()))?,
+                },
+                *gas_limit,
+            ),
+        })
+    }
+
+    /// Async version of [`TxOptionsRunner::execute_multiple_raw_with_options`].
+    pub async fn execute_multiple_raw_with_options_async<R>(
+        &self,
+        msgs: Vec<cosmrs::Any>,
+        signer: &SigningAccount,
+        options: &TxOptions,
+    ) -> RunnerExecuteResult<R>
+    where
+        R: prost::Message + Default,
+    {
+        #[cfg(feature = "wasm-inspect")]
+        for msg in &msgs {
+            if let Some((code_id, entry_point)) =
+                crate::artifact::inspect::required_entry_point(&msg.type_url, &msg.value)
+            {
+                let res: Result<QueryCodeResponse, _> = self
+                    .try_query_async(
+                        "/cosmwasm.wasm.v1.Query/Code",
+                        &QueryCodeRequest { code_id },
+                    )
+                    .await;
+                if let Some(info) = res.ok().and_then(|res| res.code_info) {
+                    crate::artifact::inspect::warn_missing_entry_point(
+                        &info.data_hash,
+                        code_id,
+                        entry_point,
+                    );
+                }
+            }
+        }
+
+        let fee = self.tx_fee(msgs.clone(), signer, options).await?;
+
+        let tx_raw = self.create_signed_tx(msgs, signer, fee, options).await?;
+
+        self.chain
+            .broadcast_tx_commit(tx_raw)
+            .await
+            .and_then(TxResult::into_result)
+            .map_err(|e| RunnerError::ExecuteError { msg: e.to_string() })?
+            .into_execute_response()
+    }
+
+    /// Returns the balance of the chain's default funding, or [`initial_coins`] if none is
+    /// configured.
+    fn default_funding(&self) -> Vec<Coin> {
+        let default_funding = &self.chain.chain_cfg().default_funding;
+        if default_funding.is_empty() {
+            initial_coins()
+        } else {
+            default_funding.clone()
+        }
+    }
+
+    /// Creates a new account with a random key. The key is kept if `sweep_on_drop` is set, so
+    /// that the account can be swept later.
+    fn new_random_account(&self) -> SigningAccount {
+        let key = loop {
+            let mut key = [0u8; 32];
+            OsRng.fill_bytes(&mut key);
+            if secp256k1::SigningKey::from_slice(&key).is_ok() {
+                break key;
+            }
+        };
+        if self.config.sweep_on_drop {
+            self.created_accounts.lock().unwrap().push(key);
+        }
+        self.account_from_key(&key)
+    }
+
+    /// Returns the wasm file to upload for `wasm`, gzipped if `compress_uploads` is set.
+    fn upload_bytes(&self, wasm: Vec<u8>) -> std::io::Result<Vec<u8>> {
+        if self.config.compress_uploads && !gzip::is_gzipped(&wasm) {
+            gzip::compress(&wasm)
+        } else {
+            Ok(wasm)
+        }
+    }
+
+    fn account_from_key(&self, key: &[u8; 32]) -> SigningAccount {
+        SigningAccount::new(
+            self.chain.chain_cfg().prefix().to_string(),
+            secp256k1::SigningKey::from_slice(key).expect("key was validated on creation"),
+            self.fee_setting(),
+        )
+    }
+
+    /// Returns the balance of `address` in `denom`.
+    async fn balance(&self, address: &str, denom: &str) -> Result<Uint128, RpcRunnerError> {
+        let res: QueryBalanceResponse = self
+            .try_query_async(
+                "/cosmos.bank.v1beta1.Query/Balance",
+                &QueryBalanceRequest {
+                    address: address.to_string(),
+                    denom: denom.to_string(),
+                },
+            )
+            .await?;
+        res.balance
+            .map(|coin| Uint128::from_str(&coin.amount))
+            .transpose()
+            .map(Option::unwrap_or_default)
+            .map_err(|e| RpcRunnerError::Generic(e.to_string()))
+    }
+
+    /// Returns [`RpcRunnerError::InsufficientFunds`] if `address` has less than `required` of
+    /// any of the coins.
+    async fn check_balances(&self, address: &str, required: &[Coin]) -> Result<(), RpcRunnerError> {
+        for required in required {
+            let available = self.balance(address, &required.denom).await?;
+            if available < required.amount {
+                return Err(RpcRunnerError::InsufficientFunds {
+                    address: address.to_string(),
+                    denom: required.denom.clone(),
+                    required: required.amount,
+                    available,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends `coins` to each of `addresses` from the funding account in a single `MsgMultiSend`.
+    ///
+    /// Returns [`RpcRunnerError::InsufficientFunds`] without sending anything if the funding
+    /// account can't pay for all of the coins and the transaction fee.
+    pub fn fund_accounts(
+        &self,
+        addresses: &[String],
+        coins: &[Coin],
+    ) -> Result<(), RpcRunnerError> {
+        block_on(self.fund_accounts_async(addresses, coins))
+    }
+
+    /// Async version of [`RpcRunner::fund_accounts`].
+    pub async fn fund_accounts_async(
+        &self,
+        addresses: &[String],
+        coins: &[Coin],
+    ) -> Result<(), RpcRunnerError> {
+        if addresses.is_empty() || coins.is_empty() {
+            return Ok(());
+        }
+
+        let total = funding_total(coins, addresses.len())?;
+        let funder = self.funding_account.address();
+        // The fee is estimated by simulating the transaction, which fails if the funding account
+        // can't pay for the coins, so check the coins before the fee
+        self.check_balances(&funder, &total).await?;
+
+        let output_coins = to_proto_coins(&merge_coins(coins.iter().cloned()));
+        let msg = MsgMultiSend {
+            inputs: vec![Input {
+                address: funder.clone(),
+                coins: to_proto_coins(&total),
+            }],
+            outputs: addresses
+                .iter()
+                .map(|address| Output {
+                    address: address.clone(),
+                    coins: output_coins.clone(),
+                })
+                .collect(),
+        };
+        let msg = cosmrs::Any {
+            type_url: MsgMultiSend::TYPE_URL.to_string(),
+            value: msg.encode_to_vec(),
+        };
+        let options = self.tx_options_for(&self.funding_account);
+        let fee = self
+            .tx_fee(vec![msg.clone()], &self.funding_account, &options)
+            .await
+            .map_err(|e| RpcRunnerError::Generic(e.to_string()))?;
+        let required = merge_coins(total.into_iter().chain(fee_coins(&fee)));
+        self.check_balances(&funder, &required).await?;
+
+        self.execute_multiple_raw_with_options_async::<MsgMultiSendResponse>(
+            vec![msg],
+            &self.funding_account,
+            &options,
+        )
+        .await
+        .map_err(|e| RpcRunnerError::Generic(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Sends the remaining balances of all accounts created by the runner back to the funding
+    /// account. Enough of the fee denom to pay for the transaction is left in each account, and
+    /// accounts that can't pay the fee are skipped.
+    ///
+    /// Only accounts created while `sweep_on_drop` is set are swept. Swept accounts are forgotten
+    /// even if sweeping fails, and the first error is returned after all accounts were tried.
+    pub fn sweep_accounts(&self) -> Result<(), RpcRunnerError> {
+        let keys = std::mem::take(&mut *self.created_accounts.lock().unwrap());
+
+        let mut result = Ok(());
+        for key in keys {
+            let swept = self.sweep_account(&self.account_from_key(&key));
+            if result.is_ok() {
+                result = swept;
+            }
+        }
+        result
+    }
+
+    /// Sends the balances of `account` except for the transaction fee to the funding account.
+    fn sweep_account(&self, account: &SigningAccount) -> Result<(), RpcRunnerError> {
+        let balances = self
+            .try_query::<_, QueryAllBalancesResponse>(
+                "/cosmos.bank.v1beta1.Query/AllBalances",
+                &QueryAllBalancesRequest {
+                    address: account.address(),
+                    pagination: None,
+                },
+            )?
+            .balances;
+        if balances.is_empty() {
+            return Ok(());
+        }
+
+        let msg = |amount| MsgSend {
+            from_address: account.address(),
+            to_address: self.funding_account.address(),
+            amount,
+        };
+        // The fee of sending everything is the same as the fee of sending everything but the fee
+        let fee = block_on(self.tx_fee(
+            vec![cosmrs::Any {
+                type_url: MsgSend::TYPE_URL.to_string(),
+                value: msg(balances.clone()).encode_to_vec(),
+            }],
+            account,
+            &self.tx_options_for(account),
+        ))
+        .map_err(|e| RpcRunnerError::Generic(e.to_string()))?;
+        let Some(amount) = sweep_amount(balances, &fee_coins(&fee)) else {
+            return Ok(());
+        };
+
+        self.execute::<_, MsgSendResponse>(msg(amount), MsgSend::TYPE_URL, account)
+            .map(|_| ())
+            .map_err(|e| RpcRunnerError::Generic(e.to_string()))
     }
 }
 
+impl Drop for RpcRunner {
+    fn drop(&mut self) {
+        if self.config.sweep_on_drop {
+            // Errors can't be returned from `drop`, so sweeping is best effort
+            let _ = self.sweep_accounts();
+        }
+    }
+}
+
+/// Merges coins of the same denom and sorts them by denom, as required by the bank module.
+fn merge_coins(coins: impl IntoIterator<Item = Coin>) -> Vec<Coin> {
+    let mut merged = BTreeMap::<String, Uint128>::new();
+    for coin in coins {
+        *merged.entry(coin.denom).or_default() += coin.amount;
+    }
+    merged
+        .into_iter()
+        .map(|(denom, amount)| Coin { denom, amount })
+        .collect()
+}
+
+/// Returns the total of `coins` sent to each of `num_accounts` accounts.
+fn funding_total(coins: &[Coin], num_accounts: usize) -> Result<Vec<Coin>, RpcRunnerError> {
+    merge_coins(coins.iter().cloned())
+        .into_iter()
+        .map(|coin| {
+            let amount = coin
+                .amount
+                .checked_mul(Uint128::from(num_accounts as u128))
+                .map_err(|e| RpcRunnerError::Generic(e.to_string()))?;
+            Ok(Coin { amount, ..coin })
+        })
+        .collect()
+}
+
+/// Returns the coins to send back to the funding account from an account with `balances`, or
+/// `None` if the account can't pay the `fee` or has nothing left to send.
+fn sweep_amount(balances: Vec<ProtoCoin>, fee: &[Coin]) -> Option<Vec<ProtoCoin>> {
+    let mut unpaid_fee = fee.len();
+    let amount = balances
+        .into_iter()
+        .filter_map(|coin| {
+            let mut amount = coin.amount.parse::<u128>().ok()?;
+            if let Some(fee) = fee.iter().find(|fee| fee.denom == coin.denom) {
+                amount = amount.checked_sub(fee.amount.u128())?;
+                unpaid_fee -= 1;
+            }
+            (amount > 0).then(|| ProtoCoin {
+                denom: coin.denom,
+                amount: amount.to_string(),
+            })
+        })
+        .collect::<Vec<_>>();
+    (unpaid_fee == 0 && !amount.is_empty()).then_some(amount)
+}
+
+/// Returns the coins paid with `fee`.
+fn fee_coins(fee: &Fee) -> Vec<Coin> {
+    fee.amount
+        .iter()
+        .map(|coin| Coin::new(coin.amount, coin.denom.to_string()))
+        .collect()
+}
+
+fn to_proto_coins(coins: &[Coin]) -> Vec<ProtoCoin> {
+    coins
+        .iter()
+        .map(|coin| ProtoCoin {
+            denom: coin.denom.clone(),
+            amount: coin.amount.to_string(),
+        })
+        .collect()
+}
+
 impl Runner<'_> for RpcRunner {
     fn execute_multiple<M, R>(
         &self,
@@ -237,7 +738,7 @@ impl Runner<'_> for RpcRunner {
             })
             .collect::<Result<Vec<cosmrs::Any>, RunnerError>>()?;
 
-        self.execute_multiple_raw(encoded_msgs, signer)
+        Runner::execute_multiple_raw(self, encoded_msgs, signer)
     }
 
     fn execute_multiple_raw<R>(
@@ -248,47 +749,7 @@ impl Runner<'_> for RpcRunner {
     where
         R: prost::Message + Default,
     {
-        let _fee = match &signer.fee_setting() {
-            FeeSetting::Auto { .. } => self.estimate_fee(msgs.clone(), signer)?,
-            FeeSetting::Custom { amount, gas_limit } => Fee::from_amount_and_gas(
-                cosmrs::Coin {
-                    denom: amount.denom.parse()?,
-                    amount: amount
-                        .amount
-                        .to_string()
-                        .parse()
-                        .map_err(|e: ParseIntError| RunnerError::GenericError(e.to_string()))?,
-                },
-                *gas_limit,
-            ),
-        };
-
-        // TODO: Fix this, sadly estimation goes to the moon and provides no real value
-        // there must be a trick somewhere
-        let fee = Fee::from_amount_and_gas(
-            cosmrs::Coin {
-                denom: self.chain.chain_cfg().denom().parse()?,
-                amount: 4_000_000,
-            },
-            25_000_000u64,
-        );
-
-        let tx_raw = self.create_signed_tx(msgs, signer, fee)?;
-
-        let tx_commit_response: TxCommitResponse =
-            block_on(self.chain.client().broadcast_tx_commit(tx_raw))?;
-
-        if tx_commit_response.check_tx.code.is_err() {
-            return Err(RunnerError::ExecuteError {
-                msg: tx_commit_response.check_tx.log,
-            });
-        }
-        if tx_commit_response.tx_result.code.is_err() {
-            return Err(RunnerError::ExecuteError {
-                msg: tx_commit_response.tx_result.log,
-            });
-        }
-        tx_commit_response.try_into()
+        self.execute_multiple_raw_with_options(msgs, signer, &self.tx_options_for(signer))
     }
 
     fn query<Q, R>(&self, path: &str, msg: &Q) -> RunnerResult<R>
@@ -296,24 +757,8 @@ impl Runner<'_> for RpcRunner {
         Q: ::prost::Message,
         R: ::prost::Message + Default,
     {
-        let mut base64_query_msg_bytes = Vec::with_capacity(msg.encoded_len());
-        msg.encode(&mut base64_query_msg_bytes)
-            .map_err(EncodeError::ProtoEncodeError)?;
-
-        let res = block_on(self.chain.client().abci_query(
-            Some(path.to_string()),
-            base64_query_msg_bytes,
-            None,
-            false,
-        ))?;
-
-        if res.code != cosmrs::tendermint::abci::Code::Ok {
-            return Err(RunnerError::QueryError {
-                msg: "error".to_string(),
-            });
-        }
-
-        Ok(R::decode(res.value.as_slice()).map_err(DecodeError::ProtoDecodeError)?)
+        self.try_query(path, msg)
+            .map_err(|e| RunnerError::QueryError { msg: e.to_string() })
     }
 
     fn execute_tx(
@@ -333,36 +778,35 @@ impl<'a> CwItRunner<'a> for RpcRunner {
         match code {
             ContractType::Artifact(artifact) => {
                 let bytes = artifact.get_wasm_byte_code()?;
+                #[cfg(feature = "wasm-inspect")]
+                crate::artifact::inspect::record_stored_code(
+                    &bytes,
+                    crate::artifact::inspect::check_wasm(&bytes)?,
+                );
+                let bytes = self.upload_bytes(bytes)?;
                 let wasm = Wasm::new(self);
-                let code_id = wasm.store_code(&bytes, None, signer)?.data.code_id;
-                Ok(code_id)
+                Ok(wasm.store_code(&bytes, None, signer)?.data.code_id)
+            }
+            #[cfg(feature = "multi-test")]
+            ContractType::MultiTestContract(_) => {
+                bail!("Only ContractType::Artifact is supported for RpcRunner")
             }
-            _ => bail!("Only ContractType::Artifact is supported for RpcRunner"),
         }
     }
 
-    fn init_account(&self, initial_balance: &[Coin]) -> Result<SigningAccount, anyhow::Error> {
-        // Create new random account
-        let new_account = SigningAccount::new(
-            self.chain.chain_cfg().prefix().to_string(),
-            secp256k1::SigningKey::random(),
-            self.config
-                .fee_setting
-                .clone()
-                .unwrap_or(self.chain.chain_cfg().auto_fee_setting())
-                .into(),
-        );
+    fn init_default_accounts(&self) -> Result<Vec<SigningAccount>, anyhow::Error> {
+        self.init_accounts(&self.default_funding(), 10)
+    }
 
-        // Fund account with initial_balance from funding_account
-        bank_send(
-            self,
-            &self.funding_account,
-            &new_account.address(),
-            initial_balance.to_vec(),
-        )
-        .map_err(|e| anyhow::anyhow!("Funding of new account failed. Error: {}", e))?;
+    fn init_default_account(&self) -> Result<SigningAccount, anyhow::Error> {
+        CwItRunner::init_account(self, &self.default_funding())
+    }
 
-        Ok(new_account)
+    fn init_account(&self, initial_balance: &[Coin]) -> Result<SigningAccount, anyhow::Error> {
+        Ok(self
+            .init_accounts(initial_balance, 1)?
+            .pop()
+            .expect("one account was created"))
     }
 
     fn init_accounts(
@@ -370,56 +814,901 @@ impl<'a> CwItRunner<'a> for RpcRunner {
         initial_balance: &[Coin],
         num_accounts: usize,
     ) -> Result<Vec<SigningAccount>, anyhow::Error> {
-        let mut accounts = Vec::new();
-        for _ in 0..num_accounts {
-            accounts.push(self.init_account(initial_balance)?);
-        }
+        let accounts = (0..num_accounts)
+            .map(|_| self.new_random_account())
+            .collect::<Vec<_>>();
+
+        // Fund all accounts with initial_balance from funding_account in one transaction
+        let addresses = accounts.iter().map(|a| a.address()).collect::<Vec<_>>();
+        self.fund_accounts(&addresses, initial_balance)
+            .map_err(|e| anyhow::anyhow!("Funding of new accounts failed. Error: {}", e))?;
+
         Ok(accounts)
     }
 
-    fn increase_time(&self, _seconds: u64) -> Result<(), anyhow::Error> {
-        // TODO: Figure out best way to sleep tests until `seconds` has passed.
-        todo!("Increase time is unimplemented for RpcRunner")
+    /// Waits until a block at least `seconds` later than the current block has been produced,
+    /// see [`AsyncCwItRunner::increase_time`].
+    fn increase_time(&self, seconds: u64) -> Result<(), anyhow::Error> {
+        block_on(AsyncCwItRunner::increase_time(self, seconds))
     }
 
     fn query_block_time_nanos(&self) -> u64 {
-        block_on(self.chain.client().latest_block())
-            .unwrap()
-            .block
-            .header
-            .time
-            .duration_since(Time::unix_epoch())
+        block_on(self.block_time_nanos_async()).unwrap()
+    }
+}
+
+impl<'a> TxOptionsRunner<'a> for RpcRunner {
+    fn execute_multiple_raw_with_options<R>(
+        &self,
+        msgs: Vec<cosmrs::Any>,
+        signer: &SigningAccount,
+        options: &TxOptions,
+    ) -> RunnerExecuteResult<R>
+    where
+        R: prost::Message + Default,
+    {
+        block_on(self.execute_multiple_raw_with_options_async(msgs, signer, options))
+    }
+
+    fn set_tx_options(&self, signer: &SigningAccount, options: TxOptions) {
+        self.tx_options
+            .lock()
             .unwrap()
-            .as_nanos() as u64
+            .insert(signer.address(), options);
+    }
+
+    fn clear_tx_options(&self, signer: &SigningAccount) {
+        self.tx_options.lock().unwrap().remove(&signer.address());
+    }
+}
+
+impl<'a> AsyncCwItRunner<'a> for RpcRunner {
+    async fn execute_multiple_raw<R>(
+        &self,
+        msgs: Vec<cosmrs::Any>,
+        signer: &SigningAccount,
+    ) -> RunnerExecuteResult<R>
+    where
+        R: prost::Message + Default,
+    {
+        let options = self.tx_options_for(signer);
+        self.execute_multiple_raw_with_options_async(msgs, signer, &options)
+            .await
+    }
+
+    async fn query<Q, R>(&self, path: &str, query: &Q) -> RunnerResult<R>
+    where
+        Q: prost::Message,
+        R: prost::Message + DeserializeOwned + Default,
+    {
+        self.try_query_async(path, query)
+            .await
+            .map_err(|e| RunnerError::QueryError { msg: e.to_string() })
+    }
+
+    fn store_code(
+        &self,
+        code: ContractType,
+        signer: &SigningAccount,
+    ) -> impl Future<Output = Result<u64, anyhow::Error>> + Send {
+        // Read the code before the future is created, since `ContractType` may not be `Send`
+        let bytes = match code {
+            ContractType::Artifact(artifact) => {
+                artifact.get_wasm_byte_code().map_err(anyhow::Error::from)
+            }
+            #[cfg(feature = "multi-test")]
+            ContractType::MultiTestContract(_) => Err(anyhow::anyhow!(
+                "Only ContractType::Artifact is supported for RpcRunner"
+            )),
+        };
+        #[cfg(feature = "wasm-inspect")]
+        let bytes = bytes.and_then(|bytes| {
+            let info = crate::artifact::inspect::check_wasm(&bytes)?;
+            crate::artifact::inspect::record_stored_code(&bytes, info);
+            Ok(bytes)
+        });
+
+        async move {
+            let bytes = bytes?;
+            let msg = MsgStoreCode {
+                sender: signer.address(),
+                wasm_byte_code: self.upload_bytes(bytes)?,
+                instantiate_permission: None,
+            };
+            let res: MsgStoreCodeResponse = AsyncCwItRunner::execute_multiple_raw(
+                self,
+                vec![cosmrs::Any {
+                    type_url: MsgStoreCode::TYPE_URL.to_string(),
+                    value: msg.encode_to_vec(),
+                }],
+                signer,
+            )
+            .await?
+            .data;
+            Ok(res.code_id)
+        }
+    }
+
+    async fn init_account(
+        &self,
+        initial_balance: &[Coin],
+    ) -> Result<SigningAccount, anyhow::Error> {
+        let account = self.new_random_account();
+        self.fund_accounts_async(&[account.address()], initial_balance)
+            .await
+            .map_err(|e| anyhow::anyhow!("Funding of new accounts failed. Error: {}", e))?;
+        Ok(account)
+    }
+
+    /// The time of a real chain can't be changed, so this waits until a block at least `seconds`
+    /// later than the current block has been produced.
+    async fn increase_time(&self, seconds: u64) -> Result<(), anyhow::Error> {
+        let target = Duration::from_nanos(self.block_time_nanos_async().await?)
+            + Duration::from_secs(seconds);
+        let timeouts = &self.chain.chain_cfg().timeouts;
+        let deadline =
+            tokio::time::Instant::now() + Duration::from_secs(seconds) + timeouts.block_timeout();
+
+        while Duration::from_nanos(self.block_time_nanos_async().await?) < target {
+            if tokio::time::Instant::now() > deadline {
+                bail!(ChainError::Timeout(format!(
+                    "no block {}s after the current block was produced",
+                    seconds
+                )));
+            }
+            tokio::time::sleep(timeouts.poll_interval()).await;
+        }
+        Ok(())
     }
 }
 
-// Commenting out RPC tests so that CI doesn't break randomly when the RPC endpoint is down
-// #[cfg(test)]
-// mod test {
-//     use crate::rpc_runner::{chain::ChainConfig, config::RpcRunnerConfig, RpcRunner};
-//     use crate::traits::CwItRunner;
-
-//     #[test]
-//     fn test_query_block_time_nanos() {
-//         let rpc_runner_config = RpcRunnerConfig {
-//             accounts_folder: "".to_string(),
-//             chain_config: ChainConfig {
-//                 chain_id: "pion-1".to_string(),
-//                 derivation_path: "m/44'/1'/0'/0/0".to_string(),
-//                 gas_adjustment: 1.5,
-//                 gas_price: 0,
-//                 grpc_endpoint: "http://grpc-palvus.pion-1.ntrn.tech:80".to_string(),
-//                 rpc_endpoint: "https://rpc-palvus.pion-1.ntrn.tech:443".to_string(),
-//                 name: "pion-1".to_string(),
-//                 denom: "ntrn".to_string(),
-//                 prefix: "neutron".to_string(),
-//             },
-//             container: None,
-//         };
-//         let rpc_runner = RpcRunner::new(rpc_runner_config, None).unwrap();
-//         let block_time_nanos = rpc_runner.query_block_time_nanos();
-//         println!("block_time_nanos: {}", block_time_nanos);
-//         assert!(block_time_nanos > 1683910796000000000);
-//     }
-// }
+/// Loads the funding account key from `funding_account_key` if set, and from
+/// `funding_account_mnemonic` otherwise.
+fn funding_account_signing_key(
+    config: &RpcRunnerConfig,
+) -> Result<secp256k1::SigningKey, RpcRunnerError> {
+    match &config.funding_account_key {
+        #[cfg(feature = "keyring")]
+        Some(FundingAccountKey::Keyring(keyring)) => Ok(keyring.signing_key()?),
+        #[cfg(feature = "keyring")]
+        Some(FundingAccountKey::ArmoredKey(armored_key)) => Ok(armored_key.signing_key()?),
+        #[cfg(not(feature = "keyring"))]
+        Some(_) => Err(RpcRunnerError::Generic(
+            "funding_account_key requires the `keyring` feature".to_string(),
+        )),
+        None => Ok(helpers::mnemonic_to_signing_key(
+            &config.funding_account_mnemonic,
+            &config.chain_config.derivation_path.parse()?,
+        )?),
+    }
+}
+
+/// Builds a transaction with the given messages, fee and options and signs it with `signer`
+/// using the sign mode in `options`. Returns the encoded `TxRaw`.
+fn sign_tx<I>(
+    msgs: I,
+    signer: &SigningAccount,
+    mut fee: Fee,
+    options: &TxOptions,
+    chain_id: &str,
+    account_number: u64,
+    sequence: u64,
+) -> RunnerResult<Vec<u8>>
+where
+    I: IntoIterator<Item = cosmrs::Any>,
+{
+    let timeout_height = Height::try_from(options.timeout_height)
+        .map_err(|e| RunnerError::GenericError(e.to_string()))?;
+    let mut tx_body = tx::Body::new(msgs, options.memo.clone(), timeout_height);
+    tx_body.non_critical_extension_options = options.non_critical_extension_options.clone();
+
+    fee.payer = options
+        .fee_payer
+        .as_deref()
+        .map(str::parse::<AccountId>)
+        .transpose()
+        .map_err(|e| RunnerError::GenericError(format!("invalid fee payer: {}", e)))?;
+    // The chain requires the fee payer to sign the transaction, and only the signer does
+    if let Some(payer) = &fee.payer {
+        if *payer != signer.account_id() {
+            return Err(RunnerError::GenericError(format!(
+                "fee payer {} is not the signer {}; transactions signed by a separate fee payer \
+                 are not supported, use a fee granter instead",
+                payer,
+                signer.account_id()
+            )));
+        }
+    }
+    fee.granter = options
+        .fee_granter
+        .as_deref()
+        .map(str::parse::<AccountId>)
+        .transpose()
+        .map_err(|e| RunnerError::GenericError(format!("invalid fee granter: {}", e)))?;
+
+    match options.sign_mode {
+        SignMode::Direct => {
+            let signer_info =
+                SignerInfo::single_direct(Some(signer.signing_key().public_key()), sequence);
+            let auth_info = signer_info.auth_info(fee);
+            let sign_doc = tx::SignDoc::new(
+                &tx_body,
+                &auth_info,
+                &chain_id
+                    .parse()
+                    .expect("parse const str of chain id should never fail"),
+                account_number,
+            )
+            .map_err(EncodeError::from_proto_error_report)?;
+
+            let tx_raw: Raw = sign_doc.sign(signer.signing_key())?;
+
+            Ok(tx_raw
+                .to_bytes()
+                .map_err(EncodeError::from_proto_error_report)?)
+        }
+        SignMode::LegacyAminoJson => {
+            if !tx_body.extension_options.is_empty()
+                || !tx_body.non_critical_extension_options.is_empty()
+            {
+                return Err(RunnerError::GenericError(
+                    AminoError::ExtensionOptions.to_string(),
+                ));
+            }
+
+            let sign_doc = amino::std_sign_doc(
+                chain_id,
+                account_number,
+                sequence,
+                &fee,
+                &tx_body.memo,
+                options.timeout_height,
+                &tx_body.messages,
+            )
+            .map_err(|e| RunnerError::GenericError(e.to_string()))?;
+            let signature = signer.signing_key().sign(&amino::sign_bytes(&sign_doc))?;
+
+            let signer_info = SignerInfo {
+                public_key: Some(signer.signing_key().public_key().into()),
+                mode_info: ModeInfo::single(tx::SignMode::LegacyAminoJson),
+                sequence,
+            };
+            let auth_info = signer_info.auth_info(fee);
+
+            Ok(TxRaw {
+                body_bytes: tx_body
+                    .into_bytes()
+                    .map_err(EncodeError::from_proto_error_report)?,
+                auth_info_bytes: auth_info
+                    .into_bytes()
+                    .map_err(EncodeError::from_proto_error_report)?,
+                signatures: vec![signature.to_bytes().to_vec()],
+            }
+            .encode_to_vec())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmrs::tx::{Fee, Tx};
+    use test_tube::Account;
+
+    use cosmwasm_std::coin;
+
+    use super::{
+        amino, fee_coins, funding_total, gzip, merge_coins, sign_tx, sweep_amount, ProtoCoin,
+    };
+    use crate::rpc_runner::{
+        chain::ChainConfig,
+        config::{ImportedAccount, RpcRunnerConfig},
+        error::RpcRunnerError,
+        RpcRunner,
+    };
+    use crate::traits::{SignMode, TxOptions, TxOptionsRunner};
+
+    const MNEMONIC: &str = "bottom loan skill merry east cradle onion journey palm apology verb edit desert impose absurd oil bubble sweet glove shallow size build burst effort";
+    const ADDRESS: &str = "osmo12smx2wdlyttvyzvzg54y2vnqwq2qjateuf7thj";
+    const GRANTER: &str = "osmo1cyyzpxplxdzkeea7kwsydadg87357qnahakaks";
+
+    /// Creates an `RpcRunner` pointing to a local node. No requests are made to the node when
+    /// creating the runner or deriving imported accounts.
+    fn rpc_runner(accounts: Vec<ImportedAccount>) -> RpcRunner {
+        rpc_runner_at("http://localhost:26657", accounts)
+    }
+
+    /// Creates an `RpcRunner` for the node at `rpc_endpoint`, funded by [`ADDRESS`].
+    fn rpc_runner_at(rpc_endpoint: &str, accounts: Vec<ImportedAccount>) -> RpcRunner {
+        RpcRunner::new(RpcRunnerConfig {
+            chain_config: ChainConfig {
+                name: "osmosis".to_string(),
+                denom: "uosmo".to_string(),
+                prefix: "osmo".to_string(),
+                chain_id: "localosmosis".to_string(),
+                gas_price: cosmwasm_std::Decimal::one(),
+                gas_price_source: Default::default(),
+                gas_adjustment: 1.2,
+                derivation_path: "m/44'/118'/0'/0/0".to_string(),
+                rpc_endpoint: rpc_endpoint.to_string(),
+                grpc_endpoint: "http://localhost:9090".to_string(),
+                websocket_endpoint: None,
+                transport: Default::default(),
+                timeouts: Default::default(),
+                default_funding: vec![],
+            },
+            funding_account_mnemonic: MNEMONIC.to_string(),
+            funding_account_key: None,
+            fee_setting: None,
+            accounts,
+            accounts_file: None,
+            sweep_on_drop: false,
+            compress_uploads: false,
+        })
+        .unwrap()
+    }
+
+    fn imported_account(name: &str, address: &str) -> ImportedAccount {
+        ImportedAccount {
+            name: name.to_string(),
+            address: address.to_string(),
+            mnemonic: MNEMONIC.to_string(),
+            pubkey: "".to_string(),
+            derivation_path: None,
+        }
+    }
+
+    #[test]
+    fn imported_account_by_name() {
+        let runner = rpc_runner(vec![
+            imported_account("admin", ADDRESS),
+            imported_account("keeper", ADDRESS),
+        ]);
+
+        assert_eq!(runner.account_names(), vec!["admin", "keeper"]);
+        assert_eq!(runner.account("admin").unwrap().address(), ADDRESS);
+        assert!(matches!(
+            runner.account("user").err().unwrap(),
+            RpcRunnerError::AccountNotFound(name) if name == "user"
+        ));
+    }
+
+    #[test]
+    fn imported_account_address_mismatch() {
+        let mut account = imported_account("admin", ADDRESS);
+        account.derivation_path = Some("m/44'/118'/0'/0/1".to_string());
+        let runner = rpc_runner(vec![account]);
+
+        match runner.account("admin").err().unwrap() {
+            RpcRunnerError::AccountAddressMismatch {
+                name,
+                expected,
+                actual,
+            } => {
+                assert_eq!(name, "admin");
+                assert_eq!(expected, ADDRESS);
+                assert_ne!(actual, ADDRESS);
+            }
+            err => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[test]
+    fn sign_tx_with_options() {
+        let runner = rpc_runner(vec![imported_account("admin", ADDRESS)]);
+        let signer = runner.account("admin").unwrap();
+        let extension = cosmrs::Any {
+            type_url: "/test.Extension".to_string(),
+            value: vec![1, 2, 3],
+        };
+        let options = TxOptions::default()
+            .memo("ics20 hook memo")
+            .timeout_height(1234)
+            .fee_granter(GRANTER)
+            .non_critical_extension_option(extension.clone());
+        let fee = Fee::from_amount_and_gas(
+            cosmrs::Coin {
+                denom: "uosmo".parse().unwrap(),
+                amount: 1000,
+            },
+            200_000u64,
+        );
+
+        let tx_bytes = sign_tx(vec![], &signer, fee, &options, "localosmosis", 1, 7).unwrap();
+        let tx = Tx::from_bytes(&tx_bytes).unwrap();
+
+        assert_eq!(tx.body.memo, "ics20 hook memo");
+        assert_eq!(tx.body.timeout_height.value(), 1234);
+        assert_eq!(tx.body.non_critical_extension_options, vec![extension]);
+        assert_eq!(tx.auth_info.fee.granter.unwrap().to_string(), GRANTER);
+        assert_eq!(tx.auth_info.fee.payer, None);
+        assert_eq!(tx.auth_info.signer_infos[0].sequence, 7);
+    }
+
+    #[test]
+    fn sign_tx_invalid_fee_payer() {
+        let runner = rpc_runner(vec![imported_account("admin", ADDRESS)]);
+        let signer = runner.account("admin").unwrap();
+        let fee = Fee::from_amount_and_gas(
+            cosmrs::Coin {
+                denom: "uosmo".parse().unwrap(),
+                amount: 1000,
+            },
+            200_000u64,
+        );
+
+        let err = sign_tx(
+            vec![],
+            &signer,
+            fee,
+            &TxOptions::default().fee_payer("not an address"),
+            "localosmosis",
+            1,
+            7,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("invalid fee payer"));
+    }
+
+    #[test]
+    fn sign_tx_fee_payer_must_be_signer() {
+        let runner = rpc_runner(vec![imported_account("admin", ADDRESS)]);
+        let signer = runner.account("admin").unwrap();
+        let fee = || {
+            Fee::from_amount_and_gas(
+                cosmrs::Coin {
+                    denom: "uosmo".parse().unwrap(),
+                    amount: 1000,
+                },
+                200_000u64,
+            )
+        };
+
+        let err = sign_tx(
+            vec![],
+            &signer,
+            fee(),
+            &TxOptions::default().fee_payer(GRANTER),
+            "localosmosis",
+            1,
+            7,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("is not the signer"));
+
+        let tx_bytes = sign_tx(
+            vec![],
+            &signer,
+            fee(),
+            &TxOptions::default().memo("memo").fee_payer(ADDRESS),
+            "localosmosis",
+            1,
+            7,
+        )
+        .unwrap();
+        let tx = Tx::from_bytes(&tx_bytes).unwrap();
+        assert_eq!(tx.auth_info.fee.payer.unwrap().to_string(), ADDRESS);
+    }
+
+    #[test]
+    fn sign_tx_legacy_amino_json() {
+        use bip32::secp256k1::ecdsa::signature::Verifier;
+        use cosmrs::crypto::secp256k1::{Signature, VerifyingKey};
+        use cosmrs::proto::cosmos::bank::v1beta1::MsgSend;
+        use cosmrs::tx::{ModeInfo, SignMode as ProtoSignMode};
+        use prost::Message;
+
+        let runner = rpc_runner(vec![imported_account("admin", ADDRESS)]);
+        let signer = runner.account("admin").unwrap();
+        let msgs = vec![cosmrs::Any {
+            type_url: "/cosmos.bank.v1beta1.MsgSend".to_string(),
+            value: MsgSend {
+                from_address: ADDRESS.to_string(),
+                to_address: GRANTER.to_string(),
+                amount: vec![],
+            }
+            .encode_to_vec(),
+        }];
+        let fee = Fee::from_amount_and_gas(
+            cosmrs::Coin {
+                denom: "uosmo".parse().unwrap(),
+                amount: 1000,
+            },
+            200_000u64,
+        );
+        let options = TxOptions::default()
+            .memo("amino")
+            .sign_mode(SignMode::LegacyAminoJson);
+
+        let tx_bytes = sign_tx(
+            msgs.clone(),
+            &signer,
+            fee.clone(),
+            &options,
+            "localosmosis",
+            1,
+            7,
+        )
+        .unwrap();
+        let tx = Tx::from_bytes(&tx_bytes).unwrap();
+
+        assert_eq!(
+            tx.auth_info.signer_infos[0].mode_info,
+            ModeInfo::single(ProtoSignMode::LegacyAminoJson)
+        );
+
+        let sign_doc = amino::std_sign_doc("localosmosis", 1, 7, &fee, "amino", 0, &msgs).unwrap();
+        let verifying_key =
+            VerifyingKey::from_sec1_bytes(&signer.signing_key().public_key().to_bytes()).unwrap();
+        let signature = Signature::try_from(tx.signatures[0].as_slice()).unwrap();
+        verifying_key
+            .verify(&amino::sign_bytes(&sign_doc), &signature)
+            .unwrap();
+
+        // Extension options can't be signed in amino JSON mode
+        let err = sign_tx(
+            msgs,
+            &signer,
+            fee,
+            &options.non_critical_extension_option(cosmrs::Any::default()),
+            "localosmosis",
+            1,
+            7,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("extension options"));
+    }
+
+    #[test]
+    fn tx_options_per_signer() {
+        let runner = rpc_runner(vec![imported_account("admin", ADDRESS)]);
+        let signer = runner.account("admin").unwrap();
+        assert_eq!(runner.tx_options_for(&signer), TxOptions::default());
+
+        let options = TxOptions::default().memo("per signer");
+        runner.set_tx_options(&signer, options.clone());
+        assert_eq!(runner.tx_options_for(&signer), options);
+
+        runner.clear_tx_options(&signer);
+        assert_eq!(runner.tx_options_for(&signer), TxOptions::default());
+    }
+
+    #[test]
+    fn funding_total_merges_and_sorts_coins() {
+        let coins = vec![coin(10, "uosmo"), coin(5, "uatom"), coin(1, "uosmo")];
+
+        assert_eq!(
+            merge_coins(coins.clone()),
+            vec![coin(5, "uatom"), coin(11, "uosmo")]
+        );
+        assert_eq!(
+            funding_total(&coins, 10).unwrap(),
+            vec![coin(50, "uatom"), coin(110, "uosmo")]
+        );
+        assert!(funding_total(&[coin(u128::MAX, "uosmo")], 2).is_err());
+    }
+
+    #[test]
+    fn sweep_amount_keeps_fee() {
+        let proto_coin = |amount: u128, denom: &str| ProtoCoin {
+            denom: denom.to_string(),
+            amount: amount.to_string(),
+        };
+        let fee = [coin(4_000_000, "uosmo")];
+
+        assert_eq!(
+            sweep_amount(
+                vec![proto_coin(5, "uatom"), proto_coin(5_000_000, "uosmo")],
+                &fee
+            ),
+            Some(vec![proto_coin(5, "uatom"), proto_coin(1_000_000, "uosmo")])
+        );
+        assert_eq!(
+            sweep_amount(vec![proto_coin(4_000_000, "uosmo")], &fee),
+            None
+        );
+        assert_eq!(
+            sweep_amount(vec![proto_coin(5, "uatom"), proto_coin(1, "uosmo")], &fee),
+            None
+        );
+        // Accounts without the fee denom can't pay the fee
+        assert_eq!(sweep_amount(vec![proto_coin(5, "uatom")], &fee), None);
+    }
+
+    #[test]
+    fn fee_coins_of_fee() {
+        let fee = Fee::from_amount_and_gas(
+            cosmrs::Coin {
+                denom: "uosmo".parse().unwrap(),
+                amount: 1000,
+            },
+            200_000u64,
+        );
+        assert_eq!(fee_coins(&fee), vec![coin(1000, "uosmo")]);
+    }
+
+    #[test]
+    fn accounts_kept_for_sweep_on_drop() {
+        let mut runner = rpc_runner(vec![]);
+        runner.new_random_account();
+        assert!(runner.created_accounts.lock().unwrap().is_empty());
+
+        runner.config.sweep_on_drop = true;
+        let account = runner.new_random_account();
+        let keys = std::mem::take(&mut *runner.created_accounts.lock().unwrap());
+        assert_eq!(keys.len(), 1);
+        assert_eq!(
+            runner.account_from_key(&keys[0]).address(),
+            account.address()
+        );
+    }
+
+    #[test]
+    fn compress_uploads() {
+        let mut runner = rpc_runner(vec![]);
+        let wasm = std::fs::read("artifacts/counter.wasm").unwrap();
+        assert_eq!(runner.upload_bytes(wasm.clone()).unwrap(), wasm);
+
+        runner.config.compress_uploads = true;
+        let uploaded = runner.upload_bytes(wasm.clone()).unwrap();
+        assert!(uploaded.len() < wasm.len());
+        assert_eq!(gzip::decompress(uploaded.clone()).unwrap(), wasm);
+
+        // Already gzipped files are not compressed again
+        assert_eq!(runner.upload_bytes(uploaded.clone()).unwrap(), uploaded);
+    }
+
+    /// Tests against a local RPC facade backed by a `MultiTestRunner` instead of a public
+    /// endpoint, which would make CI fail whenever it is down.
+    #[cfg(all(feature = "rpc-facade", feature = "multi-test"))]
+    mod facade {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::thread;
+
+        use apollo_cw_multi_test::BankSudo;
+        use cosmrs::rpc::Client;
+        use cosmrs::tendermint::abci::types::ExecTxResult;
+        use cosmrs::tendermint::Hash;
+        use cosmwasm_std::{coin, Addr};
+        use osmosis_std::types::cosmos::bank::v1beta1::{MsgSend, QueryBalanceRequest};
+        use sha2::{Digest, Sha256};
+        use test_tube::{Account, Bank, Module};
+
+        use super::{rpc_runner_at, ADDRESS};
+        use crate::helpers::{block_on, instantiate2_contract, predict_address};
+        use crate::multi_test::MultiTestRunner;
+        use crate::rpc_facade::{AbciError, FacadeBackend, MultiTestBackend, RpcFacade};
+        use crate::rpc_runner::error::RpcRunnerError;
+        use crate::rpc_runner::runner::TX_FEE_AMOUNT;
+        use crate::test_helpers::test_contract;
+        use crate::traits::CwItRunner;
+
+        fn backend() -> MultiTestBackend<'static> {
+            let backend = MultiTestBackend::new(MultiTestRunner::new("osmo"));
+            backend
+                .runner
+                .app
+                .sudo(
+                    BankSudo::Mint {
+                        to_address: ADDRESS.to_string(),
+                        amount: vec![coin(1_000_000_000_000, "uosmo")],
+                    }
+                    .into(),
+                )
+                .unwrap();
+            backend
+        }
+
+        fn start_facade() -> RpcFacade<MultiTestBackend<'static>> {
+            RpcFacade::start("localosmosis", backend).unwrap()
+        }
+
+        /// A backend failing every simulation.
+        struct NoSimulation(MultiTestBackend<'static>);
+
+        impl FacadeBackend for NoSimulation {
+            fn abci_query(&mut self, path: &str, data: &[u8]) -> Result<Vec<u8>, AbciError> {
+                self.0.abci_query(path, data)
+            }
+
+            fn deliver_tx(&mut self, tx: &[u8]) -> ExecTxResult {
+                self.0.deliver_tx(tx)
+            }
+
+            fn block_time_nanos(&self) -> u64 {
+                self.0.block_time_nanos()
+            }
+
+            fn simulate_tx(&mut self, _tx: &[u8]) -> Result<u64, AbciError> {
+                Err(AbciError::internal("unexpected simulation"))
+            }
+        }
+
+        #[test]
+        fn query_block_time_nanos() {
+            let facade = start_facade();
+            let runner = rpc_runner_at(&facade.endpoint(), vec![]);
+
+            let block_time = facade
+                .with_backend(|backend| backend.runner.query_block_time_nanos())
+                .unwrap();
+            assert_eq!(runner.query_block_time_nanos(), block_time);
+        }
+
+        #[test]
+        fn fund_accounts_and_send() {
+            let facade = start_facade();
+            let runner = rpc_runner_at(&facade.endpoint(), vec![]);
+
+            let accounts = runner
+                .init_accounts(&[coin(10_000_000, "uosmo")], 2)
+                .unwrap();
+            let (alice, bob) = (&accounts[0], &accounts[1]);
+
+            let bank = Bank::new(&runner);
+            bank.send(
+                MsgSend {
+                    from_address: alice.address(),
+                    to_address: bob.address(),
+                    amount: vec![coin(1_000, "uosmo").into()],
+                },
+                alice,
+            )
+            .unwrap();
+
+            let balance = bank
+                .query_balance(&QueryBalanceRequest {
+                    address: bob.address(),
+                    denom: "uosmo".to_string(),
+                })
+                .unwrap()
+                .balance
+                .unwrap();
+            assert_eq!(balance.amount, "10001000");
+
+            // The send is the only transaction of the latest block
+            let block = block_on(runner.chain().client().latest_block())
+                .unwrap()
+                .block;
+            let hash = Hash::Sha256(Sha256::digest(&block.data[0]).into());
+            let tx = runner.query_tx(&hash.to_string()).unwrap();
+            assert!(tx.is_ok());
+            assert_eq!(tx.height, block.header.height.value());
+            assert!(tx.events.iter().any(|e| e.ty == "transfer"));
+
+            // Messages must be sent by the signer of the transaction
+            let err = bank
+                .send(
+                    MsgSend {
+                        from_address: alice.address(),
+                        to_address: bob.address(),
+                        amount: vec![coin(1_000, "uosmo").into()],
+                    },
+                    bob,
+                )
+                .unwrap_err();
+            assert!(err.to_string().contains("but the transaction is signed by"));
+        }
+
+        #[test]
+        fn increase_time_waits_for_later_block() {
+            let facade = start_facade();
+            let runner = rpc_runner_at(&facade.endpoint(), vec![]);
+            let start = runner.query_block_time_nanos();
+
+            // Blocks are only produced by transactions, so send them from another thread
+            let done = AtomicBool::new(false);
+            let res = thread::scope(|s| {
+                s.spawn(|| {
+                    let alice = runner.init_account(&[coin(10_000_000, "uosmo")]).unwrap();
+                    while !done.load(Ordering::Relaxed) {
+                        Bank::new(&runner)
+                            .send(
+                                MsgSend {
+                                    from_address: alice.address(),
+                                    to_address: ADDRESS.to_string(),
+                                    amount: vec![coin(1, "uosmo").into()],
+                                },
+                                &alice,
+                            )
+                            .unwrap();
+                    }
+                });
+                let res = runner.increase_time(20);
+                done.store(true, Ordering::Relaxed);
+                res
+            });
+
+            res.unwrap();
+            assert!(runner.query_block_time_nanos() >= start + 20_000_000_000);
+        }
+
+        #[test]
+        fn fixed_fee_is_not_simulated() {
+            let facade = RpcFacade::start("localosmosis", || NoSimulation(backend())).unwrap();
+            let runner = rpc_runner_at(&facade.endpoint(), vec![]);
+
+            runner.init_account(&[coin(10_000_000, "uosmo")]).unwrap();
+        }
+
+        #[test]
+        fn fund_accounts_checks_fee() {
+            let facade = start_facade();
+            let runner = rpc_runner_at(&facade.endpoint(), vec![]);
+            let account = runner.init_account(&[]).unwrap();
+
+            // The funding account has exactly the coins to send, but not the fee
+            match runner
+                .fund_accounts(&[account.address()], &[coin(1_000_000_000_000, "uosmo")])
+                .unwrap_err()
+            {
+                RpcRunnerError::InsufficientFunds {
+                    denom,
+                    required,
+                    available,
+                    ..
+                } => {
+                    assert_eq!(denom, "uosmo");
+                    assert_eq!(required.u128(), 1_000_000_000_000 + TX_FEE_AMOUNT);
+                    assert_eq!(available.u128(), 1_000_000_000_000);
+                }
+                err => panic!("unexpected error: {}", err),
+            }
+        }
+
+        #[test]
+        fn sweep_accounts_keeps_fee() {
+            let facade = start_facade();
+            let mut runner = rpc_runner_at(&facade.endpoint(), vec![]);
+            runner.config.sweep_on_drop = true;
+            let account = runner.init_account(&[coin(10_000_000, "uosmo")]).unwrap();
+
+            runner.sweep_accounts().unwrap();
+
+            let address = account.address();
+            let balances = facade
+                .with_backend(move |backend| {
+                    backend
+                        .runner
+                        .app
+                        .wrap()
+                        .query_all_balances(address)
+                        .unwrap()
+                })
+                .unwrap();
+            assert_eq!(balances, vec![coin(TX_FEE_AMOUNT, "uosmo")]);
+        }
+
+        #[test]
+        fn instantiate2_at_predicted_address() {
+            let facade = start_facade();
+            let runner = rpc_runner_at(&facade.endpoint(), vec![]);
+            let alice = runner.init_account(&[coin(10_000_000, "uosmo")]).unwrap();
+            let code_id = facade
+                .with_backend(|backend| backend.runner.app.store_code(test_contract::contract()))
+                .unwrap();
+
+            let predicted = predict_address(&runner, code_id, &alice.address(), b"salt").unwrap();
+            let address: String = instantiate2_contract(
+                &runner,
+                &alice,
+                code_id,
+                &test_contract::EmptyMsg {},
+                b"salt",
+                &[],
+            )
+            .unwrap();
+            assert_eq!(address, predicted);
+
+            let contract = facade
+                .with_backend(move |backend| {
+                    backend
+                        .runner
+                        .app
+                        .contract_data(&Addr::unchecked(address))
+                        .unwrap()
+                })
+                .unwrap();
+            assert_eq!(contract.code_id, code_id as usize);
+        }
+    }
+}
@@ -0,0 +1,466 @@
+use cosmrs::proto::cosmos::base::abci::v1beta1::{GasInfo, TxMsgData};
+use cosmrs::rpc::endpoint::broadcast::tx_commit::Response as TxCommitResponse;
+use cosmrs::rpc::endpoint::tx::Response as TxQueryResponse;
+use cosmrs::rpc::event::TxInfo;
+use cosmrs::tendermint::abci::{self, types::ExecTxResult};
+use cosmwasm_std::{Attribute, Event};
+use prost::Message;
+use serde::Deserialize;
+use test_tube::{DecodeError, ExecuteResponse, RunnerError, RunnerExecuteResult};
+
+use super::chain::ChainError;
+
+#[cfg(feature = "grpc")]
+pub use grpc::GrpcClient;
+#[cfg(all(test, feature = "grpc"))]
+pub(crate) use grpc::RawCodec;
+
+/// The transport used by [`Chain`](super::chain::Chain) for queries, simulation and
+/// broadcasting transactions.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Transport {
+    /// Tendermint RPC via `rpc_endpoint`, using `abci_query` and `broadcast_tx_commit`.
+    #[default]
+    Rpc,
+    /// Cosmos SDK gRPC via `grpc_endpoint`. Requires the `grpc` feature.
+    Grpc,
+}
+
+/// The result of a transaction, independent of the transport that was used to broadcast it.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TxResult {
+    pub hash: String,
+    pub height: u64,
+    pub code: u32,
+    pub codespace: String,
+    pub log: String,
+    pub data: Vec<u8>,
+    pub events: Vec<Event>,
+    pub gas_wanted: u64,
+    pub gas_used: u64,
+}
+
+impl TxResult {
+    /// Returns true if the transaction was successful.
+    pub fn is_ok(&self) -> bool {
+        self.code == 0
+    }
+
+    /// Returns the result if the transaction was successful, otherwise a [`ChainError::Abci`]
+    /// with the code, codespace and log of the failed transaction.
+    pub fn into_result(self) -> Result<Self, ChainError> {
+        if self.is_ok() {
+            Ok(self)
+        } else {
+            Err(ChainError::Abci {
+                code: self.code,
+                codespace: self.codespace,
+                log: self.log,
+            })
+        }
+    }
+
+    /// Converts the result into a test-tube `ExecuteResponse`, decoding the response of the first
+    /// message in the transaction as `R`.
+    pub fn into_execute_response<R>(self) -> RunnerExecuteResult<R>
+    where
+        R: prost::Message + Default,
+    {
+        let tx_msg_data =
+            TxMsgData::decode(self.data.as_slice()).map_err(DecodeError::ProtoDecodeError)?;
+
+        let msg_data = tx_msg_data
+            .msg_responses
+            .first()
+            .ok_or(RunnerError::ExecuteError {
+                msg: self.log.clone(),
+            })?;
+
+        let data = R::decode(msg_data.value.as_slice()).map_err(DecodeError::ProtoDecodeError)?;
+
+        Ok(ExecuteResponse {
+            data,
+            raw_data: self.data,
+            events: self.events,
+            gas_info: GasInfo {
+                gas_wanted: self.gas_wanted,
+                gas_used: self.gas_used,
+            },
+        })
+    }
+}
+
+impl From<TxCommitResponse> for TxResult {
+    fn from(res: TxCommitResponse) -> Self {
+        let hash = res.hash.to_string();
+        let height = res.height.value();
+
+        // If CheckTx failed the transaction was never included in a block
+        if res.check_tx.code.is_err() {
+            return Self {
+                hash,
+                height,
+                code: res.check_tx.code.value(),
+                codespace: res.check_tx.codespace,
+                log: res.check_tx.log,
+                gas_wanted: res.check_tx.gas_wanted as u64,
+                gas_used: res.check_tx.gas_used as u64,
+                ..Default::default()
+            };
+        }
+
+        Self::from_exec_tx_result(hash, height, res.tx_result)
+    }
+}
+
+impl From<TxQueryResponse> for TxResult {
+    fn from(res: TxQueryResponse) -> Self {
+        Self::from_exec_tx_result(res.hash.to_string(), res.height.value(), res.tx_result)
+    }
+}
+
+impl TxResult {
+    fn from_exec_tx_result(hash: String, height: u64, tx_result: ExecTxResult) -> Self {
+        Self {
+            hash,
+            height,
+            code: tx_result.code.value(),
+            codespace: tx_result.codespace,
+            log: tx_result.log,
+            data: tx_result.data.to_vec(),
+            events: to_events(tx_result.events),
+            gas_wanted: tx_result.gas_wanted as u64,
+            gas_used: tx_result.gas_used as u64,
+        }
+    }
+
+    /// Returns the result of the transaction with the given hash from its `Tx` event. The event
+    /// type of `tendermint-rpc` leaves out the code, codespace and data of the result, so these
+    /// are taken from `block_result`, the result of the transaction in the block results.
+    pub(crate) fn from_tx_event(hash: String, tx_info: TxInfo, block_result: ExecTxResult) -> Self {
+        let parse_gas = |gas: Option<String>| gas.and_then(|gas| gas.parse().ok()).unwrap_or(0);
+        Self {
+            hash,
+            height: tx_info.height as u64,
+            code: block_result.code.value(),
+            codespace: block_result.codespace,
+            log: tx_info.result.log.unwrap_or_default(),
+            data: block_result.data.to_vec(),
+            events: to_events(tx_info.result.events),
+            gas_wanted: parse_gas(tx_info.result.gas_wanted),
+            gas_used: parse_gas(tx_info.result.gas_used),
+        }
+    }
+}
+
+fn to_events(events: Vec<abci::Event>) -> Vec<Event> {
+    events
+        .into_iter()
+        .map(|e| {
+            Event::new(e.kind).add_attributes(e.attributes.into_iter().map(|a| Attribute {
+                key: a.key,
+                value: a.value,
+            }))
+        })
+        .collect()
+}
+
+#[cfg(feature = "grpc")]
+mod grpc {
+    use cosmrs::proto::cosmos::base::abci::v1beta1::TxResponse;
+    use cosmwasm_std::{Attribute, Event};
+    use prost::bytes::{Buf, BufMut};
+    use tonic::codec::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder};
+    use tonic::codegen::http::uri::PathAndQuery;
+    use tonic::transport::{Channel, Endpoint};
+    use tonic::Status;
+
+    use super::TxResult;
+    use crate::rpc_runner::chain::ChainError;
+
+    /// A minimal gRPC client that sends already encoded protobuf messages to any unary gRPC
+    /// method. This lets us use the same query paths for gRPC as for `abci_query`.
+    #[derive(Clone, Debug)]
+    pub struct GrpcClient {
+        channel: Channel,
+    }
+
+    impl GrpcClient {
+        /// Creates a client for `grpc_endpoint`. The connection is established on the first call
+        /// and reused, and reconnected if it is lost, for all later calls.
+        pub fn new(grpc_endpoint: &str) -> Result<Self, tonic::transport::Error> {
+            let endpoint = Endpoint::from_shared(grpc_endpoint.to_string())?;
+            // The channel spawns its connection task on the runtime it is created on, so use the
+            // process-wide runtime that outlives the runtimes of individual calls
+            let _guard = crate::helpers::runtime().enter();
+            Ok(Self {
+                channel: endpoint.connect_lazy(),
+            })
+        }
+
+        /// Calls the unary gRPC method at `path` with the encoded request `data` and returns
+        /// the encoded response.
+        pub async fn unary_raw(&self, path: &str, data: Vec<u8>) -> Result<Vec<u8>, Status> {
+            let path = path
+                .parse::<PathAndQuery>()
+                .map_err(|e| Status::invalid_argument(e.to_string()))?;
+            let mut grpc = tonic::client::Grpc::new(self.channel.clone());
+            grpc.ready()
+                .await
+                .map_err(|e| Status::unavailable(e.to_string()))?;
+
+            Ok(grpc
+                .unary(tonic::Request::new(data), path, RawCodec)
+                .await?
+                .into_inner())
+        }
+    }
+
+    /// A codec that passes through the raw bytes of the messages.
+    #[derive(Clone, Copy, Debug, Default)]
+    pub(crate) struct RawCodec;
+
+    impl Codec for RawCodec {
+        type Encode = Vec<u8>;
+        type Decode = Vec<u8>;
+        type Encoder = RawCodec;
+        type Decoder = RawCodec;
+
+        fn encoder(&mut self) -> Self::Encoder {
+            RawCodec
+        }
+
+        fn decoder(&mut self) -> Self::Decoder {
+            RawCodec
+        }
+    }
+
+    impl Encoder for RawCodec {
+        type Item = Vec<u8>;
+        type Error = Status;
+
+        fn encode(&mut self, item: Self::Item, dst: &mut EncodeBuf<'_>) -> Result<(), Status> {
+            dst.put_slice(&item);
+            Ok(())
+        }
+    }
+
+    impl Decoder for RawCodec {
+        type Item = Vec<u8>;
+        type Error = Status;
+
+        fn decode(&mut self, src: &mut DecodeBuf<'_>) -> Result<Option<Self::Item>, Status> {
+            Ok(Some(src.copy_to_bytes(src.remaining()).to_vec()))
+        }
+    }
+
+    impl TryFrom<TxResponse> for TxResult {
+        type Error = ChainError;
+
+        fn try_from(res: TxResponse) -> Result<Self, Self::Error> {
+            Ok(Self {
+                hash: res.txhash,
+                height: res.height as u64,
+                code: res.code,
+                codespace: res.codespace,
+                log: res.raw_log,
+                data: hex::decode(&res.data).map_err(|e| ChainError::Generic(e.to_string()))?,
+                events: res
+                    .events
+                    .into_iter()
+                    .map(|e| {
+                        Event::new(e.r#type).add_attributes(e.attributes.into_iter().map(|a| {
+                            Attribute {
+                                key: String::from_utf8_lossy(&a.key).to_string(),
+                                value: String::from_utf8_lossy(&a.value).to_string(),
+                            }
+                        }))
+                    })
+                    .collect(),
+                gas_wanted: res.gas_wanted as u64,
+                gas_used: res.gas_used as u64,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmrs::proto::cosmos::bank::v1beta1::MsgSendResponse;
+    use cosmrs::rpc::endpoint::broadcast::tx_commit::Response as TxCommitResponse;
+    use cosmrs::tendermint::abci::{self, Code};
+    use cosmrs::tendermint::Hash;
+
+    use super::*;
+
+    fn tx_msg_data() -> Vec<u8> {
+        TxMsgData {
+            msg_responses: vec![cosmrs::Any {
+                type_url: "/cosmos.bank.v1beta1.MsgSendResponse".to_string(),
+                value: MsgSendResponse {}.encode_to_vec(),
+            }],
+            ..Default::default()
+        }
+        .encode_to_vec()
+    }
+
+    fn tx_commit_response(check_tx_code: u32, tx_code: u32) -> TxCommitResponse {
+        TxCommitResponse {
+            check_tx: abci::response::CheckTx {
+                code: Code::from(check_tx_code),
+                log: "check tx log".to_string(),
+                ..Default::default()
+            },
+            tx_result: abci::types::ExecTxResult {
+                code: Code::from(tx_code),
+                data: tx_msg_data().into(),
+                log: "tx log".to_string(),
+                gas_wanted: 200,
+                gas_used: 100,
+                events: vec![abci::Event::new(
+                    "transfer",
+                    vec![abci::EventAttribute {
+                        key: "amount".to_string(),
+                        value: "100uosmo".to_string(),
+                        index: true,
+                    }],
+                )],
+                ..Default::default()
+            },
+            hash: Hash::None,
+            height: 5u32.into(),
+        }
+    }
+
+    #[test]
+    fn transport_deserialize() {
+        #[derive(Deserialize)]
+        struct Config {
+            #[serde(default)]
+            transport: Transport,
+        }
+
+        let config: Config = serde_json::from_str("{}").unwrap();
+        assert_eq!(config.transport, Transport::Rpc);
+        let config: Config = serde_json::from_str(r#"{"transport": "grpc"}"#).unwrap();
+        assert_eq!(config.transport, Transport::Grpc);
+    }
+
+    #[test]
+    fn tx_result_from_tx_commit_response() {
+        let tx_result = TxResult::from(tx_commit_response(0, 0));
+        assert!(tx_result.is_ok());
+        assert_eq!(tx_result.height, 5);
+        assert_eq!(
+            tx_result.events,
+            vec![Event::new("transfer").add_attribute("amount", "100uosmo")]
+        );
+
+        let res = tx_result
+            .into_execute_response::<MsgSendResponse>()
+            .unwrap();
+        assert_eq!(res.gas_info.gas_used, 100);
+        assert_eq!(res.raw_data, tx_msg_data());
+    }
+
+    #[test]
+    fn tx_result_from_tx_response() {
+        let commit = tx_commit_response(0, 0);
+        let tx_result = TxResult::from(TxQueryResponse {
+            hash: Hash::None,
+            height: 7u32.into(),
+            index: 0,
+            tx_result: commit.tx_result,
+            tx: vec![],
+            proof: None,
+        });
+
+        assert!(tx_result.is_ok());
+        assert_eq!(tx_result.height, 7);
+        assert_eq!(tx_result.log, "tx log");
+        assert_eq!(tx_result.data, tx_msg_data());
+    }
+
+    #[test]
+    fn tx_result_from_tx_event() {
+        use cosmrs::rpc::event::TxResult as EventTxResult;
+
+        let commit = tx_commit_response(0, 5);
+        let tx_info = TxInfo {
+            height: 9,
+            index: Some(0),
+            tx: vec![],
+            result: EventTxResult {
+                log: Some("event log".to_string()),
+                gas_wanted: Some("200".to_string()),
+                gas_used: Some("100".to_string()),
+                events: commit.tx_result.events.clone(),
+            },
+        };
+
+        let tx_result = TxResult::from_tx_event("ABCD".to_string(), tx_info, commit.tx_result);
+        assert!(!tx_result.is_ok());
+        assert_eq!(tx_result.code, 5);
+        assert_eq!(tx_result.hash, "ABCD");
+        assert_eq!(tx_result.height, 9);
+        assert_eq!(tx_result.log, "event log");
+        assert_eq!(tx_result.data, tx_msg_data());
+        assert_eq!((tx_result.gas_wanted, tx_result.gas_used), (200, 100));
+        assert_eq!(
+            tx_result.events,
+            vec![Event::new("transfer").add_attribute("amount", "100uosmo")]
+        );
+    }
+
+    #[test]
+    fn tx_result_from_failed_check_tx() {
+        let tx_result = TxResult::from(tx_commit_response(5, 0));
+        assert!(!tx_result.is_ok());
+        assert_eq!(tx_result.code, 5);
+        assert_eq!(tx_result.log, "check tx log");
+        assert!(tx_result.events.is_empty());
+
+        match tx_result.into_result().unwrap_err() {
+            ChainError::Abci { code, log, .. } => {
+                assert_eq!(code, 5);
+                assert_eq!(log, "check tx log");
+            }
+            err => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[cfg(feature = "grpc")]
+    #[test]
+    fn tx_result_from_grpc_tx_response() {
+        use cosmrs::proto::cosmos::base::abci::v1beta1::TxResponse;
+        use cosmrs::proto::tendermint::v0_34::abci::{
+            Event as ProtoEvent, EventAttribute as ProtoEventAttribute,
+        };
+
+        let tx_result = TxResult::try_from(TxResponse {
+            height: 5,
+            txhash: "ABCD".to_string(),
+            data: hex::encode(tx_msg_data()),
+            gas_used: 100,
+            events: vec![ProtoEvent {
+                r#type: "transfer".to_string(),
+                attributes: vec![ProtoEventAttribute {
+                    key: "amount".as_bytes().to_vec().into(),
+                    value: "100uosmo".as_bytes().to_vec().into(),
+                    index: true,
+                }],
+            }],
+            ..Default::default()
+        })
+        .unwrap();
+
+        assert!(tx_result.is_ok());
+        assert_eq!(tx_result.hash, "ABCD");
+        assert_eq!(tx_result.data, tx_msg_data());
+        assert_eq!(
+            tx_result.events,
+            vec![Event::new("transfer").add_attribute("amount", "100uosmo")]
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use std::{
+    collections::BTreeMap,
     fmt::{Display, Formatter},
     str::FromStr,
 };
@@ -19,6 +20,8 @@ use osmosis_test_tube::OsmosisTestApp;
 /// An enum with concrete types implementing the Runner trait. We specify these here because the
 /// Runner trait is not object safe, and we want to be able to run tests against different types of
 /// runners.
+// Runners are created once per test, so their size doesn't matter
+#[allow(clippy::large_enum_variant)]
 #[derive(strum::EnumVariantNames)]
 #[strum(serialize_all = "kebab_case")]
 pub enum OwnedTestRunner<'a> {
@@ -35,6 +38,9 @@ pub enum OwnedTestRunner<'a> {
 /// A version of TestRunner which borrows the runner instead of owning it. This is useful for
 /// passing a TestRunner to a function which needs to own it, but we don't want to give up ownership
 /// of the runner.
+///
+/// Dropping a `TestRunner` does not drop the borrowed runner, so an `RpcRunner` with
+/// `sweep_on_drop` only sweeps its accounts once the runner itself is dropped.
 pub enum TestRunner<'a> {
     /// Needed to keep lifetime when rpc-runner and multitest features are off
     PhantomData(&'a ()),
@@ -174,7 +180,17 @@ impl<'a> Runner<'a> for TestRunner<'a> {
         match self {
             Self::PhantomData(_) => unimplemented!(),
             #[cfg(feature = "osmosis-test-tube")]
-            Self::OsmosisTestApp(app) => app.execute_multiple(msgs, signer),
+            Self::OsmosisTestApp(app) => {
+                #[cfg(feature = "wasm-inspect")]
+                for (msg, type_url) in msgs {
+                    crate::artifact::inspect::check_entry_point(
+                        *app,
+                        type_url,
+                        &prost::Message::encode_to_vec(msg),
+                    );
+                }
+                app.execute_multiple(msgs, signer)
+            }
             #[cfg(feature = "rpc-runner")]
             Self::RpcRunner(runner) => runner.execute_multiple(msgs, signer),
             #[cfg(feature = "multi-test")]
@@ -193,7 +209,13 @@ impl<'a> Runner<'a> for TestRunner<'a> {
         match self {
             Self::PhantomData(_) => unimplemented!(),
             #[cfg(feature = "osmosis-test-tube")]
-            Self::OsmosisTestApp(app) => app.execute_multiple_raw(msgs, signer),
+            Self::OsmosisTestApp(app) => {
+                #[cfg(feature = "wasm-inspect")]
+                for msg in &msgs {
+                    crate::artifact::inspect::check_entry_point(*app, &msg.type_url, &msg.value);
+                }
+                app.execute_multiple_raw(msgs, signer)
+            }
             #[cfg(feature = "rpc-runner")]
             Self::RpcRunner(runner) => runner.execute_multiple_raw(msgs, signer),
             #[cfg(feature = "multi-test")]
@@ -281,6 +303,30 @@ impl<'a> CwItRunner<'a> for TestRunner<'a> {
         }
     }
 
+    fn init_default_accounts(&self) -> Result<Vec<SigningAccount>, anyhow::Error> {
+        match self {
+            Self::PhantomData(_) => unimplemented!(),
+            #[cfg(feature = "osmosis-test-tube")]
+            Self::OsmosisTestApp(app) => app.init_default_accounts(),
+            #[cfg(feature = "rpc-runner")]
+            Self::RpcRunner(runner) => runner.init_default_accounts(),
+            #[cfg(feature = "multi-test")]
+            Self::MultiTest(runner) => runner.init_default_accounts(),
+        }
+    }
+
+    fn init_default_account(&self) -> Result<SigningAccount, anyhow::Error> {
+        match self {
+            Self::PhantomData(_) => unimplemented!(),
+            #[cfg(feature = "osmosis-test-tube")]
+            Self::OsmosisTestApp(app) => app.init_default_account(),
+            #[cfg(feature = "rpc-runner")]
+            Self::RpcRunner(runner) => runner.init_default_account(),
+            #[cfg(feature = "multi-test")]
+            Self::MultiTest(runner) => runner.init_default_account(),
+        }
+    }
+
     fn init_account(
         &self,
         initial_balance: &[cosmwasm_std::Coin],
@@ -340,6 +386,61 @@ impl<'a> CwItRunner<'a> for TestRunner<'a> {
             Self::MultiTest(runner) => runner.query_block_time_nanos(),
         }
     }
+
+    fn query_wasm_raw(
+        &self,
+        contract_addr: &str,
+        key: &[u8],
+    ) -> Result<Option<Vec<u8>>, anyhow::Error> {
+        match self {
+            Self::PhantomData(_) => unimplemented!(),
+            #[cfg(feature = "osmosis-test-tube")]
+            Self::OsmosisTestApp(app) => app.query_wasm_raw(contract_addr, key),
+            #[cfg(feature = "rpc-runner")]
+            Self::RpcRunner(runner) => runner.query_wasm_raw(contract_addr, key),
+            #[cfg(feature = "multi-test")]
+            Self::MultiTest(runner) => runner.query_wasm_raw(contract_addr, key),
+        }
+    }
+
+    fn query_wasm_raw_all(
+        &self,
+        contract_addr: &str,
+    ) -> Result<BTreeMap<Vec<u8>, Vec<u8>>, anyhow::Error> {
+        match self {
+            Self::PhantomData(_) => unimplemented!(),
+            #[cfg(feature = "osmosis-test-tube")]
+            Self::OsmosisTestApp(app) => app.query_wasm_raw_all(contract_addr),
+            #[cfg(feature = "rpc-runner")]
+            Self::RpcRunner(runner) => runner.query_wasm_raw_all(contract_addr),
+            #[cfg(feature = "multi-test")]
+            Self::MultiTest(runner) => runner.query_wasm_raw_all(contract_addr),
+        }
+    }
+
+    fn query_code_checksum(&self, code_id: u64) -> Result<Vec<u8>, anyhow::Error> {
+        match self {
+            Self::PhantomData(_) => unimplemented!(),
+            #[cfg(feature = "osmosis-test-tube")]
+            Self::OsmosisTestApp(app) => app.query_code_checksum(code_id),
+            #[cfg(feature = "rpc-runner")]
+            Self::RpcRunner(runner) => runner.query_code_checksum(code_id),
+            #[cfg(feature = "multi-test")]
+            Self::MultiTest(runner) => runner.query_code_checksum(code_id),
+        }
+    }
+
+    fn query_contract_label(&self, contract_addr: &str) -> Result<String, anyhow::Error> {
+        match self {
+            Self::PhantomData(_) => unimplemented!(),
+            #[cfg(feature = "osmosis-test-tube")]
+            Self::OsmosisTestApp(app) => app.query_contract_label(contract_addr),
+            #[cfg(feature = "rpc-runner")]
+            Self::RpcRunner(runner) => runner.query_contract_label(contract_addr),
+            #[cfg(feature = "multi-test")]
+            Self::MultiTest(runner) => runner.query_contract_label(contract_addr),
+        }
+    }
 }
 impl CwItRunner<'_> for OwnedTestRunner<'_> {
     fn store_code(
@@ -350,6 +451,14 @@ impl CwItRunner<'_> for OwnedTestRunner<'_> {
         self.as_ref().store_code(code, signer)
     }
 
+    fn init_default_accounts(&self) -> Result<Vec<SigningAccount>, anyhow::Error> {
+        self.as_ref().init_default_accounts()
+    }
+
+    fn init_default_account(&self) -> Result<SigningAccount, anyhow::Error> {
+        self.as_ref().init_default_account()
+    }
+
     fn init_account(
         &self,
         initial_balance: &[cosmwasm_std::Coin],
@@ -372,6 +481,29 @@ impl CwItRunner<'_> for OwnedTestRunner<'_> {
     fn query_block_time_nanos(&self) -> u64 {
         self.as_ref().query_block_time_nanos()
     }
+
+    fn query_wasm_raw(
+        &self,
+        contract_addr: &str,
+        key: &[u8],
+    ) -> Result<Option<Vec<u8>>, anyhow::Error> {
+        self.as_ref().query_wasm_raw(contract_addr, key)
+    }
+
+    fn query_wasm_raw_all(
+        &self,
+        contract_addr: &str,
+    ) -> Result<BTreeMap<Vec<u8>, Vec<u8>>, anyhow::Error> {
+        self.as_ref().query_wasm_raw_all(contract_addr)
+    }
+
+    fn query_code_checksum(&self, code_id: u64) -> Result<Vec<u8>, anyhow::Error> {
+        self.as_ref().query_code_checksum(code_id)
+    }
+
+    fn query_contract_label(&self, contract_addr: &str) -> Result<String, anyhow::Error> {
+        self.as_ref().query_contract_label(contract_addr)
+    }
 }
 
 #[cfg(test)]
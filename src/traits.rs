@@ -1,8 +1,21 @@
+use std::collections::BTreeMap;
+use std::future::{ready, Future};
+
 use anyhow::Error;
 use cosmwasm_std::coin;
 use cosmwasm_std::Coin;
+use osmosis_std::types::cosmos::base::query::v1beta1::{PageRequest, PageResponse};
+use osmosis_std::types::cosmwasm::wasm::v1::{
+    QueryAllContractStateRequest, QueryAllContractStateResponse, QueryCodeRequest,
+    QueryCodeResponse, QueryContractInfoRequest, QueryContractInfoResponse,
+    QueryRawContractStateRequest, QueryRawContractStateResponse,
+};
+use serde::de::DeserializeOwned;
 use test_tube::Runner;
+use test_tube::RunnerExecuteResult;
+use test_tube::RunnerResult;
 use test_tube::SigningAccount;
+use test_tube::{EncodeError, RunnerError};
 
 use crate::artifact::ContractType;
 
@@ -60,4 +73,272 @@ pub trait CwItRunner<'a>: Runner<'a> {
 
     /// Returns the current block time in nanoseconds.
     fn query_block_time_nanos(&self) -> u64;
+
+    /// Returns the value stored under `key` in the raw storage of the contract at
+    /// `contract_addr`, or `None` if the key is not set.
+    fn query_wasm_raw(&self, contract_addr: &str, key: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        let res: QueryRawContractStateResponse = self.query(
+            "/cosmwasm.wasm.v1.Query/RawContractState",
+            &QueryRawContractStateRequest {
+                address: contract_addr.to_string(),
+                query_data: key.to_vec(),
+            },
+        )?;
+        Ok(Some(res.data).filter(|data| !data.is_empty()))
+    }
+
+    /// Returns all key-value pairs in the raw storage of the contract at `contract_addr`.
+    fn query_wasm_raw_all(&self, contract_addr: &str) -> Result<BTreeMap<Vec<u8>, Vec<u8>>, Error> {
+        let mut state = BTreeMap::new();
+        let mut next_key = vec![];
+        loop {
+            let res: QueryAllContractStateResponse = self.query(
+                "/cosmwasm.wasm.v1.Query/AllContractState",
+                &QueryAllContractStateRequest {
+                    address: contract_addr.to_string(),
+                    pagination: Some(PageRequest {
+                        key: next_key,
+                        offset: 0,
+                        limit: 100,
+                        count_total: false,
+                        reverse: false,
+                    }),
+                },
+            )?;
+            state.extend(res.models.into_iter().map(|model| (model.key, model.value)));
+            match res.pagination {
+                Some(PageResponse {
+                    next_key: Some(key),
+                    ..
+                }) if !key.is_empty() => next_key = key,
+                _ => return Ok(state),
+            }
+        }
+    }
+
+    /// Returns the sha256 checksum of the code `code_id`, which is used to derive the addresses
+    /// of contracts instantiated with `MsgInstantiateContract2`.
+    fn query_code_checksum(&self, code_id: u64) -> Result<Vec<u8>, Error> {
+        let res: QueryCodeResponse = self.query(
+            "/cosmwasm.wasm.v1.Query/Code",
+            &QueryCodeRequest { code_id },
+        )?;
+        res.code_info
+            .map(|info| info.data_hash)
+            .ok_or_else(|| anyhow::anyhow!("code {} not found", code_id))
+    }
+
+    /// Returns the label the contract at `contract_addr` was instantiated with.
+    fn query_contract_label(&self, contract_addr: &str) -> Result<String, Error> {
+        let res: QueryContractInfoResponse = self.query(
+            "/cosmwasm.wasm.v1.Query/ContractInfo",
+            &QueryContractInfoRequest {
+                address: contract_addr.to_string(),
+            },
+        )?;
+        res.contract_info
+            .map(|info| info.label)
+            .ok_or_else(|| anyhow::anyhow!("contract {} not found", contract_addr))
+    }
+}
+
+/// The mode used to sign a transaction.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SignMode {
+    /// `SIGN_MODE_DIRECT`, signing the protobuf encoded transaction.
+    #[default]
+    Direct,
+    /// `SIGN_MODE_LEGACY_AMINO_JSON`, signing the amino JSON encoded transaction. Only supports
+    /// a subset of messages and no extension options.
+    LegacyAminoJson,
+}
+
+/// Options for the envelope of a transaction, i.e. everything in the transaction except the
+/// messages and the fee amount.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TxOptions {
+    /// The memo of the transaction.
+    pub memo: String,
+    /// The block height after which the transaction will not be processed. 0 means no timeout.
+    pub timeout_height: u64,
+    /// Address of the account that pays the fee. The chain requires the fee payer to sign the
+    /// transaction, so this must be the signer. Use `fee_granter` to pay the fee from another
+    /// account's fee allowance.
+    pub fee_payer: Option<String>,
+    /// Address of the account that granted the signer a fee allowance to pay the fee with.
+    pub fee_granter: Option<String>,
+    /// Extension options that the chain can ignore if it does not know them.
+    pub non_critical_extension_options: Vec<cosmrs::Any>,
+    /// The mode used to sign the transaction.
+    pub sign_mode: SignMode,
+}
+
+impl TxOptions {
+    pub fn memo(mut self, memo: impl Into<String>) -> Self {
+        self.memo = memo.into();
+        self
+    }
+
+    pub fn timeout_height(mut self, timeout_height: u64) -> Self {
+        self.timeout_height = timeout_height;
+        self
+    }
+
+    pub fn fee_payer(mut self, fee_payer: impl Into<String>) -> Self {
+        self.fee_payer = Some(fee_payer.into());
+        self
+    }
+
+    pub fn fee_granter(mut self, fee_granter: impl Into<String>) -> Self {
+        self.fee_granter = Some(fee_granter.into());
+        self
+    }
+
+    pub fn sign_mode(mut self, sign_mode: SignMode) -> Self {
+        self.sign_mode = sign_mode;
+        self
+    }
+
+    pub fn non_critical_extension_option(mut self, option: cosmrs::Any) -> Self {
+        self.non_critical_extension_options.push(option);
+        self
+    }
+}
+
+/// Extension of [`CwItRunner`] for runners that let you configure the envelope of the
+/// transactions they send. Options can be passed per call, or set per signer with
+/// [`set_tx_options`](TxOptionsRunner::set_tx_options), in which case they are used for all
+/// transactions signed by that signer, including the ones sent through the `Runner` trait.
+pub trait TxOptionsRunner<'a>: CwItRunner<'a> {
+    /// Executes the encoded messages in a single transaction with the given options.
+    fn execute_multiple_raw_with_options<R>(
+        &self,
+        msgs: Vec<cosmrs::Any>,
+        signer: &SigningAccount,
+        options: &TxOptions,
+    ) -> RunnerExecuteResult<R>
+    where
+        R: prost::Message + Default;
+
+    /// Sets the options to use for all transactions signed by `signer`.
+    fn set_tx_options(&self, signer: &SigningAccount, options: TxOptions);
+
+    /// Removes the options set for `signer` with [`set_tx_options`](TxOptionsRunner::set_tx_options).
+    fn clear_tx_options(&self, signer: &SigningAccount);
+
+    /// Executes the messages in a single transaction with the given options.
+    fn execute_multiple_with_options<M, R>(
+        &self,
+        msgs: &[(M, &str)],
+        signer: &SigningAccount,
+        options: &TxOptions,
+    ) -> RunnerExecuteResult<R>
+    where
+        M: prost::Message,
+        R: prost::Message + Default,
+    {
+        let encoded_msgs = msgs
+            .iter()
+            .map(|(msg, type_url)| {
+                let mut buf = Vec::new();
+                M::encode(msg, &mut buf).map_err(EncodeError::ProtoEncodeError)?;
+
+                Ok(cosmrs::Any {
+                    type_url: type_url.to_string(),
+                    value: buf,
+                })
+            })
+            .collect::<Result<Vec<cosmrs::Any>, RunnerError>>()?;
+
+        self.execute_multiple_raw_with_options(encoded_msgs, signer, options)
+    }
+}
+
+/// Async version of the main methods of [`CwItRunner`], for testing async code without
+/// blocking the executor or nesting runtimes.
+///
+/// [`RpcRunner`](crate::rpc_runner::RpcRunner) implements it natively. In-process runners
+/// implement [`BlockingRunner`] and get this trait through a blanket implementation which calls
+/// their blocking methods and returns an already completed future.
+///
+/// The method names are the same as in [`Runner`] and [`CwItRunner`], so if both traits are in
+/// scope, call them as e.g. `AsyncCwItRunner::query(&runner, path, &msg).await`.
+pub trait AsyncCwItRunner<'a> {
+    /// Executes the encoded messages in a single transaction.
+    fn execute_multiple_raw<R>(
+        &self,
+        msgs: Vec<cosmrs::Any>,
+        signer: &SigningAccount,
+    ) -> impl Future<Output = RunnerExecuteResult<R>> + Send
+    where
+        R: prost::Message + Default;
+
+    /// Queries the chain with the encoded query at the given gRPC path.
+    fn query<Q, R>(&self, path: &str, query: &Q) -> impl Future<Output = RunnerResult<R>> + Send
+    where
+        Q: prost::Message,
+        R: prost::Message + DeserializeOwned + Default;
+
+    /// Store the code on the chain and return the code ID.
+    fn store_code(
+        &self,
+        code: ContractType,
+        signer: &SigningAccount,
+    ) -> impl Future<Output = Result<u64, Error>> + Send;
+
+    /// Initialize a single account with the given balance.
+    fn init_account(
+        &self,
+        initial_balance: &[Coin],
+    ) -> impl Future<Output = Result<SigningAccount, Error>> + Send;
+
+    /// Increases the time of the blockchain by the given number of seconds.
+    fn increase_time(&self, seconds: u64) -> impl Future<Output = Result<(), Error>> + Send;
+}
+
+/// Marker for in-process runners whose [`CwItRunner`] methods don't do any I/O. These implement
+/// [`AsyncCwItRunner`] by calling the blocking methods.
+pub trait BlockingRunner {}
+
+impl<'a, T> AsyncCwItRunner<'a> for T
+where
+    T: CwItRunner<'a> + BlockingRunner,
+{
+    fn execute_multiple_raw<R>(
+        &self,
+        msgs: Vec<cosmrs::Any>,
+        signer: &SigningAccount,
+    ) -> impl Future<Output = RunnerExecuteResult<R>> + Send
+    where
+        R: prost::Message + Default,
+    {
+        ready(Runner::execute_multiple_raw(self, msgs, signer))
+    }
+
+    fn query<Q, R>(&self, path: &str, query: &Q) -> impl Future<Output = RunnerResult<R>> + Send
+    where
+        Q: prost::Message,
+        R: prost::Message + DeserializeOwned + Default,
+    {
+        ready(Runner::query(self, path, query))
+    }
+
+    fn store_code(
+        &self,
+        code: ContractType,
+        signer: &SigningAccount,
+    ) -> impl Future<Output = Result<u64, Error>> + Send {
+        ready(CwItRunner::store_code(self, code, signer))
+    }
+
+    fn init_account(
+        &self,
+        initial_balance: &[Coin],
+    ) -> impl Future<Output = Result<SigningAccount, Error>> + Send {
+        ready(CwItRunner::init_account(self, initial_balance))
+    }
+
+    fn increase_time(&self, seconds: u64) -> impl Future<Output = Result<(), Error>> + Send {
+        ready(CwItRunner::increase_time(self, seconds))
+    }
 }